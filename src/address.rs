@@ -0,0 +1,87 @@
+//! Offline inspection of addresses, and the scripts the scripthash endpoints are queried for.
+
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::DisplayHex;
+use bitcoin::hex::FromHex;
+use bitcoin::{Address, Network};
+use serde::Serialize;
+
+use crate::endpoints::NETWORKS;
+use crate::error;
+
+/// What an address encodes.
+#[derive(Debug, Serialize)]
+pub struct AddressInfo {
+    /// The address as given.
+    pub address: String,
+    /// Networks the address is valid for. Testnets and signet share an encoding.
+    pub networks: Vec<Network>,
+    /// The script type, such as `p2wpkh`, or `unknown` for future witness versions.
+    pub kind: String,
+    /// The scriptPubKey, hex encoded.
+    pub script_pubkey: String,
+    /// The hash esplora's `/scripthash` endpoints take: the SHA256 of the scriptPubKey.
+    pub scripthash: String,
+    /// The same hash reversed, as the Electrum protocol takes it.
+    pub electrum_scripthash: String,
+}
+
+/// Decode `address` without contacting the backend.
+pub fn inspect(address: &Address<NetworkUnchecked>) -> AddressInfo {
+    let networks = NETWORKS
+        .into_iter()
+        .filter(|n| address.is_valid_for_network(*n))
+        .collect();
+    let address = address.clone().assume_checked();
+    let script = address.script_pubkey();
+    let hash = sha256::Hash::hash(script.as_bytes());
+    let mut reversed = hash.to_byte_array();
+    reversed.reverse();
+    AddressInfo {
+        address: address.to_string(),
+        networks,
+        kind: address.address_type().map_or("unknown".to_string(), |t| t.to_string()),
+        script_pubkey: script.to_hex_string(),
+        scripthash: format!("{:x}", hash),
+        electrum_scripthash: reversed.to_lower_hex_string(),
+    }
+}
+
+/// Render `info` for the terminal.
+pub fn report(info: &AddressInfo) -> String {
+    let networks: Vec<String> = info.networks.iter().map(|n| n.to_string()).collect();
+    format!(
+        "address: {}\nnetworks: {}\ntype: {}\nscriptPubKey: {}\nscripthash: {}\nelectrum scripthash: {}\n",
+        info.address,
+        networks.join(", "),
+        info.kind,
+        info.script_pubkey,
+        info.scripthash,
+        info.electrum_scripthash
+    )
+}
+
+/// The scripthash to query for `target`: an address, a scriptPubKey in hex or a scripthash.
+///
+/// Unless `script` or `scripthash` says which, hex of 32 bytes is taken to be a scripthash and
+/// any other hex to be a script.
+pub fn scripthash(target: &str, script: bool, scripthash: bool) -> anyhow::Result<sha256::Hash> {
+    if !script && !scripthash {
+        if let Ok(address) = target.parse::<Address<NetworkUnchecked>>() {
+            let script = address.assume_checked().script_pubkey();
+            return Ok(sha256::Hash::hash(script.as_bytes()));
+        }
+    }
+    let bytes = Vec::from_hex(target).map_err(|_| {
+        error::invalid_input(format!(
+            "`{}` is neither an address, a script nor a scripthash",
+            target
+        ))
+    })?;
+    if scripthash || (!script && bytes.len() == 32) {
+        return sha256::Hash::from_slice(&bytes)
+            .map_err(|_| error::invalid_input("a scripthash is 32 bytes"));
+    }
+    Ok(sha256::Hash::hash(&bytes))
+}
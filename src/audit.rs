@@ -0,0 +1,133 @@
+//! Audits of mined blocks against the template the backend expected, from the mempool.space
+//! `/v1/block/:hash/audit-summary` endpoint. Other backends don't audit blocks.
+
+use std::collections::HashSet;
+
+use bitcoin::{BlockHash, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::{self, Feature};
+use crate::client::Client;
+use crate::error;
+use crate::oob::OutOfBand;
+
+/// A block compared with the template built from the backend's mempool.
+#[derive(Debug, Serialize)]
+pub struct Audit {
+    /// The block hash.
+    pub hash: BlockHash,
+    /// The block height.
+    pub height: u32,
+    /// Share of the template's transactions the block included, in percent, as reported by
+    /// the backend.
+    pub match_rate: Option<f64>,
+    /// Share of the template's transactions that were mined or only missed because they
+    /// arrived too late to propagate, in percent.
+    pub health: Option<f64>,
+    /// Fees the template would have collected, in satoshis.
+    pub expected_fees: Option<u64>,
+    /// Number of transactions in the template.
+    pub template_txs: usize,
+    /// Transactions mined without being in the template.
+    pub added: Vec<Txid>,
+    /// Transactions in the template that weren't mined.
+    pub missing: Vec<Txid>,
+    /// Of the missing transactions, those seen too recently to count against the miner.
+    pub fresh: Vec<Txid>,
+    /// Transactions that paid far below the block's feerate floor, if they were listed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_of_band: Option<Vec<OutOfBand>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuditSummary {
+    id: BlockHash,
+    height: u32,
+    match_rate: Option<f64>,
+    expected_fees: Option<u64>,
+    #[serde(default)]
+    template: Vec<TemplateTx>,
+    #[serde(default)]
+    added_txs: Vec<Txid>,
+    #[serde(default)]
+    missing_txs: Vec<Txid>,
+    #[serde(default)]
+    fresh_txs: Vec<Txid>,
+}
+
+#[derive(Deserialize)]
+struct TemplateTx {
+    txid: Txid,
+}
+
+/// Fetch the audit of the block `hash`.
+pub async fn audit(client: &Client, hash: &BlockHash) -> anyhow::Result<Audit> {
+    capabilities::require(client, Feature::BlockExtras).await?;
+    let summary: AuditSummary = client
+        .get_opt_json(&format!("/v1/block/{}/audit-summary", hash))
+        .await?
+        .ok_or_else(|| error::not_found(format!("audit of block {}", hash)))?;
+
+    let template: HashSet<Txid> = summary.template.iter().map(|tx| tx.txid).collect();
+    let missing: HashSet<&Txid> = summary.missing_txs.iter().collect();
+    let fresh: Vec<Txid> = summary
+        .fresh_txs
+        .into_iter()
+        .filter(|txid| missing.contains(txid))
+        .collect();
+    let missed = (summary.missing_txs.len() - fresh.len()).min(template.len());
+    let health = (!template.is_empty())
+        .then(|| (template.len() - missed) as f64 / template.len() as f64 * 100.0);
+
+    Ok(Audit {
+        hash: summary.id,
+        height: summary.height,
+        match_rate: summary.match_rate,
+        health,
+        expected_fees: summary.expected_fees,
+        template_txs: template.len(),
+        added: summary.added_txs,
+        missing: summary.missing_txs,
+        fresh,
+        out_of_band: None,
+    })
+}
+
+/// Render `audit` for the terminal.
+pub fn report(audit: &Audit) -> String {
+    let percent = |p: Option<f64>| p.map_or("-".to_string(), |p| format!("{:.2}%", p));
+    let mut out = String::new();
+    out.push_str(&format!("block {} at height {}\n", audit.hash, audit.height));
+    out.push_str(&format!("match rate: {}\n", percent(audit.match_rate)));
+    out.push_str(&format!("health: {}\n", percent(audit.health)));
+    if let Some(fees) = audit.expected_fees {
+        out.push_str(&format!("expected fees: {} sat\n", fees));
+    }
+    out.push_str(&format!("template: {} transactions\n", audit.template_txs));
+
+    out.push_str(&format!("\nadded ({}):\n", audit.added.len()));
+    for txid in &audit.added {
+        out.push_str(&format!("  {}\n", txid));
+    }
+    out.push_str(&format!("\nmissing ({}):\n", audit.missing.len()));
+    for txid in &audit.missing {
+        let note = if audit.fresh.contains(txid) {
+            "  (recently seen)"
+        } else {
+            ""
+        };
+        out.push_str(&format!("  {}{}\n", txid, note));
+    }
+    if let Some(flagged) = &audit.out_of_band {
+        out.push_str(&format!("\nlikely out of band ({}):\n", flagged.len()));
+        for tx in flagged {
+            let children = match tx.children {
+                0 => String::new(),
+                n => format!(", {:.2} sat/vB with {} child(ren)", tx.effective, n),
+            };
+            out.push_str(&format!("  {}  {:.2} sat/vB{}\n", tx.txid, tx.feerate, children));
+        }
+    }
+    out
+}
@@ -0,0 +1,164 @@
+//! Batch requests over lists of items read from a file.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, Transaction, Txid};
+use esplora_client::Error;
+use futures::{future, StreamExt, TryStreamExt};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::progress::Progress;
+
+/// Read a list of txids from `path`, one per line. Blank lines are skipped.
+pub fn read_txids(path: &Path) -> anyhow::Result<Vec<Txid>> {
+    read_lines(path)?
+        .into_iter()
+        .map(|(n, line)| {
+            line.parse()
+                .with_context(|| format!("{}:{}: invalid txid", path.display(), n))
+        })
+        .collect()
+}
+
+/// Read the non-blank lines of `path`, trimmed, with their line numbers.
+pub fn read_lines(path: &Path) -> anyhow::Result<Vec<(usize, String)>> {
+    let s =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line.trim().to_string()))
+        .collect())
+}
+
+/// Fetch the transactions `txids` with up to `jobs` requests in flight, returning them in the
+/// order given.
+///
+/// Once interrupted no more requests are made, and the transactions fetched so far are returned.
+pub async fn get_txs(
+    client: &Client,
+    txids: &[Txid],
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<Vec<Transaction>> {
+    progress.set_total(txids.len() as u64);
+    let txs = futures::stream::iter(txids)
+        .take_while(|_| future::ready(!Progress::is_interrupted()))
+        .map(|txid| async move {
+            let tx = client.get_tx(txid).await?;
+            progress.inc(1);
+            tx.ok_or_else(|| error::not_found(format!("transaction {}", txid)))
+        })
+        .buffered(jobs.max(1))
+        .try_collect()
+        .await?;
+    progress.finish();
+    Ok(txs)
+}
+
+/// The balance of an address, in satoshis.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Balance {
+    /// Balance of the confirmed transactions.
+    pub confirmed: i64,
+    /// Change to the balance from mempool transactions.
+    pub unconfirmed: i64,
+}
+
+/// Fetch the balance of each of `addresses` with up to `jobs` requests in flight, returning
+/// them in the order given.
+///
+/// A failure only fails the balance of its own address.
+pub async fn get_balances(
+    client: &Client,
+    addresses: &[String],
+    jobs: usize,
+    progress: &Progress,
+) -> Vec<anyhow::Result<Balance>> {
+    progress.set_total(addresses.len() as u64);
+    let balances = futures::stream::iter(addresses)
+        .map(|address| async move {
+            let balance = get_balance(client, address).await;
+            match balance {
+                Ok(_) => progress.inc(1),
+                Err(_) => progress.fail(),
+            }
+            balance
+        })
+        .buffered(jobs.max(1))
+        .collect()
+        .await;
+    progress.finish();
+    balances
+}
+
+async fn get_balance(client: &Client, address: &str) -> anyhow::Result<Balance> {
+    let address = address
+        .parse::<Address<NetworkUnchecked>>()
+        .context("invalid address")?
+        .assume_checked();
+    let stats = client.get_address_stats(&address).await?;
+    Ok(Balance {
+        confirmed: stats.chain_stats.funded_txo_sum as i64 - stats.chain_stats.spent_txo_sum as i64,
+        unconfirmed: stats.mempool_stats.funded_txo_sum as i64
+            - stats.mempool_stats.spent_txo_sum as i64,
+    })
+}
+
+/// The confirmation status of a transaction relative to the tip.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TxState {
+    /// Whether the transaction is in a block.
+    pub confirmed: bool,
+    /// Height of the block it confirmed in.
+    pub height: Option<u32>,
+    /// Number of confirmations as of the current tip, 0 while unconfirmed.
+    pub confirmations: u32,
+    /// UNIX timestamp of the block it confirmed in.
+    pub block_time: Option<u64>,
+}
+
+/// Fetch the status of each of `txids` with up to `jobs` requests in flight, returning them in
+/// the order given.
+///
+/// A failure only fails the status of its own transaction.
+pub async fn get_tx_states(
+    client: &Client,
+    txids: &[Txid],
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<Vec<anyhow::Result<TxState>>> {
+    let tip = client.get_height().await?;
+    progress.set_total(txids.len() as u64);
+    let states = futures::stream::iter(txids)
+        .map(|txid| async move {
+            let status = client.get_tx_status(txid).await;
+            match status {
+                Ok(_) => progress.inc(1),
+                Err(_) => progress.fail(),
+            }
+            let status = match status {
+                Err(Error::HttpResponse { status: 404, .. }) => {
+                    return Err(error::not_found(format!("transaction {}", txid)));
+                }
+                status => status?,
+            };
+            let height = status.block_height.filter(|_| status.confirmed);
+            anyhow::Ok(TxState {
+                confirmed: status.confirmed,
+                height,
+                confirmations: height.map_or(0, |h| tip.saturating_sub(h) + 1),
+                block_time: status.block_time.filter(|_| status.confirmed),
+            })
+        })
+        .buffered(jobs.max(1))
+        .collect()
+        .await;
+    progress.finish();
+    Ok(states)
+}
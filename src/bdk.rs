@@ -0,0 +1,178 @@
+//! Running the `bdk_esplora` full scan or sync of a descriptor and dumping the update.
+//!
+//! The scan goes through `bdk_esplora` itself, so the output is exactly what a BDK wallet
+//! would be handed. It runs against the primary backend only with the configured headers,
+//! failover doesn't apply. Neither can rate limiting or request signing, so they are refused
+//! rather than silently skipped.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use bdk_core::spk_client::{FullScanRequest, SyncRequest};
+use bdk_core::{BlockId, CheckPoint, ConfirmationBlockTime, TxUpdate};
+use bdk_esplora::esplora_client::AsyncClient;
+use bdk_esplora::EsploraAsyncExt;
+use bitcoin::consensus::encode;
+use bitcoin::ScriptBuf;
+use miniscript::{Descriptor, DescriptorPublicKey};
+use serde_json::{json, Value};
+
+use crate::client::Client;
+use crate::error;
+use crate::scan::Keychain;
+
+/// Run a full scan of `keychains` against the primary backend of `client`, stopping each
+/// after `stop_gap` unused scripts, and render the response as JSON.
+pub async fn full_scan(
+    client: &Client,
+    keychains: &[(Keychain, Descriptor<DescriptorPublicKey>)],
+    stop_gap: usize,
+    parallel: usize,
+) -> anyhow::Result<Value> {
+    let client = async_client(client)?;
+    let mut request = FullScanRequest::builder().chain_tip(genesis(&client).await?);
+    for (keychain, descriptor) in keychains {
+        request = request.spks_for_keychain(*keychain, scripts(descriptor, None)?);
+    }
+    let response = client
+        .full_scan(request.build(), stop_gap, parallel)
+        .await
+        .context("full scan failed")?;
+    Ok(render(
+        &response.tx_update,
+        response.chain_update.as_ref(),
+        Some(&response.last_active_indices),
+    ))
+}
+
+/// Sync the first `count` scripts of each of `keychains` against the primary backend of
+/// `client`, and render the response as JSON.
+pub async fn sync(
+    client: &Client,
+    keychains: &[(Keychain, Descriptor<DescriptorPublicKey>)],
+    count: u32,
+    parallel: usize,
+) -> anyhow::Result<Value> {
+    let client = async_client(client)?;
+    let mut spks = vec![];
+    for (keychain, descriptor) in keychains {
+        spks.extend(
+            scripts(descriptor, Some(count))?.map(|(index, spk)| ((*keychain, index), spk)),
+        );
+    }
+    let request = SyncRequest::builder()
+        .chain_tip(genesis(&client).await?)
+        .spks_with_indexes(spks)
+        .build();
+    let response = client.sync(request, parallel).await.context("sync failed")?;
+    Ok(render(&response.tx_update, response.chain_update.as_ref(), None))
+}
+
+/// The `bdk_esplora` client for `client`, refusing options its requests would bypass.
+fn async_client(client: &Client) -> anyhow::Result<AsyncClient> {
+    if client.is_rate_limited() {
+        return Err(error::invalid_input(
+            "bdk-sync can't rate limit its requests, unset --max-rps or the profile's max_rps",
+        ));
+    }
+    if client.is_signed() {
+        return Err(error::invalid_input(
+            "bdk-sync can't sign its requests, unset --hmac-key",
+        ));
+    }
+    Ok(client.async_client())
+}
+
+/// A checkpoint at the genesis block, as a fresh wallet would pass as its local chain.
+async fn genesis(client: &AsyncClient) -> anyhow::Result<CheckPoint> {
+    let hash = client.get_block_hash(0).await?;
+    Ok(CheckPoint::new(BlockId { height: 0, hash }))
+}
+
+/// The scripts of `descriptor` with their derivation index, up to `count` of them if given.
+fn scripts(
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    count: Option<u32>,
+) -> anyhow::Result<impl Iterator<Item = (u32, ScriptBuf)> + Send + 'static> {
+    // Fail on descriptors that can't be derived from up front, the iterator can only stop.
+    descriptor.at_derivation_index(0)?;
+    let end = match count {
+        Some(count) => count,
+        None if descriptor.has_wildcard() => 1 << 31,
+        None => 1,
+    };
+    let descriptor = descriptor.clone();
+    Ok((0..end).map_while(move |index| {
+        let spk = descriptor.at_derivation_index(index).ok()?.script_pubkey();
+        Some((index, spk))
+    }))
+}
+
+/// Render an update in the shape of the `bdk_core` types.
+fn render(
+    tx_update: &TxUpdate<ConfirmationBlockTime>,
+    chain_update: Option<&CheckPoint>,
+    last_active_indices: Option<&BTreeMap<Keychain, u32>>,
+) -> Value {
+    let mut txs: Vec<Value> = tx_update
+        .txs
+        .iter()
+        .map(|tx| json!({ "txid": tx.compute_txid(), "hex": encode::serialize_hex(&**tx) }))
+        .collect();
+    txs.sort_by(|a, b| a["txid"].as_str().cmp(&b["txid"].as_str()));
+    let txouts: Vec<Value> = tx_update
+        .txouts
+        .iter()
+        .map(|(outpoint, txout)| {
+            json!({
+                "outpoint": outpoint.to_string(),
+                "value": txout.value.to_sat(),
+                "script_pubkey": txout.script_pubkey.to_hex_string(),
+            })
+        })
+        .collect();
+    let anchors: Vec<Value> = tx_update
+        .anchors
+        .iter()
+        .map(|(anchor, txid)| {
+            json!({
+                "txid": txid,
+                "height": anchor.block_id.height,
+                "hash": anchor.block_id.hash,
+                "confirmation_time": anchor.confirmation_time,
+            })
+        })
+        .collect();
+    let timestamps = |set: &std::collections::HashSet<(bitcoin::Txid, u64)>| {
+        let mut list: Vec<_> = set.iter().copied().collect();
+        list.sort();
+        list.into_iter()
+            .map(|(txid, time)| json!({ "txid": txid, "time": time }))
+            .collect::<Vec<_>>()
+    };
+    let chain: Option<Vec<Value>> = chain_update.map(|tip| {
+        let mut blocks: Vec<Value> = tip
+            .iter()
+            .map(|cp| json!({ "height": cp.height(), "hash": cp.hash() }))
+            .collect();
+        blocks.reverse();
+        blocks
+    });
+
+    let mut update = json!({
+        "tx_update": {
+            "txs": txs,
+            "txouts": txouts,
+            "anchors": anchors,
+            "seen_ats": timestamps(&tx_update.seen_ats),
+            "evicted_ats": timestamps(&tx_update.evicted_ats),
+        },
+        "chain_update": chain,
+    });
+    if let Some(indices) = last_active_indices {
+        let indices: BTreeMap<String, u32> =
+            indices.iter().map(|(k, i)| (k.to_string(), *i)).collect();
+        update["last_active_indices"] = json!(indices);
+    }
+    update
+}
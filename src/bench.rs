@@ -0,0 +1,151 @@
+//! Latency benchmark of a representative set of endpoints, to compare backends.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::{sha256, Hash};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::progress::Progress;
+
+/// The latency of an endpoint over the iterations of a benchmark.
+#[derive(Debug, Serialize)]
+pub struct EndpointStats {
+    /// The endpoint, with its parameters elided.
+    pub endpoint: &'static str,
+    /// Median latency of the successful requests, in milliseconds.
+    pub p50_ms: Option<u64>,
+    /// 95th percentile latency of the successful requests, in milliseconds.
+    pub p95_ms: Option<u64>,
+    /// Number of requests that failed.
+    pub errors: usize,
+    /// Number of requests made.
+    pub requests: usize,
+}
+
+/// The benchmark of a backend.
+#[derive(Debug, Serialize)]
+pub struct Bench {
+    /// Url of the backend.
+    pub url: String,
+    /// Latency per endpoint.
+    pub endpoints: Vec<EndpointStats>,
+}
+
+/// The requests timed, resolved against the current tip so every backend gets the same ones.
+pub struct Requests(Vec<(&'static str, String)>);
+
+impl Requests {
+    /// Look up the tip, its coinbase and the script the coinbase pays to on `client`.
+    pub async fn resolve(client: &Client) -> anyhow::Result<Self> {
+        let hash = client.get_tip_hash().await?;
+        let txid = client
+            .get_txid_at_block_index(&hash, 0)
+            .await?
+            .ok_or_else(|| error::not_found(format!("coinbase of block {}", hash)))?;
+        let tx = client
+            .get_tx_info(&txid)
+            .await?
+            .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+        let script = &tx.vout[0].scriptpubkey;
+        let scripthash = sha256::Hash::hash(script.as_bytes());
+        Ok(Requests(vec![
+            ("/blocks/tip/height", "/blocks/tip/height".to_string()),
+            ("/block/:hash/header", format!("/block/{}/header", hash)),
+            ("/tx/:txid", format!("/tx/{}", txid)),
+            ("/scripthash/:hash/txs", format!("/scripthash/{:x}/txs", scripthash)),
+        ]))
+    }
+
+    /// Number of requests per iteration.
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Time `iterations` rounds of `requests` against `client`, one request at a time.
+///
+/// Requests go to the primary backend only and aren't retried, so that failures count as
+/// errors instead of inflating the latency.
+pub async fn bench(
+    client: &Client,
+    url: &str,
+    requests: &Requests,
+    iterations: usize,
+    progress: &Progress,
+) -> Bench {
+    let client = client.clone().fallback_urls(vec![]).max_retries(0);
+    let mut samples: Vec<Vec<Duration>> = vec![vec![]; requests.count()];
+    let mut errors = vec![0; requests.count()];
+    for _ in 0..iterations {
+        for (i, (_, path)) in requests.0.iter().enumerate() {
+            // Fetched as text, which bypasses the cache of immutable data.
+            let start = Instant::now();
+            match client.get_text(path).await {
+                Ok(_) => samples[i].push(start.elapsed()),
+                Err(_) => errors[i] += 1,
+            }
+            progress.inc(1);
+        }
+    }
+
+    let endpoints = requests
+        .0
+        .iter()
+        .zip(samples.iter_mut().zip(errors))
+        .map(|((endpoint, _), (samples, errors))| {
+            samples.sort();
+            EndpointStats {
+                endpoint,
+                p50_ms: percentile(samples, 50),
+                p95_ms: percentile(samples, 95),
+                errors,
+                requests: iterations,
+            }
+        })
+        .collect();
+    Bench {
+        url: url.to_string(),
+        endpoints,
+    }
+}
+
+/// The `p`th percentile of the sorted `samples` by the nearest-rank method, in milliseconds.
+fn percentile(samples: &[Duration], p: usize) -> Option<u64> {
+    let rank = (samples.len() * p).div_ceil(100).max(1);
+    samples.get(rank - 1).map(|d| d.as_millis() as u64)
+}
+
+/// Render `benches` for the terminal, side by side.
+pub fn report(benches: &[Bench]) -> String {
+    let mut out = String::new();
+    for (i, bench) in benches.iter().enumerate() {
+        let _ = writeln!(out, "[{}] {}", i + 1, bench.url);
+    }
+    let _ = write!(out, "\n{:<22}", "endpoint");
+    for i in 1..=benches.len() {
+        let _ = write!(out, "  {:>26}", format!("[{}] p50 / p95 / errors", i));
+    }
+    out.push('\n');
+
+    let ms = |ms: Option<u64>| ms.map_or("-".to_string(), |ms| format!("{} ms", ms));
+    let rows = benches.first().map_or(0, |b| b.endpoints.len());
+    for row in 0..rows {
+        let _ = write!(out, "{:<22}", benches[0].endpoints[row].endpoint);
+        for bench in benches {
+            let stats = &bench.endpoints[row];
+            let cell = format!(
+                "{} / {} / {}/{}",
+                ms(stats.p50_ms),
+                ms(stats.p95_ms),
+                stats.errors,
+                stats.requests
+            );
+            let _ = write!(out, "  {:>26}", cell);
+        }
+        out.push('\n');
+    }
+    out
+}
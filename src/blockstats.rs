@@ -0,0 +1,227 @@
+//! Fee and usage statistics of a block.
+//!
+//! mempool.space backends summarize blocks in their `/v1/block/:hash` extras, for other
+//! backends the statistics are computed from the block's transactions.
+
+use bitcoin::BlockHash;
+use esplora_client::{BlockInfo, Tx};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::progress::Progress;
+use crate::timefmt;
+
+/// Maximum weight of a block.
+const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+
+/// Number of transactions esplora returns per page of a block.
+const PAGE_SIZE: u32 = 25;
+
+/// Number of blocks between halvings of the subsidy.
+const HALVING_INTERVAL: u32 = 210_000;
+
+/// Where the statistics come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    /// The mempool.space block extras.
+    Extras,
+    /// Computed from the block's transactions.
+    Computed,
+}
+
+/// Summary of a block.
+#[derive(Debug, Serialize)]
+pub struct BlockStats {
+    /// The block hash.
+    pub hash: BlockHash,
+    /// The block height.
+    pub height: u32,
+    /// UNIX timestamp from the block header.
+    pub time: u64,
+    /// Number of transactions, including the coinbase.
+    pub tx_count: u64,
+    /// Size in bytes.
+    pub size: u64,
+    /// Weight in weight units.
+    pub weight: u64,
+    /// Share of the maximum block weight used, in percent.
+    pub utilization: f64,
+    /// Total fees, in satoshis.
+    pub fees: u64,
+    /// The block subsidy, in satoshis.
+    pub subsidy: u64,
+    /// Lowest feerate paid, in sat/vB.
+    pub min_feerate: Option<f64>,
+    /// Median feerate, in sat/vB.
+    pub median_feerate: Option<f64>,
+    /// Highest feerate paid, in sat/vB.
+    pub max_feerate: Option<f64>,
+    /// Number of transactions spending segwit inputs, the coinbase excluded.
+    pub segwit_txs: u64,
+    /// Share of those among the transactions other than the coinbase, in percent.
+    pub segwit_share: Option<f64>,
+    /// Where the statistics come from.
+    pub source: Source,
+}
+
+#[derive(Deserialize)]
+struct BlockSummary {
+    extras: Option<Extras>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Extras {
+    total_fees: u64,
+    median_fee: f64,
+    /// Feerate percentiles: min, 10th, 25th, 50th, 75th, 90th, max.
+    fee_range: Vec<f64>,
+    segwit_total_txs: u64,
+}
+
+/// The subsidy of a block at `height`, in satoshis.
+pub fn subsidy(height: u32) -> u64 {
+    match height / HALVING_INTERVAL {
+        halvings @ 0..64 => (50 * 100_000_000) >> halvings,
+        _ => 0,
+    }
+}
+
+/// Summarize the block `hash`, from the backend's block extras if it has them unless `local`
+/// is set.
+///
+/// Computing the statistics locally takes a request per 25 transactions, up to `jobs` of them
+/// are in flight at once.
+pub async fn block_stats(
+    client: &Client,
+    hash: &BlockHash,
+    local: bool,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<BlockStats> {
+    let info = client.get_block_info(hash).await?;
+    if !local {
+        let summary: Option<BlockSummary> =
+            client.get_opt_json(&format!("/v1/block/{}", hash)).await?;
+        if let Some(extras) = summary.and_then(|s| s.extras) {
+            return Ok(from_extras(&info, &extras));
+        }
+    }
+
+    let pages: Vec<u32> = (0..info.tx_count as u32).step_by(PAGE_SIZE as usize).collect();
+    progress.set_total(info.tx_count);
+    let txs: Vec<Vec<Tx>> = futures::stream::iter(pages)
+        .map(|start| async move {
+            let txs = client.get_block_txs(hash, start).await?;
+            progress.inc(txs.len() as u64);
+            anyhow::Ok(txs)
+        })
+        .buffered(jobs.max(1))
+        .try_collect()
+        .await?;
+    progress.finish();
+    Ok(compute(&info, &txs.concat()))
+}
+
+fn from_extras(info: &BlockInfo, extras: &Extras) -> BlockStats {
+    let non_coinbase = info.tx_count.saturating_sub(1);
+    BlockStats {
+        segwit_txs: extras.segwit_total_txs,
+        segwit_share: share(extras.segwit_total_txs, non_coinbase),
+        fees: extras.total_fees,
+        min_feerate: extras.fee_range.first().copied(),
+        median_feerate: Some(extras.median_fee),
+        max_feerate: extras.fee_range.last().copied(),
+        source: Source::Extras,
+        ..base(info)
+    }
+}
+
+fn compute(info: &BlockInfo, txs: &[Tx]) -> BlockStats {
+    // The coinbase is the first transaction and pays no fee.
+    let paying = txs.get(1..).unwrap_or_default();
+    let mut feerates: Vec<f64> = paying
+        .iter()
+        .map(|tx| tx.fee as f64 / tx.weight.div_ceil(4) as f64)
+        .collect();
+    feerates.sort_by(f64::total_cmp);
+    let segwit_txs = paying
+        .iter()
+        .filter(|tx| tx.vin.iter().any(|vin| !vin.witness.is_empty()))
+        .count() as u64;
+    BlockStats {
+        fees: paying.iter().map(|tx| tx.fee).sum(),
+        min_feerate: feerates.first().copied(),
+        median_feerate: feerates.get(feerates.len() / 2).copied(),
+        max_feerate: feerates.last().copied(),
+        segwit_txs,
+        segwit_share: share(segwit_txs, paying.len() as u64),
+        source: Source::Computed,
+        ..base(info)
+    }
+}
+
+/// The statistics known from the block summary alone.
+fn base(info: &BlockInfo) -> BlockStats {
+    BlockStats {
+        hash: info.id,
+        height: info.height,
+        time: info.timestamp,
+        tx_count: info.tx_count,
+        size: info.size as u64,
+        weight: info.weight,
+        utilization: info.weight as f64 / MAX_BLOCK_WEIGHT as f64 * 100.0,
+        fees: 0,
+        subsidy: subsidy(info.height),
+        min_feerate: None,
+        median_feerate: None,
+        max_feerate: None,
+        segwit_txs: 0,
+        segwit_share: None,
+        source: Source::Computed,
+    }
+}
+
+fn share(part: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| part as f64 / total as f64 * 100.0)
+}
+
+/// Render `stats` for the terminal.
+pub fn report(stats: &BlockStats) -> String {
+    let rate = |r: Option<f64>| r.map_or("-".to_string(), |r| format!("{:.2}", r));
+    let reward = stats.subsidy + stats.fees;
+    let mut out = String::new();
+    out.push_str(&format!("block {} at height {}\n", stats.hash, stats.height));
+    out.push_str(&format!("time: {}\n", timefmt::format_rfc3339(stats.time)));
+    out.push_str(&format!("transactions: {}\n", stats.tx_count));
+    out.push_str(&format!(
+        "weight: {} WU of {} ({:.1}%), {} B\n",
+        stats.weight, MAX_BLOCK_WEIGHT, stats.utilization, stats.size
+    ));
+    out.push_str(&format!(
+        "fees: {} sat, feerate min {} / median {} / max {} sat/vB\n",
+        stats.fees,
+        rate(stats.min_feerate),
+        rate(stats.median_feerate),
+        rate(stats.max_feerate)
+    ));
+    out.push_str(&format!(
+        "reward: {} sat, subsidy {} sat ({:.1}%) + fees {} sat ({:.1}%)\n",
+        reward,
+        stats.subsidy,
+        share(stats.subsidy, reward).unwrap_or(0.0),
+        stats.fees,
+        share(stats.fees, reward).unwrap_or(0.0)
+    ));
+    out.push_str(&format!(
+        "segwit: {} transactions ({}%)\n",
+        stats.segwit_txs,
+        stats.segwit_share.map_or("-".to_string(), |s| format!("{:.1}", s))
+    ));
+    if stats.source == Source::Extras {
+        out.push_str("(from the backend's block extras)\n");
+    }
+    out
+}
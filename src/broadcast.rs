@@ -0,0 +1,541 @@
+//! Broadcasting transactions, with a diagnosis of why the backend rejected one and local checks
+//! that can be run instead of submitting it.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use bitcoin::{OutPoint, Transaction, Txid};
+use esplora_client::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::error;
+
+/// Feerate in sat/vB below which nodes don't relay, Core's default `-minrelaytxfee`.
+const MIN_RELAY_FEERATE: f64 = 1.0;
+
+/// Most transactions Core accepts in a package.
+const MAX_PACKAGE_COUNT: usize = 25;
+
+/// Most weight Core accepts in a package.
+const MAX_PACKAGE_WEIGHT: u64 = 404_000;
+
+/// Why a transaction was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reason {
+    /// The feerate is below the node's relay or mempool minimum.
+    FeeTooLow,
+    /// The fee is above the node's maximum.
+    FeeTooHigh,
+    /// A replacement doesn't pay enough over the transactions it replaces.
+    ReplacementFee,
+    /// An input is spent by an unconfirmed transaction that can't be replaced.
+    MempoolConflict,
+    /// An input doesn't exist or is spent by a confirmed transaction.
+    MissingInputs,
+    /// The transaction is already in the mempool or the chain.
+    AlreadyKnown,
+    /// Its locktime or relative locktimes aren't satisfied yet.
+    NonFinal,
+    /// An output is below the dust threshold.
+    Dust,
+    /// Too many unconfirmed ancestors or descendants.
+    MempoolChainLimit,
+    /// A signature or script doesn't verify.
+    ScriptVerify,
+    /// The transaction isn't standard.
+    NonStandard,
+    /// The transaction couldn't be decoded.
+    Decode,
+    /// Anything else.
+    Unknown,
+}
+
+impl Reason {
+    /// Recognize the reason in a rejection `message` from the node.
+    fn parse(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+        if has(&["insufficient fee"]) {
+            Reason::ReplacementFee
+        } else if has(&["min relay fee not met", "mempool min fee not met"]) {
+            Reason::FeeTooLow
+        } else if has(&["absurdly-high-fee", "max-fee-exceeded"]) {
+            Reason::FeeTooHigh
+        } else if has(&["txn-mempool-conflict"]) {
+            Reason::MempoolConflict
+        } else if has(&["missingorspent", "missing-inputs", "missing inputs"]) {
+            Reason::MissingInputs
+        } else if has(&["already in block chain", "txn-already-known", "txn-already-in-mempool"]) {
+            Reason::AlreadyKnown
+        } else if has(&["non-final", "non-bip68-final"]) {
+            Reason::NonFinal
+        } else if has(&["dust"]) {
+            Reason::Dust
+        } else if has(&["too-long-mempool-chain"]) {
+            Reason::MempoolChainLimit
+        } else if has(&["script-verify-flag-failed"]) {
+            Reason::ScriptVerify
+        } else if has(&["tx decode failed"]) {
+            Reason::Decode
+        } else if has(&[
+            "scriptpubkey",
+            "scriptsig",
+            "tx-size",
+            "version",
+            "bare-multisig",
+            "multi-op-return",
+            "nonstandard",
+        ]) {
+            Reason::NonStandard
+        } else {
+            Reason::Unknown
+        }
+    }
+
+    /// What to do about it.
+    pub fn hint(self) -> &'static str {
+        match self {
+            Reason::FeeTooLow => "raise the feerate, see `get-fee-estimates` or `when-confirm`",
+            Reason::FeeTooHigh => "the fee looks like a mistake, check the change output",
+            Reason::ReplacementFee => {
+                "a replacement must pay more than the transactions it evicts, see `bump-fee`"
+            }
+            Reason::MempoolConflict => {
+                "an input is already spent by an unconfirmed transaction, see `get-output-status`"
+            }
+            Reason::MissingInputs => {
+                "an input doesn't exist or is already spent, or its parent isn't broadcast yet"
+            }
+            Reason::AlreadyKnown => "the transaction was already broadcast, nothing to do",
+            Reason::NonFinal => "its locktime isn't reached yet, broadcast it later",
+            Reason::Dust => "raise the value of outputs below the dust threshold",
+            Reason::MempoolChainLimit => "wait for some of its unconfirmed ancestors to confirm",
+            Reason::ScriptVerify => {
+                "a signature is invalid, check the inputs were signed with the right amounts"
+            }
+            Reason::NonStandard => "most nodes don't relay non-standard transactions",
+            Reason::Decode => "the hex isn't a valid serialized transaction",
+            Reason::Unknown => "see the node's message",
+        }
+    }
+}
+
+/// A transaction rejected by the backend, or by the local checks.
+#[derive(Debug)]
+pub struct Rejection {
+    /// Why it was rejected.
+    pub reason: Reason,
+    /// The node's RPC error code, if it reported one.
+    pub code: Option<i64>,
+    /// The node's message, or the failed check.
+    pub message: String,
+}
+
+impl Rejection {
+    /// Parse the body of a rejected broadcast.
+    ///
+    /// Esplora passes on the node's `sendrawtransaction` error, as in
+    /// `sendrawtransaction RPC error: {"code":-26,"message":"min relay fee not met"}`.
+    fn parse(body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct RpcError {
+            code: i64,
+            message: String,
+        }
+
+        let rpc = body
+            .split_once("RPC error:")
+            .and_then(|(_, json)| serde_json::from_str::<RpcError>(json.trim()).ok());
+        let (code, message) = match rpc {
+            Some(rpc) => (Some(rpc.code), rpc.message),
+            None => (None, body.trim().to_string()),
+        };
+        Rejection {
+            reason: Reason::parse(&message),
+            code,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction rejected: {}", self.message)?;
+        if let Some(code) = self.code {
+            write!(f, " (code {})", code)?;
+        }
+        write!(f, "; {}", self.reason.hint())
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// Broadcast `tx`, turning a rejection into a [`Rejection`].
+pub async fn broadcast(client: &Client, tx: &Transaction) -> anyhow::Result<()> {
+    match client.broadcast(tx).await {
+        Ok(()) => Ok(()),
+        Err(Error::HttpResponse {
+            status: 400,
+            message,
+        }) => Err(Rejection::parse(&message).into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The outcome of broadcasting a transaction of a package.
+#[derive(Debug, Serialize)]
+pub struct PackageTx {
+    pub txid: Txid,
+    /// Whether the transaction is in the backend's mempool, or was already.
+    pub accepted: bool,
+    /// Feerate the transaction was accepted at, with the parents it was assessed with, in
+    /// sat/vB.
+    pub effective_feerate: Option<f64>,
+    /// Why the transaction was rejected.
+    pub error: Option<String>,
+}
+
+/// The outcome of broadcasting a package.
+#[derive(Debug, Serialize)]
+pub struct PackageResult {
+    /// Whether the package was submitted as a whole, rather than one transaction at a time.
+    pub submitted_as_package: bool,
+    /// The backend's verdict on the package as a whole.
+    pub message: Option<String>,
+    /// The transactions broadcast, in order. Those after a rejection aren't attempted when
+    /// broadcasting one at a time.
+    pub txs: Vec<PackageTx>,
+    /// Transactions in the mempool the package replaced.
+    pub replaced: Vec<Txid>,
+}
+
+impl PackageResult {
+    /// Whether every transaction of the package is in the mempool.
+    pub fn accepted(&self, count: usize) -> bool {
+        self.txs.len() == count && self.txs.iter().all(|tx| tx.accepted)
+    }
+}
+
+/// Check that `txs` is a package Core accepts: a child, last, spending every other transaction,
+/// and parents that don't spend each other.
+pub fn check_package(txs: &[Transaction]) -> anyhow::Result<()> {
+    if txs.is_empty() || txs.len() > MAX_PACKAGE_COUNT {
+        return Err(error::invalid_input(format!(
+            "a package has 1 to {} transactions, got {}",
+            MAX_PACKAGE_COUNT,
+            txs.len()
+        )));
+    }
+    let weight: u64 = txs.iter().map(|tx| tx.weight().to_wu()).sum();
+    if weight > MAX_PACKAGE_WEIGHT {
+        return Err(error::invalid_input(format!(
+            "the package weighs {} WU, above the limit of {}",
+            weight, MAX_PACKAGE_WEIGHT
+        )));
+    }
+
+    let position: HashMap<Txid, usize> =
+        txs.iter().enumerate().map(|(i, tx)| (tx.compute_txid(), i)).collect();
+    if position.len() < txs.len() {
+        return Err(error::invalid_input("the package has the same transaction twice"));
+    }
+    let mut spent = HashSet::new();
+    for (i, tx) in txs.iter().enumerate() {
+        for input in &tx.input {
+            if !spent.insert(input.previous_output) {
+                return Err(error::invalid_input(format!(
+                    "{} is spent twice within the package",
+                    input.previous_output
+                )));
+            }
+            if let Some(&parent) = position.get(&input.previous_output.txid) {
+                if i + 1 < txs.len() {
+                    return Err(error::invalid_input(format!(
+                        "transaction {} spends {} of the package, only the last transaction \
+                         may spend the others",
+                        i,
+                        txs[parent].compute_txid()
+                    )));
+                }
+            }
+        }
+    }
+
+    let (child, parents) = txs.split_last().expect("the package isn't empty");
+    let spent_by_child: HashSet<Txid> =
+        child.input.iter().map(|i| i.previous_output.txid).collect();
+    if let Some(orphan) = parents
+        .iter()
+        .map(Transaction::compute_txid)
+        .find(|txid| !spent_by_child.contains(txid))
+    {
+        return Err(error::invalid_input(format!(
+            "{} isn't spent by the last transaction, a package is a child and its parents",
+            orphan
+        )));
+    }
+    Ok(())
+}
+
+/// Broadcast the package `txs`, parents first and the child last, as a whole if the backend
+/// accepts packages and otherwise one transaction at a time.
+pub async fn broadcast_package(
+    client: &Client,
+    txs: &[Transaction],
+) -> anyhow::Result<PackageResult> {
+    check_package(txs)?;
+    let Some(result) = client.submit_package(txs).await? else {
+        return broadcast_sequentially(client, txs).await;
+    };
+
+    let txs = txs
+        .iter()
+        .map(|tx| {
+            let txid = tx.compute_txid();
+            match result.tx_results.get(&tx.compute_wtxid()) {
+                Some(r) => PackageTx {
+                    txid,
+                    accepted: r.error.is_none(),
+                    effective_feerate: r
+                        .fees
+                        .as_ref()
+                        .and_then(|f| f.effective_feerate)
+                        .map(|rate| rate.to_sat_per_kwu() as f64 * 4.0 / 1000.0),
+                    error: r.error.clone(),
+                },
+                None => PackageTx {
+                    txid,
+                    accepted: false,
+                    effective_feerate: None,
+                    error: Some("not in the backend's results".to_string()),
+                },
+            }
+        })
+        .collect();
+    Ok(PackageResult {
+        submitted_as_package: true,
+        message: Some(result.package_msg),
+        txs,
+        replaced: result.replaced_transactions.unwrap_or_default(),
+    })
+}
+
+/// Broadcast `txs` in order, stopping at the first rejection as the transactions after it may
+/// depend on it.
+async fn broadcast_sequentially(
+    client: &Client,
+    txs: &[Transaction],
+) -> anyhow::Result<PackageResult> {
+    let mut results = vec![];
+    for tx in txs {
+        let error = match broadcast(client, tx).await {
+            Ok(()) => None,
+            Err(e) => match e.downcast::<Rejection>() {
+                // A parent broadcast before is fine.
+                Ok(rejection) if rejection.reason == Reason::AlreadyKnown => None,
+                Ok(rejection) => Some(rejection.to_string()),
+                Err(e) => return Err(e),
+            },
+        };
+        let accepted = error.is_none();
+        results.push(PackageTx {
+            txid: tx.compute_txid(),
+            accepted,
+            effective_feerate: None,
+            error,
+        });
+        if !accepted {
+            break;
+        }
+    }
+    Ok(PackageResult {
+        submitted_as_package: false,
+        message: None,
+        txs: results,
+        replaced: vec![],
+    })
+}
+
+/// Render `result` for the terminal.
+pub fn package_report(result: &PackageResult) -> String {
+    let mut out = String::new();
+    if result.submitted_as_package {
+        out.push_str(&format!(
+            "submitted as a package: {}\n",
+            result.message.as_deref().unwrap_or("-")
+        ));
+    } else {
+        out.push_str("the backend doesn't accept packages, broadcast one at a time\n");
+    }
+    for tx in &result.txs {
+        let status = match (&tx.error, tx.effective_feerate) {
+            (Some(error), _) => format!("rejected: {}", error),
+            (None, Some(rate)) => format!("accepted at {:.2} sat/vB", rate),
+            (None, None) => "accepted".to_string(),
+        };
+        out.push_str(&format!("{}  {}\n", tx.txid, status));
+    }
+    for txid in &result.replaced {
+        out.push_str(&format!("replaced {}\n", txid));
+    }
+    out
+}
+
+/// The outcome of a local check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    /// What was checked.
+    pub name: &'static str,
+    /// Why the check failed, `None` if it passed.
+    pub failure: Option<Reason>,
+    /// What was found.
+    pub detail: String,
+}
+
+/// Check `tx` against the backend without submitting it: that it isn't known yet, that its
+/// inputs exist and are unspent, that it pays a fee and that none of its outputs is dust.
+///
+/// Scripts and signatures aren't verified.
+pub async fn dry_run(client: &Client, tx: &Transaction) -> anyhow::Result<Vec<Check>> {
+    let txid = tx.compute_txid();
+    let mut checks = vec![];
+
+    let known = client.get_tx_info(&txid).await?.is_some();
+    checks.push(Check {
+        name: "not yet broadcast",
+        failure: known.then_some(Reason::AlreadyKnown),
+        detail: if known {
+            format!("{} is already known to the backend", txid)
+        } else {
+            format!("{} is new", txid)
+        },
+    });
+
+    let mut seen = HashSet::new();
+    let duplicate = tx.input.iter().find(|input| !seen.insert(input.previous_output));
+    let mut missing = vec![];
+    let mut spent = vec![];
+    let mut input_value = 0;
+    for input in &tx.input {
+        let OutPoint { txid: prev, vout } = input.previous_output;
+        let Some(prevout) = client
+            .get_tx_info(&prev)
+            .await?
+            .and_then(|parent| parent.vout.get(vout as usize).cloned())
+        else {
+            missing.push(input.previous_output);
+            continue;
+        };
+        input_value += prevout.value;
+        let status = client.get_output_status(&prev, vout as u64).await?;
+        if let Some(spender) = status.and_then(|s| s.txid.filter(|_| s.spent)) {
+            if spender != txid {
+                let confirmed = client.get_tx_status(&spender).await.is_ok_and(|s| s.confirmed);
+                spent.push((input.previous_output, spender, confirmed));
+            }
+        }
+    }
+    checks.push(Check {
+        name: "inputs exist",
+        failure: (!missing.is_empty() || duplicate.is_some()).then_some(Reason::MissingInputs),
+        detail: match (missing.first(), duplicate) {
+            (Some(outpoint), _) => format!(
+                "{} of {} input(s) not found, first {}",
+                missing.len(),
+                tx.input.len(),
+                outpoint
+            ),
+            (None, Some(input)) => format!("{} is spent twice", input.previous_output),
+            (None, None) => format!("all {} input(s) found", tx.input.len()),
+        },
+    });
+    checks.push(Check {
+        name: "inputs unspent",
+        failure: spent.first().map(|&(_, _, confirmed)| {
+            if confirmed {
+                Reason::MissingInputs
+            } else {
+                Reason::MempoolConflict
+            }
+        }),
+        detail: match spent.first() {
+            Some((outpoint, spender, confirmed)) => format!(
+                "{} is already spent by {} ({})",
+                outpoint,
+                spender,
+                if *confirmed {
+                    "confirmed"
+                } else {
+                    "unconfirmed"
+                }
+            ),
+            None => "no input is spent by another transaction".to_string(),
+        },
+    });
+
+    // The fee is only known once every input is found.
+    if missing.is_empty() {
+        let output_value: u64 = tx.output.iter().map(|o| o.value.to_sat()).sum();
+        let fee = input_value as i64 - output_value as i64;
+        let vsize = tx.vsize();
+        let feerate = fee as f64 / vsize as f64;
+        checks.push(Check {
+            name: "pays a fee",
+            failure: (feerate < MIN_RELAY_FEERATE).then_some(Reason::FeeTooLow),
+            detail: format!("{} sat for {} vB, {:.2} sat/vB", fee, vsize, feerate),
+        });
+    }
+
+    let dust: Vec<usize> = tx
+        .output
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| {
+            !o.script_pubkey.is_op_return() && o.value < o.script_pubkey.minimal_non_dust()
+        })
+        .map(|(i, _)| i)
+        .collect();
+    checks.push(Check {
+        name: "no dust outputs",
+        failure: (!dust.is_empty()).then_some(Reason::Dust),
+        detail: if dust.is_empty() {
+            "every output is above the dust threshold".to_string()
+        } else {
+            format!("output(s) {:?} below the dust threshold", dust)
+        },
+    });
+
+    Ok(checks)
+}
+
+/// The rejection the first failed check predicts, if any.
+pub fn failure(checks: &[Check]) -> Option<Rejection> {
+    checks.iter().find_map(|check| {
+        check.failure.map(|reason| Rejection {
+            reason,
+            code: None,
+            message: format!("check `{}` failed, {}", check.name, check.detail),
+        })
+    })
+}
+
+/// Render `checks` for the terminal.
+pub fn report(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            format!(
+                "{:<4}  {}: {}\n",
+                if check.failure.is_some() {
+                    "FAIL"
+                } else {
+                    "ok"
+                },
+                check.name,
+                check.detail
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,99 @@
+//! On-disk cache of API responses that can no longer change.
+//!
+//! Each response body is stored as a plain file at its request path, below a directory per
+//! backend so that networks never share entries, e.g.
+//! `<cache-dir>/esplora-cli/<backend>/tx/<txid>/raw`. Only the `esplora-cli` subdirectory is
+//! ever listed or removed, so pointing `--cache-dir` at a shared directory such as `~/.cache`
+//! leaves everything else in it alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bitcoin::hashes::{sha256, Hash};
+
+/// Subdirectory of the cache directory the cache creates and owns.
+const SUBDIR: &str = "esplora-cli";
+
+/// The directory holding the entries of all backends in the cache directory `root`.
+pub fn dir(root: &Path) -> PathBuf {
+    root.join(SUBDIR)
+}
+
+/// A cache directory, scoped to a single backend for lookups.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    /// Directory holding the entries of the backend.
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Open the cache of the backend at `url` in `root`.
+    pub fn new(root: &Path, url: &str) -> Self {
+        let backend = sha256::Hash::hash(url.as_bytes());
+        Self {
+            dir: dir(root).join(&backend.to_string()[..16]),
+        }
+    }
+
+    /// The cached response to `path`, if any.
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        fs::read(self.entry(path)).ok()
+    }
+
+    /// Store the response to `path`.
+    ///
+    /// The cache is best effort, failing to write an entry only means fetching it again.
+    pub fn put(&self, path: &str, body: &[u8]) {
+        let entry = self.entry(path);
+        let tmp = entry.with_extension("tmp");
+        if let Some(parent) = entry.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if fs::write(&tmp, body).is_ok() {
+            let _ = fs::rename(&tmp, &entry);
+        }
+    }
+
+    fn entry(&self, path: &str) -> PathBuf {
+        self.dir.join(path.trim_start_matches('/'))
+    }
+}
+
+/// Number of entries and their total size in bytes, across all backends in the cache
+/// directory `root`.
+pub fn stats(root: &Path) -> anyhow::Result<(u64, u64)> {
+    let dir = dir(root);
+    if !dir.exists() {
+        return Ok((0, 0));
+    }
+    let mut entries = 0;
+    let mut size = 0;
+    let mut dirs = vec![dir];
+    while let Some(dir) = dirs.pop() {
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))?
+        {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                entries += 1;
+                size += metadata.len();
+            }
+        }
+    }
+    Ok((entries, size))
+}
+
+/// Remove every entry in the cache directory `root`, returning the number of entries
+/// removed.
+pub fn clear(root: &Path) -> anyhow::Result<u64> {
+    let (entries, _) = stats(root)?;
+    let dir = dir(root);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("failed to remove {}", dir.display()))?;
+    }
+    Ok(entries)
+}
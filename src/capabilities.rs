@@ -0,0 +1,164 @@
+//! Detection of the optional endpoints a backend serves.
+//!
+//! Blockstream's esplora serves the base API only, mempool.space adds the `/v1` endpoints for
+//! fee recommendations, mining, prices, replacements and Lightning. Commands needing those
+//! check for them with [`require`] so that they fail up front with a clear message instead of
+//! on a 404 halfway through.
+
+use std::fmt;
+
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+
+/// An optional set of endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Feature {
+    /// Fee recommendations, `/v1/fees/recommended`.
+    RecommendedFees,
+    /// Blocks projected from the mempool, `/v1/fees/mempool-blocks`.
+    ProjectedBlocks,
+    /// Fee statistics and audits of blocks, `/v1/block/:hash`.
+    BlockExtras,
+    /// Mining pool statistics, `/v1/mining`.
+    Mining,
+    /// Current and historical prices, `/v1/prices`.
+    Prices,
+    /// Replacements seen in the mempool, `/v1/replacements`.
+    Replacements,
+    /// Lightning network data, `/v1/lightning`.
+    Lightning,
+    /// Address search by prefix, `/address-prefix/:prefix`.
+    AddressSearch,
+}
+
+impl Feature {
+    /// Every feature, in the order reported.
+    pub const ALL: [Feature; 8] = [
+        Feature::RecommendedFees,
+        Feature::ProjectedBlocks,
+        Feature::BlockExtras,
+        Feature::Mining,
+        Feature::Prices,
+        Feature::Replacements,
+        Feature::Lightning,
+        Feature::AddressSearch,
+    ];
+
+    /// An endpoint that answers if and only if the feature is served.
+    fn probe_path(self) -> &'static str {
+        match self {
+            Feature::RecommendedFees => "/v1/fees/recommended",
+            Feature::ProjectedBlocks => "/v1/fees/mempool-blocks",
+            Feature::BlockExtras => "/v1/blocks",
+            Feature::Mining => "/v1/mining/pools/1w",
+            Feature::Prices => "/v1/prices",
+            Feature::Replacements => "/v1/replacements",
+            Feature::Lightning => "/v1/lightning/statistics/latest",
+            Feature::AddressSearch => "/address-prefix/1",
+        }
+    }
+
+    /// Whether only mempool.space backends serve the feature.
+    fn mempool_only(self) -> bool {
+        self != Feature::AddressSearch
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Feature::RecommendedFees => "recommended fees",
+            Feature::ProjectedBlocks => "projected blocks",
+            Feature::BlockExtras => "block extras",
+            Feature::Mining => "mining statistics",
+            Feature::Prices => "prices",
+            Feature::Replacements => "replacements",
+            Feature::Lightning => "lightning",
+            Feature::AddressSearch => "address search",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Whether the backend serves a feature.
+#[derive(Debug, Serialize)]
+pub struct Capability {
+    /// The feature.
+    pub feature: Feature,
+    /// Whether it's served, `None` if the probe failed for another reason than a 404.
+    pub supported: Option<bool>,
+    /// Why the probe failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Whether the backend serves `feature`.
+pub async fn supports(client: &Client, feature: Feature) -> anyhow::Result<bool> {
+    Ok(client.get_opt_text(feature.probe_path()).await?.is_some())
+}
+
+/// Fail unless the backend serves `feature`.
+///
+/// This costs a request, callers fetching the probed endpoint itself should instead turn its
+/// 404 into [`unsupported`].
+pub async fn require(client: &Client, feature: Feature) -> anyhow::Result<()> {
+    if supports(client, feature).await? {
+        return Ok(());
+    }
+    Err(unsupported(feature))
+}
+
+/// The error of a backend that doesn't serve `feature`.
+pub fn unsupported(feature: Feature) -> anyhow::Error {
+    let needs = if feature.mempool_only() {
+        ", it needs a mempool.space backend"
+    } else {
+        ""
+    };
+    error::unsupported(format!(
+        "the backend doesn't serve {}{} (see `capabilities`)",
+        feature, needs
+    ))
+}
+
+/// Probe the backend for every feature.
+pub async fn probe(client: &Client) -> Vec<Capability> {
+    futures::stream::iter(Feature::ALL)
+        .map(|feature| async move {
+            match supports(client, feature).await {
+                Ok(supported) => Capability {
+                    feature,
+                    supported: Some(supported),
+                    error: None,
+                },
+                Err(e) => Capability {
+                    feature,
+                    supported: None,
+                    error: Some(format!("{:#}", e)),
+                },
+            }
+        })
+        .buffered(Feature::ALL.len())
+        .collect()
+        .await
+}
+
+/// Render `capabilities` for the terminal.
+pub fn report(capabilities: &[Capability]) -> String {
+    capabilities
+        .iter()
+        .map(|c| {
+            let status = match (c.supported, &c.error) {
+                (Some(true), _) => "yes".to_string(),
+                (Some(false), _) => "no".to_string(),
+                (None, Some(e)) => format!("unknown, {}", e),
+                (None, None) => "unknown".to_string(),
+            };
+            format!("{:<18}  {}\n", c.feature.to_string(), status)
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+//! Block hashes at regular heights, for embedding as trusted checkpoints in light clients.
+
+use std::fmt::Write as _;
+
+use bitcoin::BlockHash;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::progress::Progress;
+
+/// Confirmations a block needs to be checkpointed when no last height is given, so that a
+/// shallow reorg doesn't invalidate the checkpoints.
+const MIN_CONFIRMATIONS: u32 = 6;
+
+/// The hash of the block at a height.
+#[derive(Debug, Serialize)]
+pub struct Checkpoint {
+    pub height: u32,
+    pub hash: BlockHash,
+}
+
+/// Fetch the hashes of every `every`th block from `from` up to `to`, or the last block with
+/// [`MIN_CONFIRMATIONS`] confirmations if not given, requesting `jobs` of them at once.
+pub async fn checkpoints(
+    client: &Client,
+    from: u32,
+    to: Option<u32>,
+    every: u32,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<Vec<Checkpoint>> {
+    if every == 0 {
+        return Err(error::invalid_input("--every must be at least 1"));
+    }
+    let to = match to {
+        Some(to) => to,
+        None => client
+            .get_height()
+            .await?
+            .checked_sub(MIN_CONFIRMATIONS - 1)
+            .ok_or_else(|| error::invalid_input("no block is deep enough to checkpoint"))?,
+    };
+    if from > to {
+        return Err(error::invalid_input("--from must not be above the last height"));
+    }
+
+    let heights: Vec<u32> = (from..=to).step_by(every as usize).collect();
+    progress.set_total(heights.len() as u64);
+    let checkpoints = futures::stream::iter(heights)
+        .map(|height| async move {
+            let hash = client.get_block_hash(height).await?;
+            progress.inc(1);
+            anyhow::Ok(Checkpoint { height, hash })
+        })
+        .buffered(jobs.max(1))
+        .try_collect()
+        .await?;
+    progress.finish();
+    Ok(checkpoints)
+}
+
+/// Render `checkpoints` as a Rust const array of `(height, hash)` pairs named `name`.
+pub fn rust(checkpoints: &[Checkpoint], name: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "pub const {}: [(u32, &str); {}] = [", name, checkpoints.len());
+    for checkpoint in checkpoints {
+        let _ = writeln!(out, "    ({}, \"{}\"),", checkpoint.height, checkpoint.hash);
+    }
+    out.push_str("];\n");
+    out
+}
+
+/// Render `checkpoints` for the terminal, a height and hash per line.
+pub fn report(checkpoints: &[Checkpoint]) -> String {
+    checkpoints
+        .iter()
+        .map(|checkpoint| format!("{} {}\n", checkpoint.height, checkpoint.hash))
+        .collect()
+}
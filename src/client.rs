@@ -0,0 +1,614 @@
+//! HTTP client for the Esplora API.
+//!
+//! This mirrors [`esplora_client::AsyncClient`], reusing its API types, but sends every request
+//! through [`Client::send`] so that per-request concerns such as request signing have a single
+//! place to live. Responses that can no longer change are served from the [`Cache`] if one is
+//! configured.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::consensus::{deserialize, serialize, Decodable};
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::{Address, Block, BlockHash, MerkleBlock, Script, Transaction, Txid};
+use esplora_client::{
+    AddressStats, BlockInfo, BlockStatus, Builder, Error, MempoolStats, MerkleProof, OutputStatus,
+    SubmitPackageResult, Tx, TxStatus, RETRYABLE_ERROR_CODES,
+};
+use futures::Stream;
+use reqwest::{Method, Request, Response};
+use serde::de::DeserializeOwned;
+
+use crate::cache::Cache;
+use crate::fees;
+use crate::limiter::RateLimiter;
+use crate::multi::{self, Backends};
+
+/// Backoff before the first retry, doubled on every following retry.
+const BASE_BACKOFF: Duration = Duration::from_millis(256);
+
+/// Confirmations after which a block is assumed to never be reorganized out of the chain.
+const FINAL_DEPTH: u32 = 6;
+
+/// Header carrying the UNIX timestamp a request was signed at.
+pub const TIMESTAMP_HEADER: &str = "x-esplora-timestamp";
+
+/// Header carrying the hex HMAC-SHA256 signature of a request.
+pub const SIGNATURE_HEADER: &str = "x-esplora-signature";
+
+/// An async client for an Esplora API server.
+#[derive(Debug, Clone)]
+pub struct Client {
+    /// The URLs of the Esplora servers to send requests to.
+    backends: Arc<Backends>,
+    /// The inner HTTP client, configured from the [`Builder`].
+    http: reqwest::Client,
+    /// Number of times to retry a request.
+    max_retries: usize,
+    /// Key to sign each request with.
+    hmac_key: Option<Arc<[u8]>>,
+    /// Cache of immutable responses.
+    cache: Option<Cache>,
+    /// Limit on the rate of requests, shared by all clones.
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Client {
+    /// Build a [`Client`] from a [`Builder`].
+    pub fn from_builder(builder: Builder) -> Result<Self, Error> {
+        let max_retries = builder.max_retries;
+        let inner = builder.build_async()?;
+        Ok(Client {
+            backends: Arc::new(Backends::new(inner.url().to_string(), vec![])),
+            http: inner.client().clone(),
+            max_retries,
+            hmac_key: None,
+            cache: None,
+            limiter: None,
+        })
+    }
+
+    /// Fail over to the backends at `urls`, in order, when the primary one is unusable.
+    pub fn fallback_urls(mut self, urls: Vec<String>) -> Self {
+        let primary = self.backends.primary().to_string();
+        self.backends = Arc::new(Backends::new(primary, urls));
+        self
+    }
+
+    /// Sign every request with an HMAC-SHA256 over its method, path and a timestamp.
+    ///
+    /// The signed message is `METHOD\nPATH\nTIMESTAMP`, where the path includes any query
+    /// string. The timestamp and hex signature are sent in the [`TIMESTAMP_HEADER`] and
+    /// [`SIGNATURE_HEADER`] headers.
+    pub fn hmac_key(mut self, key: &[u8]) -> Self {
+        self.hmac_key = Some(key.into());
+        self
+    }
+
+    /// Serve immutable data (block headers, and transactions, block hashes and merkle proofs
+    /// buried at least [`FINAL_DEPTH`] blocks deep) from `cache`.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Retry a failed request at most `max_retries` times.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Send at most `rps` requests per second, counting retries and failovers.
+    pub fn max_rps(mut self, rps: f64) -> Self {
+        self.limiter = Some(Arc::new(RateLimiter::new(rps)));
+        self
+    }
+
+    /// The HTTP client, going through the configured proxy and sending the configured headers.
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// Whether requests are signed with an HMAC key.
+    pub fn is_signed(&self) -> bool {
+        self.hmac_key.is_some()
+    }
+
+    /// Whether requests are rate limited.
+    pub fn is_rate_limited(&self) -> bool {
+        self.limiter.is_some()
+    }
+
+    /// An [`esplora_client::AsyncClient`] for the primary backend, sharing the HTTP client and
+    /// so its headers and timeout. Its requests aren't rate limited, signed or failed over.
+    pub fn async_client(&self) -> esplora_client::AsyncClient {
+        esplora_client::AsyncClient::from_client(
+            self.backends.primary().to_string(),
+            self.http.clone(),
+        )
+    }
+
+    /// Send a request to `path`, failing over to the next backend while the current one is
+    /// unusable.
+    ///
+    /// Unsuccessful responses are returned as [`Error::HttpResponse`].
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<Response, Error> {
+        let mut failure = None;
+        for (index, base_url) in self.backends.ordered() {
+            let url = format!("{}{}", base_url, path);
+            match self.send_to(&url, method.clone(), body.clone()).await {
+                Ok(response) => {
+                    self.backends.served(index);
+                    return Ok(response);
+                }
+                Err(e) if multi::is_backend_failure(&e) => {
+                    tracing::debug!("backend {} is unusable: {}", base_url, e);
+                    failure = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(failure.unwrap_or(Error::InvalidResponse))
+    }
+
+    /// Send a request to `url`, retrying GET requests on retryable status codes.
+    async fn send_to(
+        &self,
+        url: &str,
+        method: Method,
+        body: Option<String>,
+    ) -> Result<Response, Error> {
+        let mut delay = BASE_BACKOFF;
+        let mut attempts = 0;
+
+        loop {
+            // Wait for the limiter before signing, so that the signature's timestamp is fresh
+            // when the request goes out.
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let mut request = self.http.request(method.clone(), url);
+            if let Some(body) = &body {
+                request = request.body(body.clone());
+            }
+            let request = self.sign(request.build()?);
+            let start = Instant::now();
+            let response = match self.http.execute(request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::debug!("{} {} failed after {:?}: {}", method, url, start.elapsed(), e);
+                    return Err(e.into());
+                }
+            };
+            let status = response.status();
+            tracing::debug!("{} {} {} in {:?}", method, url, status.as_u16(), start.elapsed());
+            let response = if tracing::enabled!(tracing::Level::TRACE) {
+                log_body(response).await?
+            } else {
+                response
+            };
+
+            if method == Method::GET
+                && attempts < self.max_retries
+                && RETRYABLE_ERROR_CODES.contains(&status.as_u16())
+            {
+                tracing::debug!(
+                    "retrying {} in {:?}, attempt {} of {}",
+                    url,
+                    delay,
+                    attempts + 1,
+                    self.max_retries
+                );
+                tokio::time::sleep(delay).await;
+                attempts += 1;
+                delay *= 2;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(Error::HttpResponse {
+                    status: status.as_u16(),
+                    message: response.text().await?,
+                });
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Attach the signature headers to `request` if a key is set.
+    fn sign(&self, mut request: Request) -> Request {
+        let Some(key) = &self.hmac_key else {
+            return request;
+        };
+        let timestamp = fees::now().to_string();
+        let path = match request.url().query() {
+            Some(query) => format!("{}?{}", request.url().path(), query),
+            None => request.url().path().to_string(),
+        };
+
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+        engine.input(format!("{}\n{}\n{}", request.method(), path, timestamp).as_bytes());
+        let signature = hmac::Hmac::<sha256::Hash>::from_engine(engine).to_string();
+
+        let headers = request.headers_mut();
+        // Both values are ASCII, so they are always valid header values.
+        if let (Ok(timestamp), Ok(signature)) = (timestamp.parse(), signature.parse()) {
+            headers.insert(TIMESTAMP_HEADER, timestamp);
+            headers.insert(SIGNATURE_HEADER, signature);
+        }
+        request
+    }
+
+    /// Make a GET request to `path` through the cache, if any.
+    ///
+    /// On a cache miss the response is stored once `is_final` confirms it can't change anymore.
+    async fn get_cached<F>(
+        &self,
+        path: &str,
+        is_final: impl FnOnce() -> F,
+    ) -> Result<Vec<u8>, Error>
+    where
+        F: Future<Output = Result<bool, Error>>,
+    {
+        let Some(cache) = &self.cache else {
+            let response = self.send(Method::GET, path, None).await?;
+            return Ok(response.bytes().await?.to_vec());
+        };
+        if let Some(body) = cache.get(path) {
+            return Ok(body);
+        }
+        let response = self.send(Method::GET, path, None).await?;
+        let body = response.bytes().await?.to_vec();
+        if is_final().await? {
+            cache.put(path, &body);
+        }
+        Ok(body)
+    }
+
+    /// Whether the block at `height` is at least [`FINAL_DEPTH`] blocks deep.
+    async fn is_final_height(&self, height: u32) -> Result<bool, Error> {
+        Ok(height + FINAL_DEPTH <= self.get_height().await?)
+    }
+
+    /// Whether the transaction `txid` is confirmed at least [`FINAL_DEPTH`] blocks deep.
+    async fn is_final_tx(&self, txid: &Txid) -> Result<bool, Error> {
+        match self.get_tx_status(txid).await? {
+            TxStatus {
+                confirmed: true,
+                block_height: Some(height),
+                ..
+            } => self.is_final_height(height).await,
+            _ => Ok(false),
+        }
+    }
+
+    /// Make a GET request to `path`, deserializing the consensus encoded response to `T`.
+    async fn get_response<T: Decodable>(&self, path: &str) -> Result<T, Error> {
+        let response = self.send(Method::GET, path, None).await?;
+        Ok(deserialize::<T>(&response.bytes().await?)?)
+    }
+
+    /// Make a GET request to `path`, deserializing the JSON response to `T`.
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let response = self.send(Method::GET, path, None).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    /// Make a GET request to `path`, deserializing to `Option<T>` where a 404 maps to `None`.
+    pub async fn get_opt_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, Error> {
+        opt(self.get_json(path).await)
+    }
+
+    /// Make a GET request to `path`, returning the response body as text.
+    pub async fn get_text(&self, path: &str) -> Result<String, Error> {
+        let response = self.send(Method::GET, path, None).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Make a GET request to `path`, returning the response body as text where a 404 maps to
+    /// `None`.
+    pub async fn get_opt_text(&self, path: &str) -> Result<Option<String>, Error> {
+        opt(self.get_text(path).await)
+    }
+
+    /// Get a [`Transaction`] option given its [`Txid`].
+    pub async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let path = format!("/tx/{}/raw", txid);
+        match opt(self.get_cached(&path, || self.is_final_tx(txid)).await)? {
+            Some(body) => Ok(Some(deserialize(&body)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a [`Txid`] of a transaction given its index in a block with a given hash.
+    pub async fn get_txid_at_block_index(
+        &self,
+        block_hash: &BlockHash,
+        index: usize,
+    ) -> Result<Option<Txid>, Error> {
+        match opt(self.get_text(&format!("/block/{}/txid/{}", block_hash, index)).await)? {
+            Some(s) => Ok(Some(Txid::from_str(&s).map_err(Error::HexToArray)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the status of a [`Transaction`] given its [`Txid`].
+    pub async fn get_tx_status(&self, txid: &Txid) -> Result<TxStatus, Error> {
+        self.get_json(&format!("/tx/{}/status", txid)).await
+    }
+
+    /// Get transaction info given its [`Txid`].
+    pub async fn get_tx_info(&self, txid: &Txid) -> Result<Option<Tx>, Error> {
+        self.get_opt_json(&format!("/tx/{}", txid)).await
+    }
+
+    /// Get the spend status of a [`Transaction`]'s outputs, given its [`Txid`].
+    pub async fn get_tx_outspends(&self, txid: &Txid) -> Result<Vec<OutputStatus>, Error> {
+        self.get_json(&format!("/tx/{}/outspends", txid)).await
+    }
+
+    /// Get a [`BlockHeader`] given a particular block hash.
+    pub async fn get_header_by_hash(&self, block_hash: &BlockHash) -> Result<BlockHeader, Error> {
+        let path = format!("/block/{}/header", block_hash);
+        // Headers are looked up by hash, so they are immutable right away.
+        let hex = self.get_cached(&path, || async { Ok(true) }).await?;
+        deserialize_hex(&hex)
+    }
+
+    /// Get the [`BlockStatus`] given a particular [`BlockHash`].
+    pub async fn get_block_status(&self, block_hash: &BlockHash) -> Result<BlockStatus, Error> {
+        self.get_json(&format!("/block/{}/status", block_hash)).await
+    }
+
+    /// Get a [`Block`] given a particular [`BlockHash`].
+    pub async fn get_block_by_hash(&self, block_hash: &BlockHash) -> Result<Option<Block>, Error> {
+        opt(self.get_response(&format!("/block/{}/raw", block_hash)).await)
+    }
+
+    /// Get a merkle inclusion proof for a [`Transaction`] with the given [`Txid`].
+    pub async fn get_merkle_proof(&self, txid: &Txid) -> Result<Option<MerkleProof>, Error> {
+        let path = format!("/tx/{}/merkle-proof", txid);
+        match opt(self.get_cached(&path, || self.is_final_tx(txid)).await)? {
+            Some(body) => Ok(Some(
+                serde_json::from_slice(&body).map_err(|_| Error::InvalidResponse)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a [`MerkleBlock`] inclusion proof for a [`Transaction`] with the given [`Txid`].
+    pub async fn get_merkle_block(&self, txid: &Txid) -> Result<Option<MerkleBlock>, Error> {
+        let path = format!("/tx/{}/merkleblock-proof", txid);
+        match opt(self.get_cached(&path, || self.is_final_tx(txid)).await)? {
+            Some(hex) => Ok(Some(deserialize_hex(&hex)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the spending status of an output given a [`Txid`] and the output index.
+    pub async fn get_output_status(
+        &self,
+        txid: &Txid,
+        index: u64,
+    ) -> Result<Option<OutputStatus>, Error> {
+        self.get_opt_json(&format!("/tx/{}/outspend/{}", txid, index)).await
+    }
+
+    /// Broadcast a [`Transaction`].
+    pub async fn broadcast(&self, transaction: &Transaction) -> Result<(), Error> {
+        let body = serialize(transaction).to_lower_hex_string();
+        self.send(Method::POST, "/tx", Some(body)).await?;
+        Ok(())
+    }
+
+    /// Submit `transactions`, parents first and the child last, as a package.
+    ///
+    /// Returns `None` if the backend has no package endpoint.
+    pub async fn submit_package(
+        &self,
+        transactions: &[Transaction],
+    ) -> Result<Option<SubmitPackageResult>, Error> {
+        let hexes: Vec<String> = transactions
+            .iter()
+            .map(|tx| serialize(tx).to_lower_hex_string())
+            .collect();
+        let body = serde_json::json!(hexes).to_string();
+        match self.send(Method::POST, "/txs/package", Some(body)).await {
+            Ok(response) => Ok(Some(response.json().await?)),
+            Err(Error::HttpResponse {
+                status: 404 | 405 | 501,
+                ..
+            }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the current height of the blockchain tip.
+    pub async fn get_height(&self) -> Result<u32, Error> {
+        let height = self.get_text("/blocks/tip/height").await?;
+        u32::from_str(&height).map_err(Error::Parsing)
+    }
+
+    /// Get the [`BlockHash`] of the current blockchain tip.
+    pub async fn get_tip_hash(&self) -> Result<BlockHash, Error> {
+        let hash = self.get_text("/blocks/tip/hash").await?;
+        BlockHash::from_str(&hash).map_err(Error::HexToArray)
+    }
+
+    /// Get the [`BlockHash`] of a specific block height.
+    pub async fn get_block_hash(&self, block_height: u32) -> Result<BlockHash, Error> {
+        let path = format!("/block-height/{}", block_height);
+        let hash = self.get_cached(&path, || self.is_final_height(block_height)).await?;
+        BlockHash::from_str(&String::from_utf8_lossy(&hash)).map_err(Error::HexToArray)
+    }
+
+    /// Get statistics about the confirmed and mempool transactions of an [`Address`].
+    pub async fn get_address_stats(&self, address: &Address) -> Result<AddressStats, Error> {
+        self.get_json(&format!("/address/{}", address)).await
+    }
+
+    /// Get the addresses the backend knows of starting with `prefix`, at most 10.
+    pub async fn search_address(&self, prefix: &str) -> Result<Option<Vec<String>>, Error> {
+        self.get_opt_json(&format!("/address-prefix/{}", prefix)).await
+    }
+
+    /// Get transaction history for the specified script, sorted with newest first.
+    ///
+    /// Returns up to 50 mempool transactions plus the first 25 confirmed transactions. More can
+    /// be requested by specifying the last txid seen by the previous query.
+    pub async fn scripthash_txs(
+        &self,
+        script: &Script,
+        last_seen: Option<Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        self.scripthash_txs_by_hash(&sha256::Hash::hash(script.as_bytes()), last_seen)
+            .await
+    }
+
+    /// Get transaction history for the script whose SHA256 is `script_hash`, as
+    /// [`Client::scripthash_txs`].
+    pub async fn scripthash_txs_by_hash(
+        &self,
+        script_hash: &sha256::Hash,
+        last_seen: Option<Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        let path = match last_seen {
+            Some(last_seen) => format!("/scripthash/{:x}/txs/chain/{}", script_hash, last_seen),
+            None => format!("/scripthash/{:x}/txs", script_hash),
+        };
+        self.get_json(&path).await
+    }
+
+    /// Get statistics about the mempool.
+    pub async fn get_mempool_stats(&self) -> Result<MempoolStats, Error> {
+        self.get_json("/mempool").await
+    }
+
+    /// Get the txids of all transactions in the mempool.
+    pub async fn get_mempool_txids(&self) -> Result<Vec<Txid>, Error> {
+        self.get_json("/mempool/txids").await
+    }
+
+    /// Get a map of confirmation target (in blocks) to the estimated feerate (in sat/vB).
+    pub async fn get_fee_estimates(&self) -> Result<HashMap<u16, f64>, Error> {
+        self.get_json("/fee-estimates").await
+    }
+
+    /// Get a summary about a [`Block`], given its [`BlockHash`].
+    pub async fn get_block_info(&self, blockhash: &BlockHash) -> Result<BlockInfo, Error> {
+        self.get_json(&format!("/block/{}", blockhash)).await
+    }
+
+    /// Get all [`Txid`]s that belong to a [`Block`] identified by its [`BlockHash`].
+    pub async fn get_block_txids(&self, blockhash: &BlockHash) -> Result<Vec<Txid>, Error> {
+        self.get_json(&format!("/block/{}/txids", blockhash)).await
+    }
+
+    /// Get up to 25 transactions of a [`Block`], starting at index `start`, which must be a
+    /// multiple of 25.
+    pub async fn get_block_txs(&self, blockhash: &BlockHash, start: u32) -> Result<Vec<Tx>, Error> {
+        self.get_json(&format!("/block/{}/txs/{}", blockhash, start)).await
+    }
+
+    /// Get summaries about recent blocks, starting at the tip, or at `height` if provided.
+    ///
+    /// The maximum number of blocks returned depends on the backend: esplora returns 10 while
+    /// mempool.space returns 15.
+    pub async fn get_block_infos(&self, height: Option<u32>) -> Result<Vec<BlockInfo>, Error> {
+        let path = match height {
+            Some(height) => format!("/blocks/{}", height),
+            None => "/blocks".to_string(),
+        };
+        let blocks: Vec<BlockInfo> = self.get_json(&path).await?;
+        if blocks.is_empty() {
+            return Err(Error::InvalidResponse);
+        }
+        Ok(blocks)
+    }
+
+    /// Get summaries of the blocks from height `from` to `to`, lowest first, requesting a page
+    /// at a time as the stream is consumed.
+    ///
+    /// Summaries are kept as the JSON the backend returned, with any fields specific to it.
+    pub fn get_block_range(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> impl Stream<Item = Result<serde_json::Value, Error>> + '_ {
+        // Esplora's page size, the smallest of the known backends.
+        let page = 10;
+        futures::stream::try_unfold(
+            (from, page, VecDeque::new()),
+            move |(mut next, mut page, mut ready)| async move {
+                loop {
+                    if let Some(block) = ready.pop_front() {
+                        return Ok(Some((block, (next, page, ready))));
+                    }
+                    if next > to {
+                        return Ok(None);
+                    }
+                    let top = to.min(next.saturating_add(page - 1));
+                    let blocks: Vec<serde_json::Value> =
+                        self.get_json(&format!("/blocks/{}", top)).await?;
+                    let mut blocks: Vec<(u64, serde_json::Value)> = blocks
+                        .into_iter()
+                        .filter_map(|block| Some((block["height"].as_u64()?, block)))
+                        .collect();
+                    let lowest = blocks
+                        .iter()
+                        .map(|(height, _)| *height)
+                        .min()
+                        .ok_or(Error::InvalidResponse)?;
+                    if lowest > u64::from(next) {
+                        // The backend's pages are shorter than assumed, ask again for fewer.
+                        page = blocks.len() as u32;
+                        continue;
+                    }
+                    page = page.max(blocks.len() as u32);
+                    blocks.retain(|(height, _)| *height >= u64::from(next));
+                    blocks.sort_by_key(|(height, _)| *height);
+                    ready.extend(blocks.into_iter().map(|(_, block)| block));
+                    next = top + 1;
+                }
+            },
+        )
+    }
+}
+
+/// Deserialize a hex encoded response body to `T`.
+fn deserialize_hex<T: Decodable>(body: &[u8]) -> Result<T, Error> {
+    Ok(deserialize(&Vec::from_hex(&String::from_utf8_lossy(body))?)?)
+}
+
+/// Log the body of `response` and hand back an equivalent response to read it from.
+async fn log_body(response: Response) -> Result<Response, Error> {
+    let mut builder = http::Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let body = response.bytes().await?;
+    match std::str::from_utf8(&body) {
+        Ok(text) => tracing::trace!("response body: {}", text),
+        Err(_) => tracing::trace!("response body: {} binary bytes", body.len()),
+    }
+    let response = builder.body(body).map_err(|_| Error::InvalidResponse)?;
+    Ok(Response::from(response))
+}
+
+/// Map a 404 response to `None`.
+fn opt<T>(res: Result<T, Error>) -> Result<Option<T>, Error> {
+    match res {
+        Ok(res) => Ok(Some(res)),
+        Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,195 @@
+//! Decoding of a block's coinbase transaction: the height it commits to, the miner's tag, where
+//! the reward went and how much of it is fees.
+
+use std::fmt::Write as _;
+
+use bitcoin::hex::DisplayHex;
+use bitcoin::opcodes::all::{OP_PUSHNUM_1, OP_PUSHNUM_16};
+use bitcoin::script::{self, Instruction};
+use bitcoin::{Address, BlockHash, Script, Txid};
+use serde::Serialize;
+
+use crate::blockstats::subsidy;
+use crate::client::Client;
+use crate::error;
+use crate::pretty;
+use crate::rbf::script_kind;
+use crate::show::BlockRef;
+
+/// Start of a BIP141 witness commitment output script: `OP_RETURN`, a 36 byte push and the
+/// commitment header.
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// Shortest run of printable ASCII in the scriptSig taken as a miner tag.
+const MIN_TAG_LEN: usize = 4;
+
+/// An output of a coinbase transaction.
+#[derive(Debug, Serialize)]
+pub struct CoinbaseOutput {
+    /// Value in satoshis.
+    pub value: u64,
+    /// Standard type of the script it pays to, or `op_return`.
+    pub script_type: &'static str,
+    /// The address it pays to, `None` if its script has none or the network is unknown.
+    pub address: Option<String>,
+}
+
+/// A decoded coinbase transaction.
+#[derive(Debug, Serialize)]
+pub struct Coinbase {
+    pub block: BlockHash,
+    pub height: u32,
+    pub txid: Txid,
+    /// The height pushed first in the scriptSig as BIP34 requires, if it decodes as one.
+    pub bip34_height: Option<u32>,
+    /// The scriptSig, hex encoded.
+    pub script_sig: String,
+    /// Runs of printable ASCII in the scriptSig after the height, such as the pool's name.
+    pub tags: Vec<String>,
+    pub outputs: Vec<CoinbaseOutput>,
+    /// Total value of the outputs in satoshis.
+    pub reward: u64,
+    /// The block subsidy at its height in satoshis.
+    pub subsidy: u64,
+    /// The part of the reward above the subsidy, i.e. the fees collected, in satoshis.
+    pub fees: u64,
+    /// Whether an output commits to the block's witnesses as BIP141 requires of blocks with
+    /// segwit transactions.
+    pub witness_commitment: bool,
+}
+
+/// Fetch and decode the coinbase transaction of `block`.
+pub async fn coinbase(client: &Client, block: BlockRef) -> anyhow::Result<Coinbase> {
+    let hash = block.resolve(client).await?;
+    let info = client.get_block_info(&hash).await?;
+    let txid = client
+        .get_txid_at_block_index(&hash, 0)
+        .await?
+        .ok_or_else(|| error::not_found(format!("block {}", hash)))?;
+    let tx = client
+        .get_tx(&txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+    if !tx.is_coinbase() {
+        return Err(error::invalid_input(format!("{} is not a coinbase transaction", txid)));
+    }
+    let network = pretty::network(client).await?;
+
+    let script_sig = &tx.input[0].script_sig;
+    let outputs = tx
+        .output
+        .iter()
+        .map(|output| CoinbaseOutput {
+            value: output.value.to_sat(),
+            script_type: if output.script_pubkey.is_op_return() {
+                "op_return"
+            } else {
+                script_kind(&output.script_pubkey)
+            },
+            address: network
+                .and_then(|n| Address::from_script(&output.script_pubkey, n).ok())
+                .map(|address| address.to_string()),
+        })
+        .collect();
+    let reward = tx.output.iter().map(|output| output.value.to_sat()).sum();
+    let subsidy = subsidy(info.height);
+    Ok(Coinbase {
+        block: hash,
+        height: info.height,
+        txid,
+        bip34_height: bip34_height(script_sig),
+        script_sig: script_sig.as_bytes().to_lower_hex_string(),
+        tags: tags(script_sig),
+        outputs,
+        reward,
+        subsidy,
+        fees: reward.saturating_sub(subsidy),
+        witness_commitment: tx
+            .output
+            .iter()
+            .any(|output| output.script_pubkey.as_bytes().starts_with(&WITNESS_COMMITMENT_PREFIX)),
+    })
+}
+
+/// The height pushed first in `script_sig`, as a script number or small integer opcode.
+fn bip34_height(script_sig: &Script) -> Option<u32> {
+    match script_sig.instructions().next()?.ok()? {
+        Instruction::PushBytes(bytes) => {
+            u32::try_from(script::read_scriptint(bytes.as_bytes()).ok()?).ok()
+        }
+        Instruction::Op(op)
+            if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&op.to_u8()) =>
+        {
+            Some((op.to_u8() - OP_PUSHNUM_1.to_u8() + 1) as u32)
+        }
+        Instruction::Op(_) => None,
+    }
+}
+
+/// The runs of at least [`MIN_TAG_LEN`] printable ASCII characters in `script_sig`, skipping
+/// the height push.
+///
+/// The rest of the scriptSig is arbitrary data that needn't parse as pushes, so it is scanned
+/// as raw bytes.
+fn tags(script_sig: &Script) -> Vec<String> {
+    let bytes = script_sig.as_bytes();
+    let start = match bytes.first() {
+        // A direct push of the height.
+        Some(&len @ 1..=75) => 1 + len as usize,
+        Some(_) => 1,
+        None => 0,
+    };
+    bytes[start.min(bytes.len())..]
+        .split(|b| !(b' '..=b'~').contains(b))
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .filter(|run| run.len() >= MIN_TAG_LEN)
+        .collect()
+}
+
+/// Render `coinbase` for the terminal.
+pub fn report(coinbase: &Coinbase) -> String {
+    let btc = |sat: u64| format!("{} BTC", pretty::btc(sat));
+    let mut out = String::new();
+    let _ = writeln!(out, "block    {} ({})", coinbase.block, coinbase.height);
+    let _ = writeln!(out, "txid     {}", coinbase.txid);
+    let bip34 = match coinbase.bip34_height {
+        Some(height) if height == coinbase.height => format!("{}", height),
+        Some(height) => format!("{} (block is at {})", height, coinbase.height),
+        None => "none".to_string(),
+    };
+    let _ = writeln!(out, "bip34    {}", bip34);
+    let _ = writeln!(out, "script   {}", coinbase.script_sig);
+    if !coinbase.tags.is_empty() {
+        let _ = writeln!(out, "tag      {}", coinbase.tags.join(" "));
+    }
+
+    let _ = writeln!(out, "\noutputs");
+    for (i, output) in coinbase.outputs.iter().enumerate() {
+        let address = output
+            .address
+            .clone()
+            .unwrap_or_else(|| format!("({})", output.script_type));
+        let _ = writeln!(out, "{:>4}  {:>20}  {}", i, btc(output.value), address);
+    }
+
+    let _ = writeln!(out, "\nreward   {}", btc(coinbase.reward));
+    let _ = writeln!(out, "subsidy  {}", btc(coinbase.subsidy));
+    let _ = writeln!(out, "fees     {}", btc(coinbase.fees));
+    if coinbase.reward < coinbase.subsidy {
+        let _ = writeln!(
+            out,
+            "the miner claimed {} less than the subsidy",
+            btc(coinbase.subsidy - coinbase.reward)
+        );
+    }
+    let _ = writeln!(
+        out,
+        "witness commitment: {}",
+        if coinbase.witness_commitment {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    out
+}
@@ -0,0 +1,162 @@
+//! User configuration and well-known directories.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+/// Default backends of the networks selectable by name with `--network`.
+const NETWORK_URLS: &[(&str, &str)] = &[
+    ("bitcoin", "https://blockstream.info/api"),
+    ("testnet", "https://blockstream.info/testnet/api"),
+    ("testnet4", "https://mempool.space/testnet4/api"),
+    ("signet", "https://mempool.space/signet/api"),
+    ("liquid", "https://blockstream.info/liquid/api"),
+    ("liquidtestnet", "https://blockstream.info/liquidtestnet/api"),
+];
+
+/// User configuration read from `$XDG_CONFIG_HOME/esplora-cli/config.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Profile to use when no backend is selected with a flag or environment variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Named backends selectable with `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named esplora backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Base url of the esplora API.
+    pub url: String,
+    /// Backends of the same network to fail over to, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_urls: Vec<String>,
+    /// HTTP headers to send with every request, such as an API key.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Token to send as `Authorization: Bearer <token>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+    /// Maximum number of requests per second to send.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rps: Option<f64>,
+    /// Proxy to send requests through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+impl Config {
+    /// Load the configuration, returning the default if no config file exists.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config_path()?;
+        match fs::read_to_string(&path) {
+            Ok(s) => toml::from_str(&s).with_context(|| format!("invalid {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    /// Write the configuration back to the config file.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = config_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Look up the profile `name`.
+    pub fn profile(&self, name: &str) -> anyhow::Result<&Profile> {
+        self.profiles.get(name).ok_or(anyhow!("no profile named `{}`", name))
+    }
+
+    /// Look up the `default_profile`, if one is set.
+    pub fn default_profile(&self) -> anyhow::Result<Option<&Profile>> {
+        self.default_profile
+            .as_deref()
+            .map(|name| self.profile(name))
+            .transpose()
+    }
+}
+
+/// Resolve a `--network` value to a backend url.
+///
+/// Known network names map to a default public backend, anything else is taken to be the url
+/// of an esplora API.
+pub fn network_url(network: &str) -> String {
+    NETWORK_URLS
+        .iter()
+        .find(|(name, _)| *name == network)
+        .map_or_else(|| network.to_string(), |(_, url)| url.to_string())
+}
+
+/// Parse a `Name: value` HTTP header.
+pub fn parse_header(s: &str) -> anyhow::Result<(String, String)> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or(anyhow!("expected `Name: value`, got `{}`", s))?;
+    let name = name.trim();
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(anyhow!("invalid header name `{}`", name));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// The headers to send with every request: those of `profile` overridden by `headers`, plus
+/// an `Authorization` header for `bearer_token`, or else for the profile's token.
+///
+/// Names are lowercased, as HTTP header names are case-insensitive.
+pub fn request_headers(
+    profile: Option<&Profile>,
+    headers: &[(String, String)],
+    bearer_token: Option<&str>,
+) -> BTreeMap<String, String> {
+    let mut all: BTreeMap<String, String> = profile
+        .into_iter()
+        .flat_map(|p| &p.headers)
+        .chain(headers.iter().map(|(name, value)| (name, value)))
+        .map(|(name, value)| (name.to_lowercase(), value.clone()))
+        .collect();
+    if let Some(token) = bearer_token.or(profile.and_then(|p| p.bearer_token.as_deref())) {
+        all.insert("authorization".to_string(), format!("Bearer {}", token));
+    }
+    all
+}
+
+/// Path of the config file.
+pub fn config_path() -> anyhow::Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+/// The esplora-cli config directory, `$XDG_CONFIG_HOME/esplora-cli`.
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// The esplora-cli data directory, `$XDG_DATA_HOME/esplora-cli`.
+pub fn data_dir() -> anyhow::Result<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// The esplora-cli state directory, `$XDG_STATE_HOME/esplora-cli`.
+pub fn state_dir() -> anyhow::Result<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// Resolve `$var/esplora-cli`, falling back to `$HOME/<fallback>/esplora-cli`.
+fn xdg_dir(var: &str, fallback: &str) -> anyhow::Result<PathBuf> {
+    let base = match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = std::env::var_os("HOME").ok_or(anyhow!("HOME is not set"))?;
+            PathBuf::from(home).join(fallback)
+        }
+    };
+    Ok(base.join("esplora-cli"))
+}
@@ -0,0 +1,275 @@
+//! Fee bumping of unconfirmed transactions by a child paying for its parent (CPFP).
+//!
+//! The confirmation time is judged from the mempool.space projected blocks, other backends
+//! don't provide them.
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use bitcoin::{Script, Txid};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::fees;
+use crate::mempool::{self, ProjectedBlock};
+use crate::rbf::script_kind;
+
+/// Maximum number of ancestors followed, Core's default `-limitancestorcount`.
+const MAX_ANCESTORS: usize = 25;
+
+/// Feerate in sat/vB the child pays at least, Core's default `-minrelaytxfee`.
+const MIN_RELAY_FEERATE: f64 = 1.0;
+
+/// What it takes for a child to lift an unconfirmed transaction to a target feerate.
+#[derive(Debug, Serialize)]
+pub struct Cpfp {
+    /// The transaction to bump.
+    pub txid: Txid,
+    /// Its fee, in satoshis.
+    pub fee: u64,
+    /// Its virtual size, in vbytes.
+    pub vsize: u64,
+    /// Number of unconfirmed ancestors the child pays for as well.
+    pub ancestors: usize,
+    /// Fee of those ancestors, in satoshis.
+    pub ancestors_fee: u64,
+    /// Virtual size of those ancestors, in vbytes.
+    pub ancestors_vsize: u64,
+    /// Backend estimate used, as confirmation target and feerate in sat/vB.
+    pub estimate: Option<(u16, f64)>,
+    /// Feerate the package should reach, in sat/vB.
+    pub target_feerate: f64,
+    /// Projected blocks until the transaction would be mined as it is.
+    pub blocks_now: Option<usize>,
+    /// Projected blocks until the package would be mined at the target feerate.
+    pub blocks_bumped: Option<usize>,
+    /// Unspent outputs a child could spend from.
+    pub outputs: Vec<Spendable>,
+}
+
+impl Cpfp {
+    /// Fee and virtual size of the transaction with its unconfirmed ancestors.
+    pub fn package(&self) -> (u64, u64) {
+        (self.fee + self.ancestors_fee, self.vsize + self.ancestors_vsize)
+    }
+}
+
+/// An unspent output of the transaction, and the child that would spend it.
+#[derive(Debug, Serialize)]
+pub struct Spendable {
+    /// Index of the output.
+    pub vout: u32,
+    /// Value in satoshis.
+    pub value: u64,
+    /// Type of its script.
+    pub kind: &'static str,
+    /// The child, `None` when its size can't be guessed from the script type.
+    pub child: Option<Child>,
+}
+
+/// A child spending one output to one output of the same type.
+#[derive(Debug, Serialize)]
+pub struct Child {
+    /// Estimated virtual size, in vbytes.
+    pub vsize: u64,
+    /// Fee it must pay, in satoshis.
+    pub fee: u64,
+    /// Its own feerate, in sat/vB.
+    pub feerate: f64,
+    /// Whether the output is worth enough to pay the fee and keep a non-dust output.
+    pub affordable: bool,
+}
+
+/// Work out the child that lifts `txid` and its unconfirmed ancestors to `feerate`, or to the
+/// estimate for `target` blocks if no feerate is given.
+///
+/// `child_vsize` overrides the size guessed for each output from its script type.
+pub async fn cpfp(
+    client: &Client,
+    txid: &Txid,
+    target: u16,
+    feerate: Option<f64>,
+    child_vsize: Option<u64>,
+) -> anyhow::Result<Cpfp> {
+    let tx = client
+        .get_tx_info(txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+    if tx.status.confirmed {
+        return Err(anyhow!("transaction {} is already confirmed", txid));
+    }
+    let vsize = tx.weight.div_ceil(4);
+    let (ancestors, ancestors_fee, ancestors_vsize) = ancestors(client, &tx).await?;
+
+    let estimate = match feerate {
+        Some(_) => None,
+        None => fees::estimate_for(&client.get_fee_estimates().await?, target),
+    };
+    let target_feerate = feerate
+        .or(estimate.map(|(_, rate)| rate))
+        .ok_or(anyhow!("backend returned no fee estimates, pass --feerate"))?;
+
+    let package_fee = tx.fee + ancestors_fee;
+    let package_vsize = vsize + ancestors_vsize;
+    let outputs = client
+        .get_tx_outspends(txid)
+        .await?
+        .iter()
+        .zip(&tx.vout)
+        .enumerate()
+        .filter(|(_, (status, vout))| !status.spent && !vout.scriptpubkey.is_op_return())
+        .map(|(i, (_, vout))| {
+            let child =
+                child_vsize
+                    .or_else(|| typical_child_vsize(&vout.scriptpubkey))
+                    .map(|vsize| {
+                        let needed = target_feerate * (package_vsize + vsize) as f64;
+                        let fee = (needed.ceil() as u64)
+                            .saturating_sub(package_fee)
+                            .max((MIN_RELAY_FEERATE * vsize as f64).ceil() as u64);
+                        Child {
+                            vsize,
+                            fee,
+                            feerate: fee as f64 / vsize as f64,
+                            affordable: vout.value.checked_sub(fee).is_some_and(|rest| {
+                                rest >= vout.scriptpubkey.minimal_non_dust().to_sat()
+                            }),
+                        }
+                    });
+            Spendable {
+                vout: i as u32,
+                value: vout.value,
+                kind: script_kind(&vout.scriptpubkey),
+                child,
+            }
+        })
+        .collect();
+
+    let blocks = mempool::projected_blocks(client).await?;
+    let eta = |rate: f64| {
+        blocks
+            .as_deref()
+            .and_then(|b: &[ProjectedBlock]| mempool::blocks_until(b, rate))
+    };
+
+    Ok(Cpfp {
+        txid: *txid,
+        fee: tx.fee,
+        vsize,
+        ancestors,
+        ancestors_fee,
+        ancestors_vsize,
+        estimate,
+        target_feerate,
+        blocks_now: eta(package_fee as f64 / package_vsize as f64),
+        blocks_bumped: eta(target_feerate),
+        outputs,
+    })
+}
+
+/// The number, total fee and total virtual size of the unconfirmed ancestors of `tx`.
+async fn ancestors(client: &Client, tx: &esplora_client::Tx) -> anyhow::Result<(usize, u64, u64)> {
+    let mut seen = HashSet::new();
+    let mut queue: Vec<Txid> = tx.vin.iter().map(|vin| vin.txid).collect();
+    let (mut fee, mut vsize) = (0, 0);
+    while let Some(txid) = queue.pop() {
+        if seen.len() >= MAX_ANCESTORS || seen.contains(&txid) {
+            continue;
+        }
+        let Some(parent) = client.get_tx_info(&txid).await? else {
+            continue;
+        };
+        if parent.status.confirmed {
+            continue;
+        }
+        seen.insert(txid);
+        fee += parent.fee;
+        vsize += parent.weight.div_ceil(4);
+        queue.extend(parent.vin.iter().map(|vin| vin.txid));
+    }
+    Ok((seen.len(), fee, vsize))
+}
+
+/// Virtual size of a child spending a single-key output of `script` to one output of the
+/// same type, `None` for script types whose spending size depends on the script.
+///
+/// P2SH is taken to wrap P2WPKH, and P2TR to be spent by the key path.
+fn typical_child_vsize(script: &Script) -> Option<u64> {
+    match script_kind(script) {
+        "p2pkh" => Some(192),
+        "p2sh" => Some(134),
+        "p2wpkh" => Some(110),
+        "p2tr" => Some(111),
+        _ => None,
+    }
+}
+
+/// Render `cpfp` for the terminal.
+pub fn report(cpfp: &Cpfp) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("txid: {}\n", cpfp.txid));
+    out.push_str(&format!(
+        "current: {} sat, {} vB, {:.2} sat/vB\n",
+        cpfp.fee,
+        cpfp.vsize,
+        cpfp.fee as f64 / cpfp.vsize as f64
+    ));
+    let (package_fee, package_vsize) = cpfp.package();
+    if cpfp.ancestors > 0 {
+        out.push_str(&format!(
+            "unconfirmed ancestors: {} paying {} sat for {} vB\n",
+            cpfp.ancestors, cpfp.ancestors_fee, cpfp.ancestors_vsize
+        ));
+        out.push_str(&format!(
+            "package: {} sat, {} vB, {:.2} sat/vB\n",
+            package_fee,
+            package_vsize,
+            package_fee as f64 / package_vsize as f64
+        ));
+    }
+    match cpfp.estimate {
+        Some((target, rate)) => {
+            out.push_str(&format!("estimate for {} blocks: {:.2} sat/vB\n", target, rate))
+        }
+        None => out.push_str(&format!("target: {:.2} sat/vB\n", cpfp.target_feerate)),
+    }
+    out.push_str(&format!(
+        "projected confirmation: {} now, {} bumped\n",
+        mempool::describe_blocks(cpfp.blocks_now),
+        mempool::describe_blocks(cpfp.blocks_bumped)
+    ));
+
+    if cpfp.outputs.is_empty() {
+        out.push_str("no unspent outputs to spend from\n");
+        return out;
+    }
+    out.push_str(&format!(
+        "\n{:>4}  {:>12}  {:<6}  {:>8}  {:>10}  {:>10}\n",
+        "vout", "value", "type", "child vB", "child fee", "sat/vB"
+    ));
+    for output in &cpfp.outputs {
+        let child = match &output.child {
+            Some(child) => format!(
+                "{:>8}  {:>10}  {:>10.2}{}",
+                child.vsize,
+                child.fee,
+                child.feerate,
+                if child.affordable {
+                    ""
+                } else {
+                    "  (too small)"
+                }
+            ),
+            None => format!("{:>8}  {:>10}  {:>10}", "?", "?", "?"),
+        };
+        out.push_str(&format!(
+            "{:>4}  {:>12}  {:<6}  {}\n",
+            output.vout, output.value, output.kind, child
+        ));
+    }
+    if cpfp.outputs.iter().any(|o| o.child.is_none()) {
+        out.push_str("pass --child-vsize for outputs whose spending size can't be guessed\n");
+    }
+    out
+}
@@ -0,0 +1,111 @@
+//! Text dumps of API types for the commands that print them as they are, laid out like their
+//! `Debug` output with the timestamps shown as chosen with `--time-format`.
+
+use std::fmt;
+
+use esplora_client::{BlockInfo, OutputStatus, Tx, TxStatus};
+
+use crate::fees::FeeSnapshot;
+use crate::lightning::Node;
+use crate::timefmt::Time;
+
+/// A value to dump with `{:#?}`.
+pub struct Dump<'a, T: ?Sized>(pub &'a T);
+
+impl fmt::Debug for Dump<'_, TxStatus> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = self.0;
+        f.debug_struct("TxStatus")
+            .field("confirmed", &status.confirmed)
+            .field("block_height", &status.block_height)
+            .field("block_hash", &status.block_hash)
+            .field("block_time", &status.block_time.map(Time))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, Tx> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tx = self.0;
+        f.debug_struct("Tx")
+            .field("txid", &tx.txid)
+            .field("version", &tx.version)
+            .field("locktime", &tx.locktime)
+            .field("vin", &tx.vin)
+            .field("vout", &tx.vout)
+            .field("size", &tx.size)
+            .field("weight", &tx.weight)
+            .field("status", &Dump(&tx.status))
+            .field("fee", &tx.fee)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, OutputStatus> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let output = self.0;
+        f.debug_struct("OutputStatus")
+            .field("spent", &output.spent)
+            .field("txid", &output.txid)
+            .field("vin", &output.vin)
+            .field("status", &output.status.as_ref().map(Dump))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, BlockInfo> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let block = self.0;
+        f.debug_struct("BlockInfo")
+            .field("id", &block.id)
+            .field("height", &block.height)
+            .field("version", &block.version)
+            .field("timestamp", &Time(block.timestamp))
+            .field("tx_count", &block.tx_count)
+            .field("size", &block.size)
+            .field("weight", &block.weight)
+            .field("merkle_root", &block.merkle_root)
+            .field("previousblockhash", &block.previousblockhash)
+            .field("mediantime", &Time(block.mediantime))
+            .field("nonce", &block.nonce)
+            .field("bits", &block.bits)
+            .field("difficulty", &block.difficulty)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, [BlockInfo]> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(Dump)).finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, Node> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node = self.0;
+        f.debug_struct("Node")
+            .field("public_key", &node.public_key)
+            .field("alias", &node.alias)
+            .field("color", &node.color)
+            .field("sockets", &node.sockets)
+            .field("first_seen", &node.first_seen.map(Time))
+            .field("updated_at", &node.updated_at.map(Time))
+            .field("active_channel_count", &node.active_channel_count)
+            .field("opened_channel_count", &node.opened_channel_count)
+            .field("closed_channel_count", &node.closed_channel_count)
+            .field("capacity", &node.capacity)
+            .field("as_organization", &node.as_organization)
+            .field("iso_code", &node.iso_code)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Dump<'_, FeeSnapshot> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let snapshot = self.0;
+        f.debug_struct("FeeSnapshot")
+            .field("time", &Time(snapshot.time))
+            .field("estimates", &snapshot.estimates)
+            .finish()
+    }
+}
@@ -0,0 +1,280 @@
+//! Which unspent outputs of an address or wallet are worth spending, and what consolidating
+//! them into a single output costs.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::{Address, OutPoint, Script, ScriptBuf};
+use esplora_client::Tx;
+use miniscript::{Descriptor, DescriptorPublicKey};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::fees;
+use crate::progress::Progress;
+use crate::rbf::script_kind;
+use crate::scan::{self, Keychain};
+
+/// Weight of an input before its scriptSig and witness: outpoint, sequence and an empty
+/// scriptSig length.
+const TXIN_BASE_WEIGHT: u64 = 4 * (32 + 4 + 4 + 1);
+
+/// Weight of a transaction without inputs and outputs: version, locktime and the input and
+/// output counts.
+const TX_BASE_WEIGHT: u64 = 4 * (4 + 4 + 1 + 1);
+
+/// What is being reported on.
+pub enum Wallet {
+    /// A single address.
+    Address(Address),
+    /// The keychains of a wallet descriptor.
+    Descriptor(Vec<(Keychain, Descriptor<DescriptorPublicKey>)>),
+}
+
+impl Wallet {
+    /// Parse `wallet` as an address, or else as an external descriptor with an optional
+    /// `change` descriptor.
+    pub fn parse(wallet: &str, change: Option<&str>) -> anyhow::Result<Self> {
+        match wallet.parse::<Address<NetworkUnchecked>>() {
+            Ok(address) if change.is_none() => Ok(Wallet::Address(address.assume_checked())),
+            Ok(_) => Err(error::invalid_input("--change only applies to a descriptor")),
+            Err(_) => Ok(Wallet::Descriptor(scan::keychains(wallet, change)?)),
+        }
+    }
+}
+
+/// An unspent output and what spending it costs.
+#[derive(Debug, Serialize)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    /// Value in satoshis.
+    pub value: u64,
+    /// Standard type of the script it pays to.
+    pub script_type: &'static str,
+    pub confirmed: bool,
+    /// Weight it adds to a transaction spending it, `None` if it can't be told from the script.
+    pub input_weight: Option<u64>,
+    /// Fee to spend it at the current feerate, in satoshis.
+    pub spend_cost: Option<u64>,
+    /// Whether spending it at the current feerate costs at least its value.
+    pub uneconomical: bool,
+}
+
+/// A transaction spending every output of known weight to a single output.
+#[derive(Debug, Serialize)]
+pub struct Consolidation {
+    /// Confirmation target of the feerate, in blocks.
+    pub target: u16,
+    /// Feerate in sat/vB.
+    pub feerate: f64,
+    /// Virtual size in vbytes.
+    pub vsize: u64,
+    /// Fee in satoshis.
+    pub fee: u64,
+    /// Value left in the output, negative if the inputs don't cover the fee.
+    pub remaining: i64,
+}
+
+/// The unspent outputs of a wallet and the cost of consolidating them now and at a low-fee
+/// target.
+#[derive(Debug, Serialize)]
+pub struct DustReport {
+    pub utxos: Vec<Utxo>,
+    /// Total value in satoshis.
+    pub total: u64,
+    pub now: Consolidation,
+    pub low: Consolidation,
+}
+
+/// Find the unspent outputs of `wallet` and cost them at the backend's estimates for `target`
+/// and `low_target` blocks.
+///
+/// Descriptor keychains are scanned up to `gap_limit` unused scripts, requesting `parallel`
+/// script histories at once.
+pub async fn report_for(
+    client: &Client,
+    wallet: &Wallet,
+    target: u16,
+    low_target: u16,
+    gap_limit: u32,
+    parallel: u32,
+    progress: &Progress,
+) -> anyhow::Result<DustReport> {
+    // The weight to spend each script of the wallet, and the script to consolidate to.
+    let (weights, txs, output): (HashMap<ScriptBuf, Option<u64>>, Vec<Tx>, ScriptBuf) = match wallet
+    {
+        Wallet::Address(address) => {
+            let script = address.script_pubkey();
+            let txs = scan::script_history(client, &script).await?;
+            let weight = typical_input_weight(&script);
+            (HashMap::from([(script.clone(), weight)]), txs, script)
+        }
+        Wallet::Descriptor(keychains) => {
+            let result = scan::scan(client, keychains, gap_limit, parallel, progress).await?;
+            let descriptors: HashMap<Keychain, &Descriptor<DescriptorPublicKey>> =
+                keychains.iter().map(|(k, d)| (*k, d)).collect();
+            let weights = result
+                .spks
+                .iter()
+                .map(|(spk, (keychain, index))| {
+                    let weight = descriptors[keychain]
+                        .at_derivation_index(*index)
+                        .ok()
+                        .and_then(|d| d.max_weight_to_satisfy().ok())
+                        .map(|w| TXIN_BASE_WEIGHT + w.to_wu());
+                    (spk.clone(), weight)
+                })
+                .collect();
+            let output = keychains[0].1.at_derivation_index(0)?.script_pubkey();
+            (weights, result.txs.into_values().collect(), output)
+        }
+    };
+
+    let estimates = client.get_fee_estimates().await?;
+    let (target, feerate) = fees::estimate_for(&estimates, target).unwrap_or((target, 1.0));
+    let (low_target, low_feerate) =
+        fees::estimate_for(&estimates, low_target).unwrap_or((low_target, 1.0));
+
+    let spent: HashSet<OutPoint> = txs
+        .iter()
+        .flat_map(|tx| tx.vin.iter().map(|vin| OutPoint::new(vin.txid, vin.vout)))
+        .collect();
+    let mut utxos: Vec<Utxo> = txs
+        .iter()
+        .flat_map(|tx| {
+            tx.vout.iter().enumerate().filter_map(|(vout, output)| {
+                let outpoint = OutPoint::new(tx.txid, vout as u32);
+                let input_weight = *weights.get(&output.scriptpubkey)?;
+                if spent.contains(&outpoint) {
+                    return None;
+                }
+                let spend_cost = input_weight.map(|w| fee(feerate, w));
+                Some(Utxo {
+                    outpoint,
+                    value: output.value,
+                    script_type: script_kind(&output.scriptpubkey),
+                    confirmed: tx.status.confirmed,
+                    input_weight,
+                    spend_cost,
+                    uneconomical: spend_cost.is_some_and(|cost| cost >= output.value),
+                })
+            })
+        })
+        .collect();
+    utxos.sort_by_key(|utxo| utxo.value);
+
+    let now = consolidation(&utxos, &output, target, feerate);
+    let low = consolidation(&utxos, &output, low_target, low_feerate);
+    Ok(DustReport {
+        total: utxos.iter().map(|utxo| utxo.value).sum(),
+        utxos,
+        now,
+        low,
+    })
+}
+
+/// Weight of an input spending a single-key output of `script`, `None` for script types whose
+/// spending size depends on the script.
+///
+/// P2SH is taken to wrap P2WPKH, and P2TR to be spent by the key path.
+fn typical_input_weight(script: &Script) -> Option<u64> {
+    match script_kind(script) {
+        "p2pkh" => Some(592),
+        "p2sh" => Some(364),
+        "p2wpkh" => Some(272),
+        "p2tr" => Some(230),
+        _ => None,
+    }
+}
+
+/// The fee for `weight` at `feerate` sat/vB.
+fn fee(feerate: f64, weight: u64) -> u64 {
+    (feerate * weight.div_ceil(4) as f64).ceil() as u64
+}
+
+fn consolidation(utxos: &[Utxo], output: &ScriptBuf, target: u16, feerate: f64) -> Consolidation {
+    let inputs: Vec<&Utxo> = utxos.iter().filter(|u| u.input_weight.is_some()).collect();
+    let mut weight = TX_BASE_WEIGHT + 4 * (8 + 1 + output.len() as u64);
+    weight += inputs.iter().filter_map(|u| u.input_weight).sum::<u64>();
+    if inputs.len() > 252 {
+        // The input count takes a 3 byte varint.
+        weight += 4 * 2;
+    }
+    if inputs.iter().any(|u| u.script_type != "p2pkh") {
+        // The segwit marker and flag.
+        weight += 2;
+    }
+    let fee = fee(feerate, weight);
+    let value: u64 = inputs.iter().map(|u| u.value).sum();
+    Consolidation {
+        target,
+        feerate,
+        vsize: weight.div_ceil(4),
+        fee,
+        remaining: value as i64 - fee as i64,
+    }
+}
+
+/// Render `report` for the terminal.
+pub fn report(report: &DustReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<68}  {:>14}  {:<6}  {:>10}",
+        "outpoint", "value", "type", "spend cost"
+    );
+    for utxo in &report.utxos {
+        let cost = utxo.spend_cost.map_or("?".to_string(), |c| c.to_string());
+        let flags = match (utxo.uneconomical, utxo.confirmed) {
+            (true, true) => "  uneconomical",
+            (true, false) => "  uneconomical, unconfirmed",
+            (false, false) => "  unconfirmed",
+            (false, true) => "",
+        };
+        let _ = writeln!(
+            out,
+            "{:<68}  {:>14}  {:<6}  {:>10}{}",
+            utxo.outpoint.to_string(),
+            utxo.value,
+            utxo.script_type,
+            cost,
+            flags
+        );
+    }
+
+    let uneconomical: Vec<&Utxo> = report.utxos.iter().filter(|u| u.uneconomical).collect();
+    let _ = writeln!(
+        out,
+        "\n{} outputs, {} sat; {} uneconomical at {:.2} sat/vB, {} sat",
+        report.utxos.len(),
+        report.total,
+        uneconomical.len(),
+        report.now.feerate,
+        uneconomical.iter().map(|u| u.value).sum::<u64>()
+    );
+    let unknown = report.utxos.iter().filter(|u| u.input_weight.is_none()).count();
+    if unknown > 0 {
+        let _ = writeln!(
+            out,
+            "{} outputs of unknown spending size are left out of the consolidation",
+            unknown
+        );
+    }
+    for (label, c) in [("now", &report.now), ("low-fee", &report.low)] {
+        let _ = writeln!(
+            out,
+            "consolidate {} ({} blocks, {:.2} sat/vB): {} vB, fee {} sat, {} sat left",
+            label, c.target, c.feerate, c.vsize, c.fee, c.remaining
+        );
+    }
+    if report.now.fee > report.low.fee {
+        let _ = writeln!(
+            out,
+            "waiting for the low-fee target saves {} sat",
+            report.now.fee - report.low.fee
+        );
+    }
+    out
+}
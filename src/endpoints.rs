@@ -0,0 +1,230 @@
+//! Discovery of public esplora instances.
+
+use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use bitcoin::{constants, BlockHash, Network};
+use esplora_client::Builder;
+
+use crate::client::Client;
+use crate::config::{self, Config, Profile};
+use crate::progress::Progress;
+
+/// Public instances shipped with the binary, used until `endpoints update` stores a newer list.
+const BUNDLED: &[&str] = &[
+    "https://blockstream.info/api",
+    "https://blockstream.info/testnet/api",
+    "https://mempool.space/api",
+    "https://mempool.space/testnet/api",
+    "https://mempool.space/testnet4/api",
+    "https://mempool.space/signet/api",
+];
+
+/// Seconds to wait on an instance before giving up on it.
+const PROBE_TIMEOUT: u64 = 10;
+
+/// Networks recognized by their genesis block hash.
+pub const NETWORKS: [Network; 5] = [
+    Network::Bitcoin,
+    Network::Testnet,
+    Network::Testnet4,
+    Network::Signet,
+    Network::Regtest,
+];
+
+/// The outcome of probing an instance.
+#[derive(Debug)]
+pub struct Probe {
+    /// Base url of the instance.
+    pub url: String,
+    /// The network and tip height, or the reason the instance is unusable.
+    pub result: Result<(Network, u32), String>,
+    /// Time taken to answer both requests.
+    pub latency: Duration,
+}
+
+/// Path of the stored instance list.
+fn list_path() -> anyhow::Result<PathBuf> {
+    Ok(config::config_dir()?.join("endpoints.json"))
+}
+
+/// The stored instance list, or the bundled one if `endpoints update` was never run.
+pub fn load_list() -> anyhow::Result<Vec<String>> {
+    let path = list_path()?;
+    match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(BUNDLED.iter().map(|s| s.to_string()).collect())
+        }
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Fetch an instance list (a JSON array of base urls) from `source` with `http` and store it.
+pub async fn update_list(http: &reqwest::Client, source: &str) -> anyhow::Result<Vec<String>> {
+    let list: Vec<String> = http.get(source).send().await?.error_for_status()?.json().await?;
+    if list.is_empty() {
+        return Err(anyhow!("{} lists no instances", source));
+    }
+    let path = list_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&list)?)?;
+    Ok(list)
+}
+
+/// Probe every instance in `urls` concurrently, returning them best first. `builder_for`
+/// gives the builder of a client of an instance, carrying the proxy and headers to use.
+///
+/// Reachable instances are grouped by network and ordered by tip height, then latency.
+pub async fn discover(
+    urls: &[String],
+    builder_for: impl Fn(&str) -> Builder,
+    progress: &Progress,
+) -> Vec<Probe> {
+    progress.set_total(urls.len() as u64);
+    let mut probes = futures::future::join_all(urls.iter().map(|url| async {
+        let probe = probe(url, builder_for(url)).await;
+        progress.inc(1);
+        probe
+    }))
+    .await;
+    progress.finish();
+    probes.sort_by(|a, b| match (&a.result, &b.result) {
+        (Ok((na, ha)), Ok((nb, hb))) => NETWORKS
+            .iter()
+            .position(|n| n == na)
+            .cmp(&NETWORKS.iter().position(|n| n == nb))
+            .then(hb.cmp(ha))
+            .then(a.latency.cmp(&b.latency)),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => a.url.cmp(&b.url),
+    });
+    probes
+}
+
+/// The network whose genesis block is `genesis`.
+pub fn network_of(genesis: &BlockHash) -> Option<Network> {
+    NETWORKS
+        .into_iter()
+        .find(|n| constants::genesis_block(n).block_hash() == *genesis)
+}
+
+/// Check that `url` answers, through a client built from `builder`, and identify its network
+/// from the genesis block hash.
+async fn probe(url: &str, builder: Builder) -> Probe {
+    let start = Instant::now();
+    let result = async {
+        let client = Client::from_builder(builder.timeout(PROBE_TIMEOUT))?;
+        let genesis = client.get_block_hash(0).await?;
+        let height = client.get_height().await?;
+        let network = network_of(&genesis).ok_or(anyhow!("unknown genesis block {}", genesis))?;
+        anyhow::Ok((network, height))
+    }
+    .await
+    .map_err(|e| e.to_string());
+
+    Probe {
+        url: url.to_string(),
+        result,
+        latency: start.elapsed(),
+    }
+}
+
+/// Render the probe results as a table.
+pub fn report(probes: &[Probe]) -> String {
+    let mut out = format!("{:<10}{:>10}{:>10}  url\n", "network", "height", "latency");
+    for probe in probes {
+        let line = match &probe.result {
+            Ok((network, height)) => format!(
+                "{:<10}{:>10}{:>7} ms  {}\n",
+                network.to_string(),
+                height,
+                probe.latency.as_millis(),
+                probe.url
+            ),
+            Err(e) => format!("{:<10}{:>10}{:>10}  {} ({})\n", "-", "-", "-", probe.url, e),
+        };
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Render the probe results as JSON.
+pub fn report_json(probes: &[Probe]) -> serde_json::Value {
+    let probes: Vec<serde_json::Value> = probes
+        .iter()
+        .map(|probe| match &probe.result {
+            Ok((network, height)) => serde_json::json!({
+                "url": probe.url,
+                "network": network,
+                "height": height,
+                "latency_ms": probe.latency.as_millis() as u64,
+            }),
+            Err(e) => serde_json::json!({ "url": probe.url, "error": e }),
+        })
+        .collect();
+    serde_json::Value::Array(probes)
+}
+
+/// Offer the best instance of each network as a profile named after the network.
+///
+/// Candidates are added without asking if `yes` is set, otherwise the user is prompted when
+/// attached to a terminal, and never in `json` mode. Existing profiles are never overwritten.
+/// The prompt and notes go to stderr, leaving stdout to the probe results.
+pub fn offer_profiles(probes: &[Probe], yes: bool, json: bool) -> anyhow::Result<()> {
+    let interactive = !json && std::io::stdin().is_terminal();
+    if !yes && !interactive {
+        return Ok(());
+    }
+
+    let mut config = Config::load()?;
+    let mut changed = false;
+    let mut seen = vec![];
+    for probe in probes {
+        let Ok((network, _)) = probe.result else {
+            continue;
+        };
+        if seen.contains(&network) {
+            continue;
+        }
+        seen.push(network);
+
+        let name = network.to_string();
+        if config.profiles.contains_key(&name) {
+            continue;
+        }
+        if !yes {
+            eprint!("Add {} as profile `{}`? [y/N] ", probe.url, name);
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().lock().read_line(&mut answer)?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                continue;
+            }
+        }
+        config.profiles.insert(
+            name.clone(),
+            Profile {
+                url: probe.url.clone(),
+                fallback_urls: vec![],
+                headers: Default::default(),
+                bearer_token: None,
+                max_rps: None,
+                proxy: None,
+            },
+        );
+        eprintln!("Added profile `{}`", name);
+        changed = true;
+    }
+
+    if changed {
+        config.save()?;
+    }
+    Ok(())
+}
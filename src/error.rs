@@ -0,0 +1,186 @@
+//! Exit codes scripts can rely on, and the mapping of errors onto them.
+//!
+//! Errors raised by the CLI itself carry their [`Kind`] with [`not_found`] and
+//! [`invalid_input`], errors from the backend are classified by their HTTP status.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::broadcast::{Reason, Rejection};
+
+/// Description of the exit codes, for `--help`.
+pub const HELP: &str = "Exit codes:
+  0    success
+  1    any other error
+  2    not found
+  3    rejected by the backend's mempool
+  4    network error, unavailable backend or refused credentials
+  5    invalid input
+  130  interrupted by Ctrl-C, after writing partial results";
+
+/// What went wrong, as far as a script calling the CLI needs to know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    /// The transaction, block, address or other item doesn't exist.
+    NotFound,
+    /// The backend refused to relay a transaction.
+    Rejected,
+    /// The backend couldn't be reached, failed to answer, or a proxy or gateway in front of it
+    /// refused the credentials.
+    Network,
+    /// An argument or input file is malformed.
+    InvalidInput,
+    /// Ctrl-C stopped the command before it completed.
+    Interrupted,
+    /// Anything else.
+    Other,
+}
+
+impl Kind {
+    /// The process exit code.
+    pub fn code(self) -> u8 {
+        match self {
+            Kind::Other => 1,
+            Kind::NotFound => 2,
+            Kind::Rejected => 3,
+            Kind::Network => 4,
+            Kind::InvalidInput => 5,
+            Kind::Interrupted => 130,
+        }
+    }
+}
+
+/// An error raised by the CLI itself whose kind is known.
+#[derive(Debug)]
+struct KindError {
+    kind: Kind,
+    message: String,
+}
+
+impl fmt::Display for KindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for KindError {}
+
+/// An error reporting that `what` doesn't exist.
+pub fn not_found(what: impl fmt::Display) -> anyhow::Error {
+    anyhow::Error::new(KindError {
+        kind: Kind::NotFound,
+        message: format!("{} not found", what),
+    })
+}
+
+/// An error reporting that the backend doesn't serve an endpoint, classified as not found like
+/// the 404 it answers it with.
+pub fn unsupported(message: impl fmt::Display) -> anyhow::Error {
+    anyhow::Error::new(KindError {
+        kind: Kind::NotFound,
+        message: message.to_string(),
+    })
+}
+
+/// An error reporting malformed input.
+pub fn invalid_input(message: impl fmt::Display) -> anyhow::Error {
+    anyhow::Error::new(KindError {
+        kind: Kind::InvalidInput,
+        message: message.to_string(),
+    })
+}
+
+/// An error reporting that Ctrl-C stopped the command, after `what` was done.
+pub fn interrupted(what: impl fmt::Display) -> anyhow::Error {
+    anyhow::Error::new(KindError {
+        kind: Kind::Interrupted,
+        message: format!("interrupted after {}", what),
+    })
+}
+
+/// Classify `err` by the first error in its chain of a recognized type.
+pub fn classify(err: &anyhow::Error) -> Kind {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<KindError>() {
+            return e.kind;
+        }
+        if cause.is::<Rejection>() {
+            return Kind::Rejected;
+        }
+        if let Some(e) = cause.downcast_ref::<esplora_client::Error>() {
+            return classify_esplora(e);
+        }
+        if cause.is::<reqwest::Error>() {
+            return Kind::Network;
+        }
+        if cause.is::<bitcoin::consensus::encode::FromHexError>()
+            || cause.is::<bitcoin::consensus::encode::Error>()
+            || cause.is::<bitcoin::hex::HexToBytesError>()
+            || cause.is::<bitcoin::hex::HexToArrayError>()
+            || cause.is::<bitcoin::address::ParseError>()
+            || cause.is::<miniscript::Error>()
+        {
+            return Kind::InvalidInput;
+        }
+    }
+    Kind::Other
+}
+
+fn classify_esplora(e: &esplora_client::Error) -> Kind {
+    use esplora_client::Error;
+
+    match e {
+        Error::HttpResponse { status: 404, .. } => Kind::NotFound,
+        // Esplora passes on the `sendrawtransaction` error of the node on rejection.
+        Error::HttpResponse {
+            status: 400,
+            message,
+        } if message.contains("RPC error") => Kind::Rejected,
+        // Auth proxies and timeouts in front of the backend, not a bad request.
+        Error::HttpResponse {
+            status: 401 | 403 | 407 | 408 | 429,
+            ..
+        } => Kind::Network,
+        Error::HttpResponse { status, .. } if *status >= 500 => Kind::Network,
+        Error::HttpResponse {
+            status: 400 | 422, ..
+        } => Kind::InvalidInput,
+        Error::Reqwest(_) => Kind::Network,
+        _ => Kind::Other,
+    }
+}
+
+/// Error output in JSON mode.
+#[derive(Serialize)]
+struct Report<'a> {
+    error: &'a str,
+    kind: Kind,
+    code: u8,
+    /// Why a transaction was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<Reason>,
+}
+
+/// Print `err` on stderr, as a line of JSON if `json` is set, and return the exit code for its
+/// kind.
+pub fn report(err: &anyhow::Error, json: bool) -> ExitCode {
+    let kind = classify(err);
+    if json {
+        let report = Report {
+            error: &format!("{:#}", err),
+            kind,
+            code: kind.code(),
+            reason: err.downcast_ref::<Rejection>().map(|r| r.reason),
+        };
+        match serde_json::to_string(&report) {
+            Ok(line) => eprintln!("{}", line),
+            Err(_) => eprintln!("Error: {:?}", err),
+        }
+    } else {
+        eprintln!("Error: {:?}", err);
+    }
+    ExitCode::from(kind.code())
+}
@@ -0,0 +1,163 @@
+//! Feerates paid in recent blocks, to judge whether the current fees are high for the period.
+//!
+//! The per-block statistics come from [`blockstats`], from the backend's block extras when it
+//! has them or computed from the blocks' transactions otherwise.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use bitcoin::BlockHash;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+
+use crate::blockstats::{self, BlockStats};
+use crate::capabilities::{self, Feature};
+use crate::client::Client;
+use crate::error;
+use crate::fees::sparkline;
+use crate::progress::Progress;
+use crate::timefmt::{self, format_time};
+
+/// Percentiles of the median feerates over the window, in sat/vB.
+#[derive(Debug, Serialize)]
+pub struct Percentiles {
+    pub p10: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p90: f64,
+}
+
+/// The feerates of a window of blocks ending at the tip.
+#[derive(Debug, Serialize)]
+pub struct FeeHistory {
+    /// The blocks, lowest first.
+    pub blocks: Vec<BlockStats>,
+    /// Percentiles of the blocks' median feerates, `None` if no block has one.
+    pub percentiles: Option<Percentiles>,
+}
+
+/// Summarize the last `blocks` blocks, from the backend's block extras if it has them unless
+/// `local` is set.
+///
+/// Up to `jobs` blocks are summarized at once. Computing the statistics locally takes a request
+/// per 25 transactions of every block.
+pub async fn fee_history(
+    client: &Client,
+    blocks: u32,
+    local: bool,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<FeeHistory> {
+    if blocks == 0 {
+        return Err(error::invalid_input("--blocks must be at least 1"));
+    }
+    let local = local || !capabilities::supports(client, Feature::BlockExtras).await?;
+    let tip = client.get_height().await?;
+    let from = tip.saturating_sub(blocks - 1);
+    let hashes: Vec<BlockHash> = client
+        .get_block_range(from, tip)
+        .map(|block| anyhow::Ok(serde_json::from_value::<BlockHash>(block?["id"].clone())?))
+        .try_collect()
+        .await?;
+
+    progress.set_total(hashes.len() as u64);
+    // The blocks are the unit of progress, not their pages of transactions.
+    let quiet = Progress::hidden();
+    let blocks: Vec<BlockStats> = futures::stream::iter(&hashes)
+        .map(|hash| {
+            let quiet = &quiet;
+            async move {
+                let stats = blockstats::block_stats(client, hash, local, 1, quiet).await?;
+                progress.inc(1);
+                anyhow::Ok(stats)
+            }
+        })
+        .buffered(jobs.max(1))
+        .try_collect()
+        .await?;
+    progress.finish();
+
+    let mut medians: Vec<f64> = blocks.iter().filter_map(|b| b.median_feerate).collect();
+    medians.sort_by(f64::total_cmp);
+    let percentiles = (!medians.is_empty()).then(|| Percentiles {
+        p10: percentile(&medians, 10),
+        p25: percentile(&medians, 25),
+        p50: percentile(&medians, 50),
+        p75: percentile(&medians, 75),
+        p90: percentile(&medians, 90),
+    });
+    Ok(FeeHistory {
+        blocks,
+        percentiles,
+    })
+}
+
+/// The `p`th percentile of the sorted, non-empty `values` by the nearest-rank method.
+fn percentile(values: &[f64], p: usize) -> f64 {
+    let rank = (values.len() * p).div_ceil(100).max(1);
+    values[rank - 1]
+}
+
+/// Write the blocks of `history` as CSV to `path`.
+pub fn write_csv(path: &Path, history: &FeeHistory) -> anyhow::Result<()> {
+    let mut out =
+        String::from("date,height,hash,tx_count,min_feerate,median_feerate,max_feerate,fees_sat\n");
+    let rate = |r: Option<f64>| r.map_or(String::new(), |r| format!("{:.2}", r));
+    for block in &history.blocks {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            format_time(block.time),
+            block.height,
+            block.hash,
+            block.tx_count,
+            rate(block.min_feerate),
+            rate(block.median_feerate),
+            rate(block.max_feerate),
+            block.fees
+        );
+    }
+    fs::write(path, out).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Render `history` for the terminal.
+pub fn report(history: &FeeHistory) -> String {
+    let rate = |r: Option<f64>| r.map_or("-".to_string(), |r| format!("{:.2}", r));
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:>8}  {:<25}  {:>6}  {:>8}  {:>8}  {:>8}",
+        "height", "time", "txs", "min", "median", "max"
+    );
+    for block in &history.blocks {
+        let _ = writeln!(
+            out,
+            "{:>8}  {:<25}  {:>6}  {:>8}  {:>8}  {:>8}",
+            block.height,
+            timefmt::format_rfc3339(block.time),
+            block.tx_count,
+            rate(block.min_feerate),
+            rate(block.median_feerate),
+            rate(block.max_feerate)
+        );
+    }
+
+    let _ = writeln!(out, "\nmedian feerate over {} blocks (sat/vB):", history.blocks.len());
+    match &history.percentiles {
+        Some(p) => {
+            let _ = writeln!(
+                out,
+                "p10 {:.2}  p25 {:.2}  p50 {:.2}  p75 {:.2}  p90 {:.2}",
+                p.p10, p.p25, p.p50, p.p75, p.p90
+            );
+            let medians: Vec<f64> =
+                history.blocks.iter().filter_map(|b| b.median_feerate).collect();
+            let _ = writeln!(out, "trend: {}", sparkline(&medians));
+        }
+        None => out.push_str("no block paid a fee\n"),
+    }
+    out
+}
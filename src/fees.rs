@@ -0,0 +1,209 @@
+//! Fee estimate logging and analysis of how fees moved over time.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::config;
+use crate::error;
+use crate::timefmt;
+
+/// Levels used to draw sparklines, lowest first.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Maximum number of points drawn in a sparkline.
+const SPARK_WIDTH: usize = 24;
+
+/// A point-in-time record of the backend's fee estimates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSnapshot {
+    /// UNIX timestamp of when the estimates were fetched.
+    pub time: u64,
+    /// Fee estimates in sat/vB keyed by confirmation target.
+    pub estimates: BTreeMap<u16, f64>,
+}
+
+/// A point in time given as a UNIX timestamp, `now`, or relative such as `2h ago`.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSpec(pub u64);
+
+impl FromStr for TimeSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "now" {
+            return Ok(TimeSpec(now()));
+        }
+        if let Ok(time) = s.parse::<u64>() {
+            return Ok(TimeSpec(time));
+        }
+        let ago = s.strip_suffix("ago").map(str::trim).ok_or(anyhow!(
+            "expected a UNIX timestamp, `now`, or a relative time like `2h ago`"
+        ))?;
+        let secs = parse_duration(ago)?;
+        Ok(TimeSpec(now().saturating_sub(secs)))
+    }
+}
+
+/// Parse a duration such as `90s`, `15m`, `2h` or `1d` into seconds.
+pub fn parse_duration(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().with_context(|| format!("invalid duration `{}`", s))?;
+    let scale = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        unit => return Err(anyhow!("unknown duration unit `{}`", unit)),
+    };
+    n.checked_mul(scale)
+        .ok_or_else(|| error::invalid_input(format!("duration `{}` is too long", s)))
+}
+
+/// Seconds since the UNIX epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The estimate in `estimates` for the nearest confirmation target at or above `target`, with
+/// the target it is for. Falls back to the longest target, like Core falling back to a longer
+/// horizon.
+pub fn estimate_for(estimates: &HashMap<u16, f64>, target: u16) -> Option<(u16, f64)> {
+    estimates
+        .iter()
+        .filter(|(&blocks, _)| blocks >= target)
+        .min_by_key(|(&blocks, _)| blocks)
+        .or_else(|| estimates.iter().max_by_key(|(&blocks, _)| blocks))
+        .map(|(&blocks, &rate)| (blocks, rate))
+}
+
+/// The default fee log location, `$XDG_DATA_HOME/esplora-cli/fees.jsonl`.
+pub fn default_log_path() -> anyhow::Result<PathBuf> {
+    Ok(config::data_dir()?.join("fees.jsonl"))
+}
+
+/// Fetch the current fee estimates and append them to the log at `path`.
+pub async fn record(client: &Client, path: &Path) -> anyhow::Result<FeeSnapshot> {
+    let estimates = client.get_fee_estimates().await?;
+    let snapshot = FeeSnapshot {
+        time: now(),
+        estimates: estimates.into_iter().collect(),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&snapshot)?)?;
+
+    Ok(snapshot)
+}
+
+/// Read every snapshot from the log at `path`, oldest first.
+pub fn read_log(path: &Path) -> anyhow::Result<Vec<FeeSnapshot>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut snapshots = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot: FeeSnapshot = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: malformed snapshot", path.display(), i + 1))?;
+        snapshots.push(snapshot);
+    }
+    snapshots.sort_by_key(|s| s.time);
+    Ok(snapshots)
+}
+
+/// Report how each fee tier moved between `from` and `to`.
+///
+/// The endpoints of the window are the latest snapshots taken at or before each time.
+pub fn diff(snapshots: &[FeeSnapshot], from: u64, to: u64) -> anyhow::Result<String> {
+    if from >= to {
+        return Err(error::invalid_input("--from must be earlier than --to"));
+    }
+    let at = |time: u64| snapshots.iter().rev().find(|s| s.time <= time);
+    let start = at(from).ok_or(anyhow!("no snapshot logged at or before --from"))?;
+    let end = at(to).ok_or(anyhow!("no snapshot logged at or before --to"))?;
+    let window: Vec<&FeeSnapshot> = snapshots
+        .iter()
+        .filter(|s| s.time >= start.time && s.time <= end.time)
+        .collect();
+
+    let mut out = format!(
+        "{} snapshot(s) from {} to {}\n\n{:>6}  {:>9}  {:>9}  {:>8}  trend\n",
+        window.len(),
+        timefmt::format(start.time),
+        timefmt::format(end.time),
+        "target",
+        "from",
+        "to",
+        "change",
+    );
+    for (target, &old) in &start.estimates {
+        let Some(&new) = end.estimates.get(target) else {
+            continue;
+        };
+        let change = if old > 0.0 {
+            (new - old) / old * 100.0
+        } else {
+            0.0
+        };
+        let series: Vec<f64> = window
+            .iter()
+            .filter_map(|s| s.estimates.get(target).copied())
+            .collect();
+        out.push_str(&format!(
+            "{:>6}  {:>9.2}  {:>9.2}  {:>+7.1}%  {}\n",
+            target,
+            old,
+            new,
+            change,
+            sparkline(&series)
+        ));
+    }
+
+    Ok(out)
+}
+
+/// Draw `values` as a sparkline, averaging them into at most [`SPARK_WIDTH`] buckets.
+pub fn sparkline(values: &[f64]) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    let buckets = values.len().min(SPARK_WIDTH);
+    let points: Vec<f64> = (0..buckets)
+        .map(|i| {
+            let chunk = &values[i * values.len() / buckets..(i + 1) * values.len() / buckets];
+            chunk.iter().sum::<f64>() / chunk.len() as f64
+        })
+        .collect();
+    let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    points
+        .iter()
+        .map(|v| {
+            if max > min {
+                let level = (v - min) / (max - min) * (SPARKS.len() - 1) as f64;
+                SPARKS[level.round() as usize]
+            } else {
+                SPARKS[SPARKS.len() / 2]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+//! Downloading and validating ranges of block headers.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use bitcoin::block::Header;
+use bitcoin::consensus::serialize;
+use bitcoin::BlockHash;
+use futures::{StreamExt, TryStreamExt};
+
+use crate::client::Client;
+use crate::progress::Progress;
+
+/// Download the headers from height `from` to `to` inclusive, with up to `jobs` requests in
+/// flight, and write them to `out` as consecutive 80-byte consensus encoded headers, returning
+/// the height and hash of the last header written.
+///
+/// Every header must hash to the block hash the backend reports for its height, satisfy the
+/// proof of work of its encoded target and link to the header before it. The file is only put
+/// in place once the whole range validated, or once interrupted with the headers validated so
+/// far.
+pub async fn sync(
+    client: &Client,
+    from: u32,
+    to: u32,
+    out: &Path,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<(u32, BlockHash)> {
+    if from > to {
+        return Err(anyhow!("--from must not be above --to"));
+    }
+    progress.set_total(u64::from(to - from) + 1);
+
+    let tmp = out.with_extension("tmp");
+    let file = File::create(&tmp).with_context(|| format!("failed to create {}", tmp.display()))?;
+    let last = match write_headers(client, from, to, file, jobs, progress).await {
+        Ok(last) => last,
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    fs::rename(&tmp, out).with_context(|| format!("failed to write {}", out.display()))?;
+
+    Ok(last)
+}
+
+/// Fetch, validate and write the headers of the range to `file`, stopping early once interrupted.
+async fn write_headers(
+    client: &Client,
+    from: u32,
+    to: u32,
+    file: File,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<(u32, BlockHash)> {
+    let mut file = BufWriter::new(file);
+    let mut headers = futures::stream::iter(from..=to)
+        .map(|height| async move {
+            let hash = client.get_block_hash(height).await?;
+            let header = client.get_header_by_hash(&hash).await?;
+            anyhow::Ok((height, hash, header))
+        })
+        .buffered(jobs.max(1));
+
+    let mut last: Option<(u32, BlockHash)> = None;
+    while let Some((height, hash, header)) = headers.try_next().await? {
+        validate(height, &hash, &header, last.map(|(_, hash)| hash))?;
+        file.write_all(&serialize(&header))?;
+        last = Some((height, hash));
+        progress.inc(1);
+        if Progress::is_interrupted() {
+            break;
+        }
+    }
+    progress.finish();
+    file.flush()?;
+
+    last.ok_or(anyhow!("no headers in range"))
+}
+
+/// Check a single header of the chain, given the hash of the header at the height below.
+fn validate(
+    height: u32,
+    hash: &BlockHash,
+    header: &Header,
+    prev: Option<BlockHash>,
+) -> anyhow::Result<()> {
+    if header.block_hash() != *hash {
+        return Err(anyhow!("header at height {} does not hash to {}", height, hash));
+    }
+    header
+        .validate_pow(header.target())
+        .map_err(|e| anyhow!("header at height {} has invalid proof of work: {}", height, e))?;
+    if let Some(prev) = prev {
+        if header.prev_blockhash != prev {
+            return Err(anyhow!(
+                "header at height {} does not link to {} at height {}",
+                height,
+                prev,
+                height - 1
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Backend health: reachability, latency, freshness and lag behind a reference.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use bitcoin::BlockHash;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::tip;
+
+/// Round-trip time of a request to an endpoint.
+#[derive(Debug, Serialize)]
+pub struct Latency {
+    /// The endpoint requested.
+    pub endpoint: &'static str,
+    /// Round-trip time, in milliseconds.
+    pub ms: u64,
+}
+
+/// How far the backend is behind a reference instance.
+#[derive(Debug, Serialize)]
+pub struct Lag {
+    /// Url of the reference instance.
+    pub url: String,
+    /// Tip height of the reference instance.
+    pub height: u32,
+    /// Number of blocks the backend is behind, negative if it is ahead.
+    pub blocks: i64,
+}
+
+/// The health of a backend.
+#[derive(Debug, Serialize)]
+pub struct Health {
+    /// Url of the backend.
+    pub url: String,
+    /// Latency of the cheap tip endpoints.
+    pub latency: Vec<Latency>,
+    /// Tip height.
+    pub height: u32,
+    /// Tip hash.
+    pub hash: BlockHash,
+    /// Seconds since the tip was mined.
+    pub tip_age: u64,
+    /// Lag behind the reference instance, if one was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lag: Option<Lag>,
+}
+
+/// Measure the health of the backend at `url`, comparing its tip with `reference` if given.
+pub async fn check(
+    client: &Client,
+    url: &str,
+    reference: Option<(&str, &Client)>,
+) -> anyhow::Result<Health> {
+    let start = Instant::now();
+    let height = client.get_height().await?;
+    let height_rtt = start.elapsed();
+    let start = Instant::now();
+    let hash = client.get_tip_hash().await?;
+    let hash_rtt = start.elapsed();
+    let tip_age = crate::fees::now().saturating_sub(tip::block_time(client, &hash).await?);
+
+    let lag = match reference {
+        Some((url, reference)) => {
+            let reference_height = reference.get_height().await?;
+            Some(Lag {
+                url: url.to_string(),
+                height: reference_height,
+                blocks: i64::from(reference_height) - i64::from(height),
+            })
+        }
+        None => None,
+    };
+
+    Ok(Health {
+        url: url.to_string(),
+        latency: vec![
+            latency("/blocks/tip/height", height_rtt),
+            latency("/blocks/tip/hash", hash_rtt),
+        ],
+        height,
+        hash,
+        tip_age,
+        lag,
+    })
+}
+
+fn latency(endpoint: &'static str, rtt: Duration) -> Latency {
+    Latency {
+        endpoint,
+        ms: rtt.as_millis() as u64,
+    }
+}
+
+/// Render the health report as text.
+pub fn report(health: &Health) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<10}{}", "backend", health.url);
+    for latency in &health.latency {
+        let _ = writeln!(out, "{:<10}{} ms  {}", "latency", latency.ms, latency.endpoint);
+    }
+    let _ = writeln!(out, "{:<10}{} {}", "tip", health.height, health.hash);
+    let _ = writeln!(out, "{:<10}{}", "tip age", tip::format_age(health.tip_age));
+    if let Some(lag) = &health.lag {
+        let _ = writeln!(
+            out,
+            "{:<10}{} block(s) behind {} at {}",
+            "lag", lag.blocks, lag.url, lag.height
+        );
+    }
+    out
+}
@@ -0,0 +1,144 @@
+//! Export of the confirmed history of an address for accounting.
+//!
+//! Historical prices for the fiat columns are taken from the mempool.space
+//! `/v1/historical-price` endpoint, other backends don't provide them.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use bitcoin::{Address, Txid};
+use futures::{future, StreamExt, TryStreamExt};
+use serde::Deserialize;
+
+use crate::capabilities::{self, Feature};
+use crate::client::Client;
+use crate::progress::Progress;
+use crate::scan;
+use crate::timefmt::format_time;
+
+/// A confirmed transaction of the address.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The transaction id.
+    pub txid: Txid,
+    /// Height of the block the transaction confirmed in.
+    pub height: u32,
+    /// UNIX timestamp of the block.
+    pub time: u64,
+    /// Net change to the address balance, in satoshis.
+    pub net: i64,
+    /// Balance of the address after the transaction, in satoshis.
+    pub balance: i64,
+    /// Price of one bitcoin in the fiat currency at the block time.
+    pub price: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct HistoricalPrice {
+    prices: Vec<HashMap<String, f64>>,
+}
+
+/// Fetch the full confirmed history of `address`, oldest first, with the price in `fiat` at
+/// each block time if given, looking up to `jobs` prices at once.
+///
+/// Returns the entries and the number of unconfirmed transactions left out. Progress is
+/// counted in prices looked up, and once interrupted only the entries priced so far are
+/// returned.
+pub async fn history(
+    client: &Client,
+    address: &Address,
+    fiat: Option<&str>,
+    jobs: usize,
+    progress: &Progress,
+) -> anyhow::Result<(Vec<Entry>, usize)> {
+    if fiat.is_some() {
+        capabilities::require(client, Feature::Prices).await?;
+    }
+    let script = address.script_pubkey();
+    let txs = scan::script_history(client, &script).await?;
+    let unconfirmed = txs.iter().filter(|tx| !tx.status.confirmed).count();
+
+    // History comes newest first.
+    let mut balance = 0;
+    let mut entries = vec![];
+    for tx in txs.iter().rev() {
+        let (Some(height), Some(time)) = (tx.status.block_height, tx.status.block_time) else {
+            continue;
+        };
+        let net = scan::net_effect(tx, &script);
+        balance += net;
+        entries.push(Entry {
+            txid: tx.txid,
+            height,
+            time,
+            net,
+            balance,
+            price: None,
+        });
+    }
+
+    if let Some(fiat) = fiat {
+        progress.set_total(entries.len() as u64);
+        let prices: Vec<f64> = futures::stream::iter(&entries)
+            .take_while(|_| future::ready(!Progress::is_interrupted()))
+            .map(|entry| async move {
+                let price = price(client, fiat, entry.time).await?;
+                progress.inc(1);
+                anyhow::Ok(price)
+            })
+            .buffered(jobs.max(1))
+            .try_collect()
+            .await?;
+        progress.finish();
+        entries.truncate(prices.len());
+        for (entry, price) in entries.iter_mut().zip(prices) {
+            entry.price = Some(price);
+        }
+    }
+
+    Ok((entries, unconfirmed))
+}
+
+/// The price of one bitcoin in `fiat` at UNIX time `time`.
+async fn price(client: &Client, fiat: &str, time: u64) -> anyhow::Result<f64> {
+    let path = format!("/v1/historical-price?currency={}&timestamp={}", fiat, time);
+    let response: Option<HistoricalPrice> = client.get_opt_json(&path).await?;
+    response
+        .and_then(|r| r.prices.into_iter().next())
+        .and_then(|prices| prices.get(fiat).copied())
+        .filter(|&price| price > 0.0)
+        .ok_or_else(|| anyhow!("backend has no {} price for {}", fiat, format_time(time)))
+}
+
+/// Write `entries` as CSV to `path`.
+pub fn write_csv(path: &Path, entries: &[Entry], fiat: Option<&str>) -> anyhow::Result<()> {
+    let mut out = String::from("date,txid,height,credit_sat,debit_sat,balance_sat");
+    if let Some(fiat) = fiat {
+        let fiat = fiat.to_lowercase();
+        let _ = write!(out, ",{}_price,{}_value", fiat, fiat);
+    }
+    out.push('\n');
+
+    for entry in entries {
+        let _ = write!(
+            out,
+            "{},{},{},{},{},{}",
+            format_time(entry.time),
+            entry.txid,
+            entry.height,
+            entry.net.max(0),
+            (-entry.net).max(0),
+            entry.balance
+        );
+        if let Some(price) = entry.price {
+            let value = entry.net as f64 / 1e8 * price;
+            let _ = write!(out, ",{:.2},{:.2}", price, value);
+        }
+        out.push('\n');
+    }
+
+    fs::write(path, out).with_context(|| format!("failed to write {}", path.display()))
+}
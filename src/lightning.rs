@@ -0,0 +1,221 @@
+//! Lightning network data from the mempool.space `/v1/lightning` API.
+
+// Response fields are only read through their `Debug` output.
+#![allow(dead_code)]
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bitcoin::secp256k1::PublicKey;
+use esplora_client::Error;
+use serde::Deserialize;
+use url::form_urlencoded;
+
+use crate::client::Client;
+
+/// Network-wide statistics, the latest snapshot along with the one before it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkStats {
+    /// The most recent statistics.
+    pub latest: Stats,
+    /// The previous statistics, for comparison.
+    pub previous: Option<Stats>,
+}
+
+/// A snapshot of Lightning network statistics.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Stats {
+    /// Time the snapshot was taken.
+    pub added: String,
+    /// Number of public channels.
+    pub channel_count: u64,
+    /// Number of public nodes.
+    pub node_count: u64,
+    /// Total public capacity, in satoshis.
+    pub total_capacity: u64,
+    /// Number of Tor only nodes.
+    pub tor_nodes: u64,
+    /// Number of clearnet only nodes.
+    pub clearnet_nodes: u64,
+    /// Number of nodes without a public address.
+    pub unannounced_nodes: u64,
+    /// Number of nodes reachable on both clearnet and Tor.
+    pub clearnet_tor_nodes: Option<u64>,
+    /// Average channel capacity, in satoshis.
+    pub avg_capacity: u64,
+    /// Average fee rate, in parts per million.
+    pub avg_fee_rate: u64,
+    /// Average base fee, in millisatoshis.
+    pub avg_base_fee_mtokens: u64,
+    /// Median channel capacity, in satoshis.
+    pub med_capacity: u64,
+    /// Median fee rate, in parts per million.
+    pub med_fee_rate: u64,
+    /// Median base fee, in millisatoshis.
+    pub med_base_fee_mtokens: u64,
+}
+
+/// Details of a Lightning node.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Node {
+    /// The node public key.
+    pub public_key: PublicKey,
+    /// The node alias.
+    pub alias: String,
+    /// The node color, as a hex string.
+    pub color: Option<String>,
+    /// Comma separated list of advertised addresses.
+    pub sockets: Option<String>,
+    /// UNIX timestamp of when the node was first seen.
+    pub first_seen: Option<u64>,
+    /// UNIX timestamp of the last node announcement.
+    pub updated_at: Option<u64>,
+    /// Number of active channels.
+    pub active_channel_count: Option<u64>,
+    /// Number of opened channels.
+    pub opened_channel_count: Option<u64>,
+    /// Number of closed channels.
+    pub closed_channel_count: Option<u64>,
+    /// Total capacity of active channels, in satoshis.
+    pub capacity: Option<u64>,
+    /// The autonomous system organization hosting the node.
+    pub as_organization: Option<String>,
+    /// The ISO country code the node is located in.
+    pub iso_code: Option<String>,
+}
+
+/// The result of searching nodes and channels by alias, public key or short channel id.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchResult {
+    /// The matching nodes.
+    pub nodes: Vec<NodeMatch>,
+    /// The matching channels.
+    pub channels: Vec<ChannelMatch>,
+}
+
+/// A node matching a search.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeMatch {
+    /// The node public key.
+    pub public_key: PublicKey,
+    /// The node alias.
+    pub alias: String,
+    /// Total capacity, in satoshis.
+    pub capacity: Option<u64>,
+    /// Number of channels.
+    pub channels: Option<u64>,
+}
+
+/// A channel matching a search.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelMatch {
+    /// The channel id, as a numeric short channel id.
+    pub id: String,
+    /// The short channel id in `block x tx x output` form.
+    pub short_id: String,
+}
+
+/// Details of a Lightning channel.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Channel {
+    /// The channel id, as a numeric short channel id.
+    pub id: String,
+    /// The short channel id in `block x tx x output` form.
+    pub short_id: String,
+    /// Channel capacity, in satoshis.
+    pub capacity: u64,
+    /// The funding transaction id.
+    pub transaction_id: String,
+    /// The funding output index.
+    pub transaction_vout: u32,
+    /// The closing transaction id, if the channel is closed.
+    pub closing_transaction_id: Option<String>,
+    /// How the channel was closed, if known.
+    pub closing_reason: Option<u8>,
+    /// Channel status: 0 inactive, 1 active, 2 closed.
+    pub status: u8,
+    /// Time the channel was first seen.
+    pub created: Option<String>,
+    /// Time of the last channel update.
+    pub updated_at: Option<String>,
+    /// Policy of the first node.
+    pub node_left: ChannelPolicy,
+    /// Policy of the second node.
+    pub node_right: ChannelPolicy,
+}
+
+/// One side of a channel with the routing policy advertised by that node.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelPolicy {
+    /// The node public key.
+    pub public_key: PublicKey,
+    /// The node alias.
+    pub alias: String,
+    /// Base fee, in millisatoshis.
+    pub base_fee_mtokens: Option<u64>,
+    /// Proportional fee rate, in parts per million.
+    pub fee_rate: Option<u64>,
+    /// CLTV expiry delta.
+    pub cltv_delta: Option<u32>,
+    /// Minimum HTLC size, in millisatoshis.
+    pub min_htlc_mtokens: Option<u64>,
+    /// Maximum HTLC size, in millisatoshis.
+    pub max_htlc_mtokens: Option<u64>,
+    /// Whether the node disabled forwarding over the channel.
+    pub is_disabled: Option<bool>,
+}
+
+/// A short channel id, parsed from either the numeric or the `BxTxO` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShortChannelId(u64);
+
+impl FromStr for ShortChannelId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(id) = s.parse::<u64>() {
+            return Ok(ShortChannelId(id));
+        }
+        let parts: Vec<&str> = s.split('x').collect();
+        let [block, tx, output] = parts.as_slice() else {
+            return Err(anyhow!("expected short channel id as a number or BLOCKxTXxOUTPUT"));
+        };
+        let block: u64 = block.parse()?;
+        let tx: u64 = tx.parse()?;
+        let output: u64 = output.parse()?;
+        if block >= 1 << 24 || tx >= 1 << 24 || output >= 1 << 16 {
+            return Err(anyhow!("short channel id component out of range"));
+        }
+        Ok(ShortChannelId(block << 40 | tx << 16 | output))
+    }
+}
+
+impl fmt::Display for ShortChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Get the latest network-wide statistics, `None` if the backend doesn't serve lightning data.
+pub async fn get_stats(client: &Client) -> Result<Option<NetworkStats>, Error> {
+    client.get_opt_json("/v1/lightning/statistics/latest").await
+}
+
+/// Get a node by its public key.
+pub async fn get_node(client: &Client, pubkey: &PublicKey) -> Result<Option<Node>, Error> {
+    client.get_opt_json(&format!("/v1/lightning/nodes/{}", pubkey)).await
+}
+
+/// Search nodes and channels by alias, public key or short channel id.
+pub async fn search(client: &Client, query: &str) -> Result<SearchResult, Error> {
+    let query: String = form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    client
+        .get_json(&format!("/v1/lightning/search?searchText={}", query))
+        .await
+}
+
+/// Get a channel by its short channel id.
+pub async fn get_channel(client: &Client, id: ShortChannelId) -> Result<Option<Channel>, Error> {
+    client.get_opt_json(&format!("/v1/lightning/channels/{}", id)).await
+}
@@ -0,0 +1,58 @@
+//! Client-side rate limiting, so that batch commands pace themselves on public instances.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// A token bucket refilled at a fixed rate, holding at most one second's worth of tokens.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    /// Tokens the bucket holds when full.
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens available, negative when requests are waiting for tokens already handed out.
+    tokens: f64,
+    /// When `tokens` was last brought up to date.
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `rps` requests per second, starting full.
+    pub fn new(rps: f64) -> Self {
+        let capacity = rps.max(1.0);
+        RateLimiter {
+            rate: rps,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be sent.
+    ///
+    /// Each caller reserves its token up front, so concurrent callers are let through in the
+    /// order they arrived.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity) - 1.0;
+            bucket.updated = now;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
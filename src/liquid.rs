@@ -0,0 +1,57 @@
+//! Liquid (Elements) support.
+//!
+//! Elements transactions carry asset tags and may have confidential amounts, so they can't be
+//! decoded as bitcoin transactions. Commands that would otherwise decode into `rust-bitcoin`
+//! types fall back to the backend's JSON or hex representation on Liquid.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bitcoin::hex::FromHex;
+use bitcoin::Txid;
+use esplora_client::Error;
+
+use crate::client::Client;
+
+/// A Liquid asset id, the 32-byte hex identifier of an issued asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetId(String);
+
+impl FromStr for AssetId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <[u8; 32]>::from_hex(s).map_err(|_| anyhow!("expected a 64 character hex asset id"))?;
+        Ok(AssetId(s.to_lowercase()))
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Whether the backend at `url` serves a Liquid network.
+pub fn is_liquid(url: &str) -> bool {
+    url.contains("/liquid")
+}
+
+/// Get issuance and supply information of an asset.
+pub async fn get_asset(
+    client: &Client,
+    asset: &AssetId,
+) -> Result<Option<serde_json::Value>, Error> {
+    client.get_opt_json(&format!("/asset/{}", asset)).await
+}
+
+/// Get the consensus encoded hex of a transaction.
+pub async fn get_tx_hex(client: &Client, txid: &Txid) -> Result<String, Error> {
+    client.get_text(&format!("/tx/{}/hex", txid)).await
+}
+
+/// Get a transaction in the format returned by Esplora, keeping asset and confidential fields.
+pub async fn get_tx_info(client: &Client, txid: &Txid) -> Result<serde_json::Value, Error> {
+    client.get_json(&format!("/tx/{}", txid)).await
+}
@@ -0,0 +1,23 @@
+//! Diagnostic logging to stderr, enabled with `-v` or `RUST_LOG`.
+
+use std::io::IsTerminal;
+
+use tracing_subscriber::EnvFilter;
+
+/// Log each request at `-v` and response bodies too at `-vv`. Without `-v`, `RUST_LOG` is
+/// honoured if set.
+pub fn init(verbose: u8) {
+    let filter = match verbose {
+        0 => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        },
+        1 => EnvFilter::new("esplora_cli=debug"),
+        _ => EnvFilter::new("esplora_cli=trace"),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
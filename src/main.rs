@@ -5,106 +5,1248 @@
 
 #![allow(clippy::uninlined_format_args)]
 
+mod address;
+mod audit;
+mod batch;
+mod bdk;
+mod bench;
+mod blockstats;
+mod broadcast;
+mod cache;
+mod capabilities;
+mod checkpoints;
+mod client;
+mod coinbase;
+mod config;
+mod cpfp;
+mod dump;
+mod dust;
+mod endpoints;
+mod error;
+mod feehistory;
+mod fees;
+mod headers;
+mod health;
+mod history;
+mod http;
+mod lightning;
+mod limiter;
+mod liquid;
+mod logging;
+mod mempool;
+mod metrics;
+mod multi;
+mod oob;
+mod pretty;
+mod progress;
+mod rbf;
+mod repl;
+mod replacements;
+mod rpc;
+mod scan;
+mod show;
+mod state;
+mod timefmt;
+mod tip;
+mod tracker;
+mod txdata;
+mod verify;
+mod watch;
+
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
 use anyhow::anyhow;
-use bitcoin::{address::NetworkUnchecked, consensus, Address, BlockHash, Transaction, Txid};
-use clap::{Parser, Subcommand};
+use bitcoin::hex::FromHex;
+use bitcoin::{
+    address::NetworkUnchecked, consensus, Address, BlockHash, OutPoint, Transaction, Txid,
+};
+use cache::Cache;
+use capabilities::Feature;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use client::Client;
+use config::Config;
+use dump::Dump;
 use esplora_client::Builder;
+use fees::TimeSpec;
+use futures::TryStreamExt;
+use lightning::ShortChannelId;
+use liquid::AssetId;
+use pretty::Style;
+use progress::Progress;
+use show::BlockRef;
+use state::TipState;
+use timefmt::TimeFormat;
+use tip::TipChange;
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = error::HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
-    #[clap(long, short, default_value = "https://blockstream.info/api")]
+    /// Network name (bitcoin, testnet, testnet4, signet, liquid, liquidtestnet) or esplora url
+    /// [default: the config file's `default_profile`, else bitcoin]
+    #[clap(long, short, env = "ESPLORA_NETWORK")]
     network: Option<String>,
+    /// Use the backend of a profile from the config file.
+    #[clap(long, short)]
+    profile: Option<String>,
+    /// Esplora url, repeat to fail over to the next url when a backend is unusable.
+    #[clap(long = "url", env = "ESPLORA_URL")]
+    urls: Vec<String>,
+    /// Proxy to send requests through, e.g. `socks5h://127.0.0.1:9050`. Overrides the
+    /// profile's `proxy`.
+    #[clap(long, env = "ESPLORA_PROXY")]
+    proxy: Option<String>,
+    /// HTTP header to send with every request to the backend, as `Name: value`. Repeatable,
+    /// overrides headers of the profile.
+    #[clap(long = "header", value_name = "HEADER", value_parser = config::parse_header)]
+    headers: Vec<(String, String)>,
+    /// Token to send to the backend as `Authorization: Bearer <token>`.
+    #[clap(long, env = "ESPLORA_API_TOKEN", hide_env_values = true)]
+    bearer_token: Option<String>,
+    /// Send at most this many requests per second to the backend, overrides the profile's
+    /// `max_rps`.
+    #[clap(long = "max-rps", global = true)]
+    max_rps: Option<f64>,
+    /// Sign each request with an HMAC-SHA256 over its method, path and timestamp.
+    #[clap(long, env = "ESPLORA_HMAC_KEY", hide_env_values = true)]
+    hmac_key: Option<String>,
+    /// Cache immutable data such as confirmed transactions and block headers in an
+    /// `esplora-cli` subdirectory of this directory.
+    #[clap(long, global = true, env = "ESPLORA_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Cross-check the tip height, and the block hashes and transactions queried, against a
+    /// second esplora url, failing if the backends disagree. It is queried through the same
+    /// proxy and with the same headers.
+    #[clap(long)]
+    verify_against: Option<String>,
+    /// Don't check that the backend's tip is recent before running a command.
+    #[clap(long, global = true)]
+    no_staleness_check: bool,
+    /// Age of the backend's tip above which it is considered stale, e.g. `90m` or `3h`.
+    #[clap(long, global = true, default_value = "2h", value_parser = fees::parse_duration)]
+    max_tip_age: u64,
+    /// Fail instead of warning when the backend's tip is stale.
+    #[clap(long, global = true)]
+    abort_if_stale: bool,
+    /// Output format. JSON output also reports progress of long-running commands as NDJSON
+    /// on stderr.
+    #[clap(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Shorthand for `--format json`.
+    #[clap(long, global = true)]
+    json: bool,
+    /// Don't color `--format pretty` output. Also disabled by setting `NO_COLOR` or when
+    /// stdout isn't a terminal.
+    #[clap(long, global = true)]
+    no_color: bool,
+    /// How to show block and transaction times in text output [default: unix; rfc3339 in
+    /// reports that always showed dates, local for `--format pretty`]
+    #[clap(long = "time-format", global = true, value_enum)]
+    time_format: Option<TimeFormat>,
+    /// Log each request with its status, duration and retries on stderr, and with `-vv` the
+    /// response bodies too.
+    #[clap(long, short, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+/// Output formats selectable with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    /// Tables of transaction inputs and outputs with addresses and amounts, falling back to
+    /// text for other commands.
+    Pretty,
 }
 
 #[derive(Subcommand)]
-#[clap(rename_all = "lower")]
+#[clap(rename_all = "kebab-case")]
 enum Commands {
     /// Get transaction by id.
-    GetTx { txid: Txid },
+    #[clap(alias = "gettx", visible_alias = "tx")]
+    GetTx {
+        txid: Txid,
+        /// Write the serialized transaction as raw bytes, e.g. to redirect to a file.
+        #[clap(long, conflicts_with = "hex")]
+        raw: bool,
+        /// Print the serialized transaction as hex, the default.
+        #[clap(long)]
+        hex: bool,
+    },
     /// Get info of a transaction.
+    #[clap(alias = "gettxinfo")]
     GetTxInfo { txid: Txid },
+    /// Get the transactions listed in a file (one txid per line), in the order listed
+    #[clap(alias = "gettxs")]
+    GetTxs {
+        /// File of txids.
+        #[clap(long)]
+        file: PathBuf,
+        /// Number of transactions to request at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
     /// Get transaction at block index
+    #[clap(alias = "gettxatindex")]
     GetTxAtIndex { hash: BlockHash, index: usize },
     /// Get transaction status by id
-    GetTxStatus { txid: Txid },
+    #[clap(alias = "gettxstatus")]
+    GetTxStatus {
+        /// Transactions to get the status of.
+        #[clap(required_unless_present = "file")]
+        txids: Vec<Txid>,
+        /// File of txids, one per line, to get the status of as well.
+        #[clap(long)]
+        file: Option<PathBuf>,
+        /// Number of statuses to request at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Extract the data embedded in a transaction: OP_RETURN payloads and witness envelopes
+    /// such as inscriptions
+    #[clap(alias = "gettxdata")]
+    GetTxData {
+        txid: Txid,
+        /// Write each payload to a file in this directory instead of printing it.
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
     /// Get block header by block hash
-    GetHeader { hash: BlockHash },
+    #[clap(alias = "getheader")]
+    GetHeader {
+        hash: BlockHash,
+        /// Write the serialized header as raw bytes, e.g. to redirect to a file.
+        #[clap(long, conflicts_with = "hex")]
+        raw: bool,
+        /// Print the serialized header as hex instead of its fields.
+        #[clap(long)]
+        hex: bool,
+    },
     /// Get block status by block hash
+    #[clap(alias = "getblockstatus")]
     GetBlockStatus { hash: BlockHash },
     /// Get block by block hash
+    #[clap(alias = "getblock", visible_alias = "block")]
     GetBlock { hash: BlockHash },
+    /// Summarize a block by hash or height: fees, feerates, weight used, subsidy and segwit
+    /// adoption
+    #[clap(alias = "getblockinfo")]
+    GetBlockInfo {
+        block: BlockRef,
+        /// Compute the summary from the block's transactions even if the backend provides one.
+        #[clap(long)]
+        local: bool,
+        /// Number of pages of transactions to request at once when computing the summary.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Decode a block's coinbase transaction by block hash or height: BIP34 height, miner tag,
+    /// outputs, and the reward against the subsidy
+    #[clap(alias = "getcoinbase")]
+    GetCoinbase { block: BlockRef },
+    /// Compare a block with the template the backend expected: match rate, health, and the
+    /// transactions added and missing (mempool.space backends only)
+    #[clap(alias = "getblockaudit")]
+    GetBlockAudit {
+        block: BlockRef,
+        /// Also list the block's transactions that paid far below its feerate floor, even
+        /// with their children in the block, i.e. likely confirmed out of band.
+        #[clap(long)]
+        out_of_band: bool,
+        /// Number of pages of transactions to request at once when listing them.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
     /// Get transaction merkle proof by tx id
+    #[clap(alias = "getmerkleproof")]
     GetMerkleProof { txid: Txid },
     /// Get transaction merkle block inclusion proof by id
+    #[clap(alias = "getmerkleblock")]
     GetMerkleBlock { txid: Txid },
     /// Get output spending status by tx id and output index
+    #[clap(alias = "getoutputstatus")]
     GetOutputStatus { txid: Txid, index: u64 },
     /// Broadcast transaction.
-    Broadcast { tx_hex: String },
-    /// Get best blockhash and height
-    GetTip,
+    Broadcast {
+        tx_hex: String,
+        /// Check the transaction against the backend instead of broadcasting it.
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+    },
+    /// Broadcast a child and its unconfirmed parents together as a package, so that the child
+    /// can pay for parents below the mempool minimum feerate
+    #[clap(alias = "broadcastpackage")]
+    BroadcastPackage {
+        /// Transactions as hex, parents first and the child last.
+        #[clap(required = true)]
+        tx_hexes: Vec<String>,
+    },
+    /// Get the height, hash and time of the best block
+    #[clap(alias = "gettip", visible_alias = "tip")]
+    GetTip {
+        /// Wait for the tip to change and print the new one.
+        #[clap(long)]
+        wait: bool,
+        /// Time between polls with `--wait`, e.g. `5s` or `1m`.
+        #[clap(long, default_value = "10s", value_parser = fees::parse_duration)]
+        interval: u64,
+    },
     /// Get block hash at height
+    #[clap(alias = "getblockhash")]
     GetBlockHash { height: u32 },
     /// Get a fee estimate by confirmation target in sat/vB
+    #[clap(alias = "getfeeestimates", visible_alias = "fees")]
     GetFeeEstimates,
+    /// Work out the fee a replacement of an unconfirmed transaction must pay and build it as a
+    /// PSBT when its change output can be identified
+    #[clap(alias = "bumpfee")]
+    BumpFee {
+        txid: Txid,
+        /// Confirmation target in blocks to take the backend's feerate estimate for.
+        #[clap(long, default_value_t = 3)]
+        target: u16,
+    },
+    /// Work out the fee a child spending an unspent output of an unconfirmed transaction must
+    /// pay to lift it to a target feerate, and when it would confirm
+    Cpfp {
+        txid: Txid,
+        /// Confirmation target in blocks to take the backend's feerate estimate for.
+        #[clap(long, default_value_t = 3)]
+        target: u16,
+        /// Target feerate in sat/vB, instead of the backend's estimate.
+        #[clap(long, conflicts_with = "target")]
+        feerate: Option<f64>,
+        /// Virtual size of the child in vbytes, instead of guessing it from each output's script
+        /// type.
+        #[clap(long = "child-vsize")]
+        child_vsize: Option<u64>,
+    },
+    /// Estimate which block a transaction paying a feerate would be mined in, and roughly how
+    /// long that takes
+    #[clap(alias = "whenconfirm")]
+    WhenConfirm {
+        /// Feerate in sat/vB.
+        #[clap(long)]
+        feerate: f64,
+        /// Virtual size of the transaction in vbytes.
+        #[clap(long, default_value_t = 200)]
+        vsize: u64,
+    },
+    /// List the most recent replacements seen by the backend (mempool.space backends only)
+    #[clap(alias = "getreplacements")]
+    GetReplacements {
+        /// Only list replacements of transactions that didn't signal.
+        #[clap(long = "full-rbf")]
+        full_rbf: bool,
+    },
+    /// Get the replacement history of a transaction (mempool.space backends only)
+    #[clap(alias = "gettxrbf")]
+    GetTxRbf { txid: Txid },
     /// Get confirmed transaction history for the specified address/scripthash sorted by date
+    #[clap(name = "get-scripthash-txs", alias = "getscripthashtxs")]
     GetScriptHashTxs {
-        address: Address<NetworkUnchecked>,
+        /// Address, scriptPubKey in hex or scripthash (the SHA256 of a scriptPubKey).
+        target: String,
         last_seen: Option<Txid>,
+        /// Take the target to be a scriptPubKey in hex.
+        #[clap(long, conflicts_with = "scripthash")]
+        script: bool,
+        /// Take the target to be a scripthash.
+        #[clap(long)]
+        scripthash: bool,
+    },
+    /// Decode an address offline: the networks it's valid for, its script type, scriptPubKey
+    /// and the scripthash to query it by
+    #[clap(alias = "validateaddress")]
+    ValidateAddress { address: Address<NetworkUnchecked> },
+    /// List the addresses the backend knows of starting with a prefix, e.g. to recover a
+    /// mistyped address (at most 10)
+    #[clap(alias = "searchaddress")]
+    SearchAddress { prefix: String },
+    /// Export the confirmed history of an address with the credit or debit of each transaction
+    /// as CSV
+    #[clap(alias = "exporthistory")]
+    ExportHistory {
+        address: Address<NetworkUnchecked>,
+        /// File to write the CSV to.
+        #[clap(long)]
+        csv: PathBuf,
+        /// Add the price and value of each transaction in this currency, e.g. `USD`
+        /// (mempool.space backends only).
+        #[clap(long)]
+        fiat: Option<String>,
+        /// Number of prices to look up at once with `--fiat`.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Get the confirmed and unconfirmed balance of every address in a file, one per line, with
+    /// their total
+    Balances {
+        /// File of addresses.
+        #[clap(long)]
+        file: PathBuf,
+        /// Number of addresses to request at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
     },
     /// Get recent block summaries at the tip or at height if provided (max summaries is backend
     /// dependent).
+    #[clap(alias = "getblocks")]
     GetBlocks {
         /// Height to fetch blocks from.
-        #[clap(long, short = 's')]
+        #[clap(long, short = 's', conflicts_with_all = ["from", "to"])]
         height: Option<u32>,
+        /// Walk the blocks from this height up, as many pages as it takes.
+        #[clap(long)]
+        from: Option<u32>,
+        /// Height to stop the walk at [default: the tip]
+        #[clap(long, requires = "from")]
+        to: Option<u32>,
+    },
+    /// Print the hash of every Nth block, to embed as trusted checkpoints in light clients
+    Checkpoints {
+        /// Number of blocks between checkpoints.
+        #[clap(long, default_value_t = 2016)]
+        every: u32,
+        /// Height of the first checkpoint.
+        #[clap(long, default_value_t = 0)]
+        from: u32,
+        /// Height to stop at [default: the last block with 6 confirmations]
+        #[clap(long)]
+        to: Option<u32>,
+        /// Print the checkpoints as a Rust const array with this name.
+        #[clap(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "CHECKPOINTS")]
+        rust: Option<String>,
+        /// Number of block hashes to request at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Show an explorer-style detail page in the terminal pager
+    Show {
+        #[command(subcommand)]
+        page: ShowCommands,
+    },
+    /// Query the Lightning network (mempool.space backends only)
+    Ln {
+        #[command(subcommand)]
+        command: LnCommands,
+    },
+    /// Append the current fee estimates to the fee log
+    #[clap(alias = "feelog")]
+    FeeLog {
+        /// Fee log to append to [default: $XDG_DATA_HOME/esplora-cli/fees.jsonl]
+        #[clap(long)]
+        file: Option<PathBuf>,
+    },
+    /// Report how each fee tier moved over a window of the fee log
+    #[clap(alias = "feediff")]
+    FeeDiff {
+        /// Start of the window, e.g. `2h ago` or a UNIX timestamp.
+        #[clap(long, default_value = "1h ago")]
+        from: TimeSpec,
+        /// End of the window, e.g. `now` or a UNIX timestamp.
+        #[clap(long, default_value = "now")]
+        to: TimeSpec,
+        /// Fee log to read [default: $XDG_DATA_HOME/esplora-cli/fees.jsonl]
+        #[clap(long)]
+        file: Option<PathBuf>,
+    },
+    /// Print the median feerate of each of the last blocks and its percentiles over the window
+    #[clap(alias = "feehistory")]
+    FeeHistory {
+        /// Number of blocks up to the tip to look at.
+        #[clap(long, default_value_t = 144)]
+        blocks: u32,
+        /// Compute the feerates from the blocks' transactions even if the backend provides them.
+        #[clap(long)]
+        local: bool,
+        /// Number of blocks to summarize at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+        /// Also write the blocks as CSV to this file.
+        #[clap(long)]
+        csv: Option<PathBuf>,
+    },
+    /// List the unspent outputs of an address or descriptor with what each costs to spend,
+    /// flagging the uneconomical ones, and the fee to consolidate them now and at a low-fee
+    /// target
+    #[clap(alias = "dustreport")]
+    DustReport {
+        /// An address, or an external (receive) descriptor or multipath `<0;1>` descriptor.
+        wallet: String,
+        /// Internal (change) descriptor.
+        #[clap(long)]
+        change: Option<String>,
+        /// Confirmation target in blocks of the current feerate.
+        #[clap(long, default_value_t = 3)]
+        target: u16,
+        /// Confirmation target in blocks of the low feerate.
+        #[clap(long = "low-target", default_value_t = 1008)]
+        low_target: u16,
+        /// Number of consecutive unused scripts after which to stop scanning a keychain.
+        #[clap(long = "gap-limit", default_value_t = 20)]
+        gap_limit: u32,
+        /// Number of script histories to request at once.
+        #[clap(long, default_value_t = 4)]
+        parallel: u32,
+    },
+    /// Get issuance and supply information of a Liquid asset
+    #[clap(alias = "getasset")]
+    GetAsset { asset_id: AssetId },
+    /// Scan a wallet descriptor and report net flows per transaction
+    Scan {
+        /// External (receive) descriptor, or a multipath `<0;1>` descriptor.
+        descriptor: String,
+        /// Internal (change) descriptor.
+        #[clap(long)]
+        change: Option<String>,
+        /// Number of consecutive unused scripts after which to stop scanning a keychain.
+        #[clap(long = "gap-limit", default_value_t = 20)]
+        gap_limit: u32,
+        /// Number of script histories to request at once.
+        #[clap(long, default_value_t = 4)]
+        parallel: u32,
+    },
+    /// Run a BDK full scan (or sync) of a descriptor through bdk_esplora and print the wallet
+    /// update it returns as JSON. Only the primary url is used, and --max-rps and --hmac-key
+    /// are refused
+    #[clap(alias = "bdksync")]
+    BdkSync {
+        /// External (receive) descriptor, or a multipath `<0;1>` descriptor.
+        descriptor: String,
+        /// Internal (change) descriptor.
+        #[clap(long)]
+        change: Option<String>,
+        /// Number of consecutive unused scripts after which to stop a full scan.
+        #[clap(long = "stop-gap", default_value_t = 20)]
+        stop_gap: usize,
+        /// Sync the first N scripts of each keychain instead of running a full scan.
+        #[clap(long, value_name = "N")]
+        sync: Option<u32>,
+        /// Number of requests to make at once.
+        #[clap(long, default_value_t = 4)]
+        parallel: usize,
+    },
+    /// Wait until the tip reaches a height (or the next block) and print the new tip hash
+    #[clap(alias = "waitforblock")]
+    WaitForBlock {
+        /// Height to wait for [default: the next block]
+        #[clap(long)]
+        height: Option<u32>,
+        /// Time to wait before giving up, e.g. `30m` or `2h`.
+        #[clap(long, value_parser = fees::parse_duration)]
+        timeout: Option<u64>,
+        /// Time between polls, e.g. `10s` or `1m`.
+        #[clap(long, default_value = "10s", value_parser = fees::parse_duration)]
+        interval: u64,
+    },
+    /// Stream transactions added to and removed from the mempool as NDJSON
+    #[clap(alias = "mempooldelta")]
+    MempoolDelta {
+        /// Time between mempool snapshots, e.g. `5s` or `1m`.
+        #[clap(long, default_value = "10s", value_parser = fees::parse_duration)]
+        interval: u64,
+        /// Number of added transactions to look up at once.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Tail new mempool and confirmed transactions of an address with the change to its
+    /// balance
+    #[clap(alias = "watchaddress")]
+    WatchAddress {
+        address: Address<NetworkUnchecked>,
+        /// Time between polls, e.g. `30s` or `1m`.
+        #[clap(long, default_value = "30s", value_parser = fees::parse_duration)]
+        interval: u64,
+    },
+    /// Wait for an output to be spent and print the spending transaction, optionally running a
+    /// command
+    #[clap(alias = "watchutxo")]
+    WatchUtxo {
+        /// The output, as `txid:vout`.
+        outpoint: OutPoint,
+        /// Command to run through `sh -c` once the output is spent. It gets the outpoint and
+        /// spending transaction in `ESPLORA_OUTPOINT`, `ESPLORA_SPENDING_TXID`,
+        /// `ESPLORA_SPENDING_VIN` and `ESPLORA_SPENDING_HEIGHT`.
+        #[clap(long)]
+        exec: Option<String>,
+        /// Time between polls, e.g. `30s` or `1m`.
+        #[clap(long, default_value = "30s", value_parser = fees::parse_duration)]
+        interval: u64,
+    },
+    /// Time the tip, header, transaction and scripthash endpoints and report their p50/p95
+    /// latency and error rate
+    Bench {
+        /// Number of times each endpoint is requested.
+        #[clap(long, default_value_t = 20)]
+        iterations: usize,
+        /// Esplora url of a second backend to benchmark side by side, queried through the same
+        /// proxy and with the same headers.
+        #[clap(long)]
+        compare: Option<String>,
+    },
+    /// Probe which optional endpoints the backend serves, such as the mempool.space ones
+    Capabilities,
+    /// Report backend reachability, latency, tip freshness and lag behind a reference
+    Health {
+        /// Esplora url of a reference instance to measure lag against, queried through the
+        /// same proxy and with the same headers.
+        #[clap(long)]
+        reference: Option<String>,
+    },
+    /// Poll the backend and export tip, mempool, fee and watched address/tx gauges for
+    /// Prometheus
+    #[clap(alias = "servemetrics")]
+    ServeMetrics {
+        /// Address to serve metrics on.
+        #[clap(long, default_value = "127.0.0.1:9184")]
+        listen: String,
+        /// Time between polls, e.g. `30s` or `1m`.
+        #[clap(long, default_value = "30s", value_parser = fees::parse_duration)]
+        interval: u64,
+        /// Address to export the balance of, may be repeated.
+        #[clap(long = "watch-address")]
+        watch_addresses: Vec<Address<NetworkUnchecked>>,
+        /// Transaction to export the confirmations of, may be repeated.
+        #[clap(long = "watch-tx")]
+        watch_txs: Vec<Txid>,
+    },
+    /// Serve a Bitcoin Core compatible JSON-RPC subset (getblockhash, getrawtransaction,
+    /// sendrawtransaction, getblockheader, estimatesmartfee) backed by the esplora instance
+    #[clap(alias = "serverpc")]
+    ServeRpc {
+        /// Address to serve JSON-RPC on.
+        #[clap(long, default_value = "127.0.0.1:8332")]
+        listen: String,
+    },
+    /// Track a watch-only wallet in a local index synced incrementally from the backend
+    Track {
+        #[command(subcommand)]
+        command: TrackCommands,
+    },
+    /// Check whether the tip seen on the last run is still in the best chain
+    #[clap(alias = "checktip")]
+    CheckTip,
+    /// Inspect or clear the cache given with --cache-dir
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Move the persisted state to another machine
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
+    /// Start an interactive session running commands against one backend
+    Repl,
+    /// Download a range of block headers to a file of 80-byte headers, validating the chain
+    #[clap(alias = "syncheaders")]
+    SyncHeaders {
+        /// First height to download.
+        #[clap(long)]
+        from: u32,
+        /// Last height to download [default: the current tip]
+        #[clap(long)]
+        to: Option<u32>,
+        /// File to write the headers to.
+        #[clap(long)]
+        out: PathBuf,
+        /// Number of headers to request at once, lower it for rate limited backends.
+        #[clap(long, default_value_t = 8)]
+        jobs: usize,
+    },
+    /// Print a shell completion script
+    Completions { shell: Shell },
+    /// Print the man page, or write one per command to a directory
+    Mangen {
+        /// Directory to write the man pages of all commands to.
+        #[clap(long = "out-dir")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Find public esplora instances and save them as profiles
+    Endpoints {
+        #[command(subcommand)]
+        command: EndpointsCommands,
     },
 }
 
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum ShowCommands {
+    /// Show block header, stats and transaction list by block hash or height
+    Block { block: BlockRef },
+    /// Show transaction summary, inputs and outputs by tx id
+    Tx { txid: Txid },
+}
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum LnCommands {
+    /// Get network-wide statistics
+    Stats,
+    /// Get node details by public key or alias
+    Node { node: String },
+    /// Get channel details by short channel id
+    Channel { id: ShortChannelId },
+}
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum CacheCommands {
+    /// Print the number of cached entries and their total size
+    Stats,
+    /// Remove all cached entries
+    Clear,
+}
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum StateCommands {
+    /// Package the state and data directories (tip, watch list, REPL history, tracker
+    /// database and fee log), and the cache of --cache-dir, into a bundle
+    Export {
+        /// Bundle to write, a zstd compressed tarball.
+        #[clap(long)]
+        bundle: PathBuf,
+    },
+    /// Restore the state and data directories, and the cache into --cache-dir, from a bundle
+    Import {
+        /// Bundle to read.
+        #[clap(long)]
+        bundle: PathBuf,
+        /// Replace existing state and data files.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum TrackCommands {
+    /// Start tracking a wallet
+    Add {
+        /// External (receive) descriptor, or a multipath `<0;1>` descriptor.
+        descriptor: String,
+        /// Internal (change) descriptor.
+        #[clap(long)]
+        change: Option<String>,
+        /// Name to track the wallet under.
+        #[clap(long, default_value = "default")]
+        wallet: String,
+    },
+    /// Fetch what changed since the last sync
+    Sync {
+        /// Name of the wallet.
+        #[clap(long, default_value = "default")]
+        wallet: String,
+        /// Number of consecutive unused scripts after which to stop deriving new ones.
+        #[clap(long = "gap-limit", default_value_t = 20)]
+        gap_limit: u32,
+        /// Number of script histories to request at once.
+        #[clap(long, default_value_t = 4)]
+        parallel: u32,
+    },
+    /// Print the balance as of the last sync
+    Balance {
+        /// Name of the wallet.
+        #[clap(long, default_value = "default")]
+        wallet: String,
+    },
+    /// List the transactions as of the last sync with their effect on the balance
+    Txs {
+        /// Name of the wallet.
+        #[clap(long, default_value = "default")]
+        wallet: String,
+    },
+}
+
+#[derive(Subcommand)]
+#[clap(rename_all = "kebab-case")]
+enum EndpointsCommands {
+    /// Probe known instances for health and network and offer the best as profiles
+    Discover {
+        /// Add the best instance of each network as a profile without asking.
+        #[clap(long, short)]
+        yes: bool,
+    },
+    /// Replace the known instance list with one fetched from a url (a JSON array of urls)
+    Update { source: String },
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
-    let network = cli.network.expect("must set esplora url");
-    let builder = Builder::new(&network);
-    let client = builder.build_async()?;
+async fn main() -> ExitCode {
+    let parsed = Cli::command()
+        .try_get_matches()
+        .and_then(|matches| Ok((Cli::from_arg_matches(&matches)?, matches)));
+    let (mut cli, matches) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) if e.use_stderr() => {
+            let _ = e.print();
+            return ExitCode::from(error::Kind::InvalidInput.code());
+        }
+        Err(e) => e.exit(),
+    };
+    let json = cli.json || cli.format == Format::Json;
+    logging::init(cli.verbose);
+    if let Some(format) = cli.time_format {
+        timefmt::init(format);
+    }
+    let result = match select_backend(&mut cli, &matches) {
+        Ok(()) => try_main(cli).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => error::report(&e, json),
+    }
+}
 
+/// Settle which of `--url`, `--network` and `--profile` selects the backend.
+///
+/// A flag takes precedence over the `ESPLORA_URL` and `ESPLORA_NETWORK` environment variables,
+/// and `ESPLORA_URL` over `ESPLORA_NETWORK`. Without any of them the config file's
+/// `default_profile` is used, and otherwise the default network.
+fn select_backend(cli: &mut Cli, matches: &ArgMatches) -> anyhow::Result<()> {
+    let flags: Vec<&str> = [("urls", "--url"), ("network", "--network"), ("profile", "--profile")]
+        .into_iter()
+        .filter(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .map(|(_, flag)| flag)
+        .collect();
+    match flags[..] {
+        [] => {}
+        ["--url"] => cli.network = None,
+        ["--network"] => cli.urls.clear(),
+        ["--profile"] => {
+            cli.urls.clear();
+            cli.network = None;
+        }
+        _ => {
+            return Err(error::invalid_input(format!(
+                "{} can't be used with {}",
+                flags[0], flags[1]
+            )))
+        }
+    }
+    Ok(())
+}
+
+async fn try_main(cli: Cli) -> anyhow::Result<()> {
+    let profile = match &cli.profile {
+        Some(name) => Some(Config::load()?.profile(name)?.clone()),
+        None if cli.urls.is_empty() && cli.network.is_none() => {
+            Config::load()?.default_profile()?.cloned()
+        }
+        None => None,
+    };
+    let (network, fallback_urls) = match (&profile, cli.urls.split_first()) {
+        (_, Some((url, fallbacks))) => (url.clone(), fallbacks.to_vec()),
+        (Some(profile), None) => (profile.url.clone(), profile.fallback_urls.clone()),
+        (None, None) => (
+            config::network_url(cli.network.as_deref().unwrap_or("bitcoin")),
+            vec![],
+        ),
+    };
+    let is_liquid = liquid::is_liquid(&network);
+    let mut builder = Builder::new(&network);
+    if let Some(proxy) = cli.proxy.as_ref().or(profile.as_ref().and_then(|p| p.proxy.as_ref())) {
+        builder = builder.proxy(proxy);
+    }
+    let headers =
+        config::request_headers(profile.as_ref(), &cli.headers, cli.bearer_token.as_deref());
+    for (name, value) in &headers {
+        builder = builder.header(name, value);
+    }
+    let transport = builder.clone();
+    let mut client = Client::from_builder(builder)?.fallback_urls(fallback_urls);
+    if let Some(key) = &cli.hmac_key {
+        client = client.hmac_key(key.as_bytes());
+    }
+    if let Some(rps) = cli.max_rps.or(profile.as_ref().and_then(|p| p.max_rps)) {
+        if rps.is_nan() || rps <= 0.0 {
+            return Err(error::invalid_input("--max-rps must be positive"));
+        }
+        client = client.max_rps(rps);
+    }
+    if let Some(dir) = &cli.cache_dir {
+        client = client.cache(Cache::new(dir, &network));
+    }
+
+    let session = Session {
+        client,
+        network,
+        is_liquid,
+        json: cli.json || cli.format == Format::Json,
+        pretty: (!cli.json && cli.format == Format::Pretty).then(|| Style::detect(cli.no_color)),
+        cache_dir: cli.cache_dir,
+        transport,
+    };
+    // Health reports the age of the tip itself.
+    let is_health = matches!(cli.command, Commands::Health { .. });
+    if !cli.no_staleness_check && cli.command.uses_backend() && !is_health {
+        tip::check_staleness(&session.client, cli.max_tip_age, cli.abort_if_stale).await?;
+    }
+    let checks = cli.command.checks();
     match cli.command {
-        Commands::GetTx { txid } => {
-            let tx = client.get_tx(&txid).await?.ok_or(anyhow!("None"))?;
-            println!("{:#?}", bitcoin::consensus::encode::serialize_hex(&tx));
+        Commands::Repl => repl::repl(&session).await?,
+        command => run(command, &session).await?,
+    }
+
+    if let Some(url) = &cli.verify_against {
+        let other = session.client_for(url)?;
+        let mismatches = verify::verify(&session.client, &other, &checks).await?;
+        for mismatch in &mismatches {
+            eprintln!(
+                "mismatch on {}: {} says {}, {} says {}",
+                mismatch.check, session.network, mismatch.primary, url, mismatch.other
+            );
+        }
+        if !mismatches.is_empty() {
+            return Err(anyhow!("{} and {} disagree", session.network, url));
+        }
+    }
+
+    Ok(())
+}
+
+impl Commands {
+    /// Whether the command queries the selected backend.
+    fn uses_backend(&self) -> bool {
+        if let Commands::Track { command } = self {
+            return matches!(command, TrackCommands::Sync { .. });
+        }
+        !matches!(
+            self,
+            Commands::FeeDiff { .. }
+                | Commands::ValidateAddress { .. }
+                | Commands::Cache { .. }
+                | Commands::State { .. }
+                | Commands::Endpoints { .. }
+                | Commands::Completions { .. }
+                | Commands::Mangen { .. }
+        )
+    }
+}
+
+impl Commands {
+    /// Facts a second backend must agree on for the result of the command to be trusted.
+    fn checks(&self) -> Vec<verify::Check> {
+        use verify::Check;
+
+        if !self.uses_backend() || matches!(self, Commands::Repl) {
+            return vec![];
+        }
+        let mut checks = vec![Check::TipHeight];
+        match self {
+            Commands::GetTx { txid, .. }
+            | Commands::GetTxInfo { txid }
+            | Commands::GetMerkleProof { txid }
+            | Commands::GetMerkleBlock { txid }
+            | Commands::GetOutputStatus { txid, .. }
+            | Commands::Show {
+                page: ShowCommands::Tx { txid },
+            } => checks.push(Check::Tx(*txid)),
+            Commands::GetTxStatus { txids, .. } => {
+                checks.extend(txids.iter().copied().map(Check::Tx))
+            }
+            Commands::GetBlockHash { height }
+            | Commands::Show {
+                page:
+                    ShowCommands::Block {
+                        block: BlockRef::Height(height),
+                    },
+            } => checks.push(Check::BlockHash(*height)),
+            _ => {}
+        }
+        checks
+    }
+}
+
+/// The wallet tracked under `name`.
+fn tracked_wallet(db: &tracker::Db, name: &str) -> anyhow::Result<tracker::Wallet> {
+    db.wallet(name)?
+        .ok_or_else(|| anyhow!("no wallet `{}` is tracked, add it with `track add`", name))
+}
+
+/// Write `bytes` to stdout as they are, refusing to garble a terminal with them.
+fn write_raw(bytes: &[u8]) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    if stdout.is_terminal() {
+        return Err(error::invalid_input(
+            "refusing to write raw bytes to a terminal, redirect stdout or use --hex",
+        ));
+    }
+    stdout.write_all(bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The backend and global options commands run against.
+struct Session {
+    client: Client,
+    /// Url of the backend.
+    network: String,
+    is_liquid: bool,
+    json: bool,
+    /// Set for `--format pretty`.
+    pretty: Option<Style>,
+    cache_dir: Option<PathBuf>,
+    /// The builder of the client, for the proxy and headers of clients of other backends.
+    transport: Builder,
+}
+
+impl Session {
+    /// A client of the backend at `url`, going through the same proxy and sending the same
+    /// headers as the session's client.
+    fn client_for(&self, url: &str) -> anyhow::Result<Client> {
+        Ok(Client::from_builder(self.builder_for(url))?)
+    }
+
+    /// The builder of [`Session::client_for`], for callers that adjust it further.
+    fn builder_for(&self, url: &str) -> Builder {
+        Builder {
+            base_url: url.to_string(),
+            ..self.transport.clone()
+        }
+    }
+}
+
+/// Run a single command.
+async fn run(command: Commands, session: &Session) -> anyhow::Result<()> {
+    let Session {
+        client,
+        network,
+        is_liquid,
+        json,
+        pretty,
+        cache_dir,
+        ..
+    } = session;
+    let (is_liquid, json, pretty) = (*is_liquid, *json, *pretty);
+    let progress = Progress::new(json);
+
+    match command {
+        Commands::GetTx { txid, raw, .. } if is_liquid => {
+            let tx_hex = liquid::get_tx_hex(client, &txid).await?;
+            if raw {
+                write_raw(&Vec::from_hex(tx_hex.trim())?)?;
+            } else {
+                println!("{}", tx_hex.trim());
+            }
+        }
+        Commands::GetTx { txid, raw, .. } => {
+            if let (Some(style), false) = (pretty, raw) {
+                print!("{}", pretty::tx(client, &txid, style).await?);
+                return Ok(());
+            }
+            let tx = client
+                .get_tx(&txid)
+                .await?
+                .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+            if raw {
+                write_raw(&consensus::serialize(&tx))?;
+            } else {
+                println!("{}", consensus::encode::serialize_hex(&tx));
+            }
+        }
+        Commands::GetTxInfo { txid } if is_liquid => {
+            let res = liquid::get_tx_info(client, &txid).await?;
+            println!("{:#}", res);
         }
         Commands::GetTxInfo { txid } => {
-            let res = client.get_tx_info(&txid).await?;
-            println!("{:#?}", res);
+            if let Some(style) = pretty {
+                print!("{}", pretty::tx(client, &txid, style).await?);
+                return Ok(());
+            }
+            if json {
+                // The backend's JSON as is, `Tx` doesn't serialize.
+                let mut value: serde_json::Value = client
+                    .get_opt_json(&format!("/tx/{}", txid))
+                    .await?
+                    .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+                let tx: esplora_client::Tx = serde_json::from_value(value.clone())?;
+                let outspends = client.get_tx_outspends(&txid).await?;
+                value["out_of_band"] = match oob::assess(client, &tx, &outspends).await? {
+                    Some(assessment) => oob::report_json(&assessment),
+                    None => serde_json::Value::Null,
+                };
+                println!("{}", value);
+                return Ok(());
+            }
+            let res = client
+                .get_tx_info(&txid)
+                .await?
+                .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+            println!("{:#?}", Dump(&res));
+        }
+        Commands::GetTxs { file, jobs } => {
+            let txids = batch::read_txids(&file)?;
+            progress.interruptible();
+            let txs = batch::get_txs(client, &txids, jobs, &progress).await?;
+            if json {
+                let txs: Vec<_> = txids
+                    .iter()
+                    .zip(&txs)
+                    .map(|(txid, tx)| {
+                        serde_json::json!({ "txid": txid, "hex": consensus::encode::serialize_hex(tx) })
+                    })
+                    .collect();
+                println!("{}", serde_json::Value::Array(txs));
+            } else {
+                for tx in &txs {
+                    println!("{}", consensus::encode::serialize_hex(tx));
+                }
+            }
+            if txs.len() < txids.len() {
+                return Err(error::interrupted(format!(
+                    "{} of {} transactions",
+                    txs.len(),
+                    txids.len()
+                )));
+            }
         }
         Commands::GetTxAtIndex { hash, index } => {
-            let txid = client
-                .get_txid_at_block_index(&hash, index)
-                .await?
-                .ok_or(anyhow!("None"))?;
+            let txid = client.get_txid_at_block_index(&hash, index).await?.ok_or_else(|| {
+                error::not_found(format!("transaction {} of block {}", index, hash))
+            })?;
             println!("{:#?}", txid);
         }
-        Commands::GetTxStatus { txid } => {
-            let tx_status = client.get_tx_status(&txid).await?;
-            println!("{:#?}", tx_status);
+        Commands::GetTxStatus {
+            mut txids,
+            file,
+            jobs,
+        } => {
+            if let Some(file) = file {
+                txids.extend(batch::read_txids(&file)?);
+            }
+            let states = batch::get_tx_states(client, &txids, jobs, &progress).await?;
+            let failed = states.iter().filter(|s| s.is_err()).count();
+
+            if json {
+                let rows: Vec<_> = txids
+                    .iter()
+                    .zip(&states)
+                    .map(|(txid, state)| match state {
+                        Ok(state) => {
+                            let mut row = serde_json::to_value(state)?;
+                            row["txid"] = serde_json::json!(txid);
+                            anyhow::Ok(row)
+                        }
+                        Err(e) => {
+                            Ok(serde_json::json!({ "txid": txid, "error": format!("{:#}", e) }))
+                        }
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                println!("{}", serde_json::Value::Array(rows));
+            } else {
+                for (txid, state) in txids.iter().zip(&states) {
+                    match state {
+                        Ok(state) if state.confirmed => println!(
+                            "{}  confirmed    height {}  {} confirmations  {}",
+                            txid,
+                            state.height.map_or("?".to_string(), |h| h.to_string()),
+                            state.confirmations,
+                            state.block_time.map_or("?".to_string(), timefmt::format_rfc3339)
+                        ),
+                        Ok(_) => println!("{}  unconfirmed", txid),
+                        Err(e) => println!("{}  error: {:#}", txid, e),
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow!("failed to fetch {} of {} statuses", failed, txids.len()));
+            }
+        }
+        Commands::GetTxData { txid, output } => {
+            let tx = client
+                .get_tx(&txid)
+                .await?
+                .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+            let data = txdata::extract(&tx);
+            if let Some(dir) = output {
+                let paths = txdata::write_files(&dir, &txid, &data)?;
+                if json {
+                    println!("{}", serde_json::json!({ "files": paths }));
+                } else {
+                    for path in paths {
+                        println!("Wrote {}", path.display());
+                    }
+                }
+            } else if json {
+                println!("{}", serde_json::to_string(&data)?);
+            } else {
+                print!("{}", txdata::report(&data));
+            }
         }
-        Commands::GetHeader { hash } => {
+        Commands::GetHeader { hash, raw, hex } => {
             let header = client.get_header_by_hash(&hash).await?;
-            println!("{:#?}", header);
+            if raw {
+                write_raw(&consensus::serialize(&header))?;
+            } else if hex {
+                println!("{}", consensus::encode::serialize_hex(&header));
+            } else {
+                println!("{:#?}", header);
+            }
         }
         Commands::GetBlockStatus { hash } => {
             let status = client.get_block_status(&hash).await?;
             println!("{:#?}", status);
         }
+        Commands::GetBlock { hash } if is_liquid => {
+            for txid in client.get_block_txids(&hash).await? {
+                println!("{:#?}", txid);
+            }
+        }
         Commands::GetBlock { hash } => {
-            let block = client.get_block_by_hash(&hash).await?.ok_or(anyhow!("None"))?;
+            let block = client
+                .get_block_by_hash(&hash)
+                .await?
+                .ok_or_else(|| error::not_found(format!("block {}", hash)))?;
             for tx in &block.txdata {
                 println!("{:#?}", tx.compute_txid());
             }
         }
+        Commands::GetBlockInfo { block, local, jobs } => {
+            let hash = block.resolve(client).await?;
+            let stats = blockstats::block_stats(client, &hash, local, jobs, &progress).await?;
+            if json {
+                println!("{}", serde_json::to_string(&stats)?);
+            } else {
+                print!("{}", blockstats::report(&stats));
+            }
+        }
+        Commands::GetCoinbase { block } => {
+            let coinbase = coinbase::coinbase(client, block).await?;
+            if json {
+                println!("{}", serde_json::to_string(&coinbase)?);
+            } else {
+                print!("{}", coinbase::report(&coinbase));
+            }
+        }
+        Commands::GetBlockAudit {
+            block,
+            out_of_band,
+            jobs,
+        } => {
+            let hash = block.resolve(client).await?;
+            let mut audit = audit::audit(client, &hash).await?;
+            if out_of_band {
+                let flagged = oob::scan_block(client, &hash, jobs)
+                    .await?
+                    .ok_or_else(|| error::unsupported("the backend has no block feerates"))?;
+                audit.out_of_band = Some(flagged);
+            }
+            if json {
+                println!("{}", serde_json::to_string(&audit)?);
+            } else {
+                print!("{}", audit::report(&audit));
+            }
+        }
         Commands::GetMerkleProof { txid } => {
             let res = client.get_merkle_proof(&txid).await?;
             println!("{:#?}", res);
@@ -114,16 +1256,55 @@ async fn main() -> anyhow::Result<()> {
             println!("{:#?}", res);
         }
         Commands::GetOutputStatus { txid, index } => {
-            let status = client.get_output_status(&txid, index).await?.ok_or(anyhow!("None"))?;
-            println!("{:#?}", status);
+            let status = client
+                .get_output_status(&txid, index)
+                .await?
+                .ok_or_else(|| error::not_found(format!("output {}:{}", txid, index)))?;
+            println!("{:#?}", Dump(&status));
         }
-        Commands::Broadcast { tx_hex } => {
+        Commands::Broadcast { tx_hex, dry_run } => {
             let tx: Transaction = consensus::encode::deserialize_hex(&tx_hex)?;
-            client.broadcast(&tx).await?;
+            if dry_run {
+                let checks = broadcast::dry_run(client, &tx).await?;
+                if json {
+                    println!("{}", serde_json::to_string(&checks)?);
+                } else {
+                    print!("{}", broadcast::report(&checks));
+                }
+                if let Some(rejection) = broadcast::failure(&checks) {
+                    return Err(rejection.into());
+                }
+            } else {
+                broadcast::broadcast(client, &tx).await?;
+            }
         }
-        Commands::GetTip => {
-            let blocks = client.get_blocks(None).await?;
-            println!("{:#?}", &blocks[0]);
+        Commands::BroadcastPackage { tx_hexes } => {
+            let txs = tx_hexes
+                .iter()
+                .map(|hex| consensus::encode::deserialize_hex(hex))
+                .collect::<Result<Vec<Transaction>, _>>()?;
+            let result = broadcast::broadcast_package(client, &txs).await?;
+            if json {
+                println!("{}", serde_json::to_string(&result)?);
+            } else {
+                print!("{}", broadcast::package_report(&result));
+            }
+            if !result.accepted(txs.len()) {
+                return Err(anyhow!("the package wasn't accepted"));
+            }
+        }
+        Commands::GetTip { wait, interval } => {
+            let mut tip = tip::tip_info(client).await?;
+            if wait {
+                let interval = Duration::from_secs(interval.max(1));
+                tip::wait_for_change(client, &tip.hash, interval).await?;
+                tip = tip::tip_info(client).await?;
+            }
+            if json {
+                println!("{}", serde_json::to_string(&tip)?);
+            } else {
+                print!("{}", tip::report(&tip));
+            }
         }
         Commands::GetBlockHash { height } => {
             let hash = client.get_block_hash(height).await?;
@@ -133,17 +1314,637 @@ async fn main() -> anyhow::Result<()> {
             let fees = client.get_fee_estimates().await?;
             println!("{:#?}", fees);
         }
-        Commands::GetScriptHashTxs { address, last_seen } => {
-            let addr = address.clone().assume_checked();
-            let txs = client.scripthash_txs(&addr.script_pubkey(), last_seen).await?;
+        Commands::BumpFee { txid, target } => {
+            let bump = rbf::bump(client, &txid, target).await?;
+            if json {
+                println!("{}", serde_json::to_string(&bump)?);
+            } else {
+                print!("{}", rbf::report(&bump));
+            }
+        }
+        Commands::Cpfp {
+            txid,
+            target,
+            feerate,
+            child_vsize,
+        } => {
+            let cpfp = cpfp::cpfp(client, &txid, target, feerate, child_vsize).await?;
+            if json {
+                println!("{}", serde_json::to_string(&cpfp)?);
+            } else {
+                print!("{}", cpfp::report(&cpfp));
+            }
+        }
+        Commands::WhenConfirm { feerate, vsize } => {
+            let position = mempool::position(client, feerate, vsize).await?;
+            if json {
+                println!("{}", serde_json::to_string(&position)?);
+            } else {
+                print!("{}", mempool::report(&position));
+            }
+        }
+        Commands::GetReplacements { full_rbf } => {
+            let trees = replacements::recent(client, full_rbf).await?;
+            if json {
+                println!("{}", serde_json::to_string(&trees)?);
+            } else {
+                print!("{}", replacements::report(&trees));
+            }
+        }
+        Commands::GetTxRbf { txid } => {
+            let rbf = replacements::tx_rbf(client, &txid).await?;
+            if json {
+                println!("{}", serde_json::to_string(&rbf)?);
+            } else {
+                print!("{}", replacements::report_tx(&txid, &rbf));
+            }
+        }
+        Commands::GetScriptHashTxs {
+            target,
+            last_seen,
+            script,
+            scripthash,
+        } => {
+            let hash = address::scripthash(&target, script, scripthash)?;
+            let txs = client.scripthash_txs_by_hash(&hash, last_seen).await?;
             for tx in txs {
                 println!("{:#?}", tx.txid);
             }
         }
-        Commands::GetBlocks { height } => {
-            let blocks = client.get_blocks(height).await?;
-            println!("{:#?}", blocks);
+        Commands::ValidateAddress { address } => {
+            let info = address::inspect(&address);
+            if json {
+                println!("{}", serde_json::to_string(&info)?);
+            } else {
+                print!("{}", address::report(&info));
+            }
+        }
+        Commands::SearchAddress { prefix } => {
+            if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(error::invalid_input(format!(
+                    "`{}` isn't the start of an address",
+                    prefix
+                )));
+            }
+            let addresses = client
+                .search_address(&prefix)
+                .await?
+                .ok_or_else(|| capabilities::unsupported(Feature::AddressSearch))?;
+            if json {
+                println!("{}", serde_json::to_string(&addresses)?);
+            } else if addresses.is_empty() {
+                println!("no known address starts with {}", prefix);
+            } else {
+                for address in addresses {
+                    println!("{}", address);
+                }
+            }
         }
+        Commands::ExportHistory {
+            address,
+            csv,
+            fiat,
+            jobs,
+        } => {
+            let address = address.assume_checked();
+            let fiat = fiat.map(|f| f.to_uppercase());
+            if fiat.is_some() {
+                // Looking up the prices is what takes long.
+                progress.interruptible();
+            }
+            let (entries, unconfirmed) =
+                history::history(client, &address, fiat.as_deref(), jobs, &progress).await?;
+            history::write_csv(&csv, &entries, fiat.as_deref())?;
+            if unconfirmed > 0 {
+                eprintln!("note: left out {} unconfirmed transaction(s)", unconfirmed);
+            }
+            if json {
+                println!("{}", serde_json::json!({ "transactions": entries.len() }));
+            } else {
+                println!("Wrote {} transactions to {}", entries.len(), csv.display());
+            }
+            if Progress::is_interrupted() {
+                return Err(error::interrupted(format!("{} transactions", entries.len())));
+            }
+        }
+        Commands::Balances { file, jobs } => {
+            let addresses: Vec<String> =
+                batch::read_lines(&file)?.into_iter().map(|(_, line)| line).collect();
+            let balances = batch::get_balances(client, &addresses, jobs, &progress).await;
+            let mut total = batch::Balance::default();
+            for balance in balances.iter().flatten() {
+                total.confirmed += balance.confirmed;
+                total.unconfirmed += balance.unconfirmed;
+            }
+            let failed = balances.iter().filter(|b| b.is_err()).count();
+
+            if json {
+                let rows: Vec<_> = addresses
+                    .iter()
+                    .zip(&balances)
+                    .map(|(address, balance)| match balance {
+                        Ok(b) => serde_json::json!({
+                            "address": address,
+                            "confirmed": b.confirmed,
+                            "unconfirmed": b.unconfirmed,
+                        }),
+                        Err(e) => {
+                            serde_json::json!({ "address": address, "error": format!("{:#}", e) })
+                        }
+                    })
+                    .collect();
+                println!("{}", serde_json::json!({ "addresses": rows, "total": total }));
+            } else {
+                let width = addresses.iter().map(String::len).max().unwrap_or(0).max(7);
+                println!("{:<width$}  {:>16}  {:>16}", "address", "confirmed", "unconfirmed");
+                for (address, balance) in addresses.iter().zip(&balances) {
+                    match balance {
+                        Ok(b) => println!(
+                            "{:<width$}  {:>16}  {:>+16}",
+                            address, b.confirmed, b.unconfirmed
+                        ),
+                        Err(e) => println!("{:<width$}  error: {:#}", address, e),
+                    }
+                }
+                println!(
+                    "{:<width$}  {:>16}  {:>+16}",
+                    "total", total.confirmed, total.unconfirmed
+                );
+            }
+            if failed > 0 {
+                return Err(anyhow!("failed to fetch {} of {} addresses", failed, addresses.len()));
+            }
+        }
+        Commands::GetBlocks {
+            from: Some(from),
+            to,
+            ..
+        } => {
+            let to = match to {
+                Some(to) => to,
+                None => client.get_height().await?,
+            };
+            if from > to {
+                return Err(error::invalid_input("--from must not be above --to"));
+            }
+            let mut blocks = std::pin::pin!(client.get_block_range(from, to));
+            while let Some(block) = blocks.try_next().await? {
+                if json {
+                    println!("{}", block);
+                } else {
+                    let block: esplora_client::BlockInfo = serde_json::from_value(block)?;
+                    println!("{:#?}", Dump(&block));
+                }
+            }
+        }
+        Commands::GetBlocks { height, .. } => {
+            let blocks = client.get_block_infos(height).await?;
+            println!("{:#?}", Dump(blocks.as_slice()));
+        }
+        Commands::Checkpoints {
+            every,
+            from,
+            to,
+            rust,
+            jobs,
+        } => {
+            let checkpoints =
+                checkpoints::checkpoints(client, from, to, every, jobs, &progress).await?;
+            if let Some(name) = rust {
+                print!("{}", checkpoints::rust(&checkpoints, &name));
+            } else if json {
+                println!("{}", serde_json::to_string(&checkpoints)?);
+            } else {
+                print!("{}", checkpoints::report(&checkpoints));
+            }
+        }
+        Commands::Show { page } => {
+            let page = match page {
+                ShowCommands::Block { block } => show::block_page(client, block).await?,
+                ShowCommands::Tx { txid } => show::tx_page(client, txid).await?,
+            };
+            show::page(&page)?;
+        }
+        Commands::Ln { command } => {
+            if !matches!(command, LnCommands::Stats) {
+                // A 404 of the other endpoints could be a node or channel not found.
+                capabilities::require(client, Feature::Lightning).await?;
+            }
+            match command {
+                LnCommands::Stats => {
+                    let stats = lightning::get_stats(client)
+                        .await?
+                        .ok_or_else(|| capabilities::unsupported(Feature::Lightning))?;
+                    println!("{:#?}", stats);
+                }
+                LnCommands::Node { node } => {
+                    let pubkey = match node.parse() {
+                        Ok(pubkey) => pubkey,
+                        Err(_) => {
+                            let res = lightning::search(client, &node).await?;
+                            match res.nodes.as_slice() {
+                                [found] => found.public_key,
+                                _ => {
+                                    println!("{:#?}", res.nodes);
+                                    return Ok(());
+                                }
+                            }
+                        }
+                    };
+                    let node = lightning::get_node(client, &pubkey)
+                        .await?
+                        .ok_or_else(|| error::not_found(format!("node {}", pubkey)))?;
+                    println!("{:#?}", Dump(&node));
+                }
+                LnCommands::Channel { id } => {
+                    let channel = lightning::get_channel(client, id)
+                        .await?
+                        .ok_or_else(|| error::not_found(format!("channel {}", id)))?;
+                    println!("{:#?}", channel);
+                }
+            }
+        }
+        Commands::FeeLog { file } => {
+            let path = file.map_or_else(fees::default_log_path, Ok)?;
+            let snapshot = fees::record(client, &path).await?;
+            println!("{:#?}", Dump(&snapshot));
+        }
+        Commands::FeeDiff { from, to, file } => {
+            let path = file.map_or_else(fees::default_log_path, Ok)?;
+            let snapshots = fees::read_log(&path)?;
+            print!("{}", fees::diff(&snapshots, from.0, to.0)?);
+        }
+        Commands::FeeHistory {
+            blocks,
+            local,
+            jobs,
+            csv,
+        } => {
+            let history = feehistory::fee_history(client, blocks, local, jobs, &progress).await?;
+            if let Some(csv) = &csv {
+                feehistory::write_csv(csv, &history)?;
+            }
+            if json {
+                println!("{}", serde_json::to_string(&history)?);
+            } else {
+                print!("{}", feehistory::report(&history));
+            }
+        }
+        Commands::DustReport {
+            wallet,
+            change,
+            target,
+            low_target,
+            gap_limit,
+            parallel,
+        } => {
+            let wallet = dust::Wallet::parse(&wallet, change.as_deref())?;
+            let report = dust::report_for(
+                client, &wallet, target, low_target, gap_limit, parallel, &progress,
+            )
+            .await?;
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                print!("{}", dust::report(&report));
+            }
+        }
+        Commands::GetAsset { asset_id } => {
+            let asset = liquid::get_asset(client, &asset_id)
+                .await?
+                .ok_or_else(|| error::not_found(format!("asset {}", asset_id)))?;
+            println!("{:#}", asset);
+        }
+        Commands::Scan {
+            descriptor,
+            change,
+            gap_limit,
+            parallel,
+        } => {
+            let keychains = scan::keychains(&descriptor, change.as_deref())?;
+            progress.interruptible();
+            let result = scan::scan(client, &keychains, gap_limit, parallel, &progress).await?;
+            let summaries = scan::rollup(&result);
+            if json {
+                println!("{}", scan::report_json(&result, &summaries));
+            } else {
+                print!("{}", scan::report(&result, &summaries));
+            }
+            if Progress::is_interrupted() {
+                return Err(error::interrupted(format!("{} scripts", result.spks.len())));
+            }
+        }
+        Commands::BdkSync {
+            descriptor,
+            change,
+            stop_gap,
+            sync,
+            parallel,
+        } => {
+            let keychains = scan::keychains(&descriptor, change.as_deref())?;
+            let update = match sync {
+                Some(count) => bdk::sync(client, &keychains, count, parallel).await?,
+                None => bdk::full_scan(client, &keychains, stop_gap, parallel).await?,
+            };
+            if json {
+                println!("{}", update);
+            } else {
+                println!("{}", serde_json::to_string_pretty(&update)?);
+            }
+        }
+        Commands::WaitForBlock {
+            height,
+            timeout,
+            interval,
+        } => {
+            let interval = Duration::from_secs(interval.max(1));
+            let timeout = timeout.map(Duration::from_secs);
+            let (height, hash) =
+                tip::wait_for_block(client, height, interval, timeout, &progress).await?;
+            if json {
+                println!("{}", serde_json::json!({ "height": height, "hash": hash }));
+            } else {
+                println!("{}", hash);
+            }
+        }
+        Commands::MempoolDelta { interval, jobs } => {
+            mempool::stream(client, Duration::from_secs(interval.max(1)), jobs).await?;
+        }
+        Commands::WatchAddress { address, interval } => {
+            let address = address.assume_checked();
+            let interval = Duration::from_secs(interval.max(1));
+            watch::watch(client, network, &address, interval, json).await?;
+        }
+        Commands::WatchUtxo {
+            outpoint,
+            exec,
+            interval,
+        } => {
+            let interval = Duration::from_secs(interval.max(1));
+            let spend = watch::wait_for_spend(client, &outpoint, interval).await?;
+            if json {
+                println!("{}", serde_json::to_string(&spend)?);
+            } else {
+                print!("{}", watch::report(&spend));
+            }
+            if let Some(command) = exec {
+                watch::exec(&command, &spend).await?;
+            }
+        }
+        Commands::Bench {
+            iterations,
+            compare,
+        } => {
+            if iterations == 0 {
+                return Err(error::invalid_input("--iterations must be at least 1"));
+            }
+            let requests = bench::Requests::resolve(client).await?;
+            let mut backends = vec![(client.clone(), network.clone())];
+            if let Some(url) = compare {
+                backends.push((session.client_for(&url)?, url));
+            }
+            progress.set_total((iterations * requests.count() * backends.len()) as u64);
+            let mut benches = vec![];
+            for (client, url) in &backends {
+                benches.push(bench::bench(client, url, &requests, iterations, &progress).await);
+            }
+            progress.finish();
+            if json {
+                println!("{}", serde_json::to_string(&benches)?);
+            } else {
+                print!("{}", bench::report(&benches));
+            }
+        }
+        Commands::Capabilities => {
+            let capabilities = capabilities::probe(client).await;
+            if json {
+                println!("{}", serde_json::to_string(&capabilities)?);
+            } else {
+                print!("{}", capabilities::report(&capabilities));
+            }
+        }
+        Commands::Health { reference } => {
+            let reference = match reference {
+                Some(url) => Some((session.client_for(&url)?, url)),
+                None => None,
+            };
+            let reference = reference.as_ref().map(|(client, url)| (url.as_str(), client));
+            match health::check(client, network, reference).await {
+                Ok(health) if json => println!("{}", serde_json::to_string(&health)?),
+                Ok(health) => print!("{}", health::report(&health)),
+                Err(e) => {
+                    if json {
+                        let error = format!("{:#}", e);
+                        println!("{}", serde_json::json!({ "url": network, "error": error }));
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Commands::ServeMetrics {
+            listen,
+            interval,
+            watch_addresses,
+            watch_txs,
+        } => {
+            let watch = metrics::Watch {
+                addresses: watch_addresses.into_iter().map(Address::assume_checked).collect(),
+                txids: watch_txs,
+            };
+            let interval = Duration::from_secs(interval.max(1));
+            metrics::serve(client, &listen, interval, &watch).await?;
+        }
+        Commands::ServeRpc { listen } => rpc::serve(client, &listen).await?,
+        Commands::Track { command } => {
+            let mut db = tracker::open()?;
+            match command {
+                TrackCommands::Add {
+                    descriptor,
+                    change,
+                    wallet,
+                } => {
+                    // Reject descriptors that can't be synced before storing them.
+                    scan::keychains(&descriptor, change.as_deref())?;
+                    db.add_wallet(&tracker::Wallet {
+                        name: wallet.clone(),
+                        descriptor,
+                        change,
+                    })?;
+                    println!("Tracking wallet `{}`, run `track sync` to fetch its history", wallet);
+                }
+                TrackCommands::Sync {
+                    wallet,
+                    gap_limit,
+                    parallel,
+                } => {
+                    let wallet = tracked_wallet(&db, &wallet)?;
+                    let summary =
+                        tracker::sync(client, &mut db, &wallet, gap_limit, parallel, &progress)
+                            .await?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::json!({
+                                "scripts": summary.scripts,
+                                "new_txs": summary.new_txs,
+                                "confirmed": summary.confirmed,
+                                "dropped": summary.dropped,
+                            })
+                        );
+                    } else {
+                        println!(
+                            "Synced {} scripts: {} new, {} newly confirmed, {} dropped transaction(s)",
+                            summary.scripts, summary.new_txs, summary.confirmed, summary.dropped
+                        );
+                    }
+                }
+                TrackCommands::Balance { wallet } => {
+                    let wallet = tracked_wallet(&db, &wallet)?;
+                    let (confirmed, unconfirmed) = db.balance(&wallet.name)?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::json!({ "confirmed": confirmed, "unconfirmed": unconfirmed })
+                        );
+                    } else {
+                        println!("confirmed balance: {} sat", confirmed);
+                        println!("unconfirmed balance: {:+} sat", unconfirmed);
+                    }
+                }
+                TrackCommands::Txs { wallet } => {
+                    let wallet = tracked_wallet(&db, &wallet)?;
+                    let txs = db.txs(&wallet.name)?;
+                    if json {
+                        let txs: Vec<_> = txs
+                            .iter()
+                            .map(|tx| {
+                                serde_json::json!({
+                                    "txid": tx.txid,
+                                    "height": tx.height,
+                                    "time": tx.time,
+                                    "net": tx.net,
+                                })
+                            })
+                            .collect();
+                        println!("{}", serde_json::Value::Array(txs));
+                    } else {
+                        for tx in &txs {
+                            let height = tx.height.map_or("mempool".to_string(), |h| h.to_string());
+                            println!("{:>8}  {}  {:+} sat", height, tx.txid, tx.net);
+                        }
+                    }
+                }
+            }
+        }
+        Commands::CheckTip => {
+            let mut state: TipState = state::load(TipState::FILE)?;
+            let previous = state.tips.get(network).copied();
+            let current = tip::current_tip(client).await?;
+            let change = tip::compare(client, previous, current).await?;
+
+            println!("tip: {} {}", current.height, current.hash);
+            if let Some(previous) = previous {
+                println!("previous: {} {}", previous.height, previous.hash);
+            }
+            match change {
+                TipChange::First => println!("no previous tip recorded"),
+                TipChange::Extended { blocks } => println!("no reorg, {} new block(s)", blocks),
+                TipChange::Reorg { depth, fork_point } => {
+                    println!("reorg: depth {}", depth);
+                    println!("fork point: {} {}", fork_point.height, fork_point.hash);
+                }
+            }
+
+            state.tips.insert(network.clone(), current);
+            state::save(TipState::FILE, &state)?;
+        }
+        Commands::Cache { command } => {
+            let dir = cache_dir
+                .as_ref()
+                .ok_or(anyhow!("no cache directory, set --cache-dir"))?;
+            match command {
+                CacheCommands::Stats => {
+                    let (entries, size) = cache::stats(dir)?;
+                    println!("{} entries, {} bytes", entries, size);
+                }
+                CacheCommands::Clear => {
+                    let entries = cache::clear(dir)?;
+                    println!("Removed {} entries", entries);
+                }
+            }
+        }
+        Commands::State { command } => match command {
+            StateCommands::Export { bundle } => {
+                let contents = state::export(&bundle, cache_dir.as_deref())?;
+                println!("Exported {} to {}", state::describe(&contents), bundle.display());
+                if cache_dir.is_none() {
+                    println!("not bundled: the response cache, pass --cache-dir to include it");
+                }
+                println!("{}", state::NOT_BUNDLED);
+            }
+            StateCommands::Import { bundle, force } => {
+                let restored = state::import(&bundle, force, cache_dir.as_deref())?;
+                println!("Imported {} from {}", state::describe(&restored), bundle.display());
+                if restored.cache_skipped > 0 {
+                    println!(
+                        "skipped {} cache entries, pass --cache-dir to restore them",
+                        restored.cache_skipped
+                    );
+                }
+            }
+        },
+        Commands::SyncHeaders {
+            from,
+            to,
+            out,
+            jobs,
+        } => {
+            let to = match to {
+                Some(to) => to,
+                None => client.get_height().await?,
+            };
+            progress.interruptible();
+            let (height, hash) = headers::sync(client, from, to, &out, jobs, &progress).await?;
+            if json {
+                println!("{}", serde_json::json!({ "height": height, "hash": hash }));
+            } else {
+                println!("Wrote {} headers to {}", height - from + 1, out.display());
+            }
+            if Progress::is_interrupted() && height < to {
+                return Err(error::interrupted(format!("height {}", height)));
+            }
+        }
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Commands::Mangen { out_dir } => match out_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(&dir)?;
+                clap_mangen::generate_to(Cli::command(), &dir)?;
+                println!("Wrote man pages to {}", dir.display());
+            }
+            None => clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?,
+        },
+        Commands::Endpoints { command } => match command {
+            EndpointsCommands::Discover { yes } => {
+                let urls = endpoints::load_list()?;
+                let probes =
+                    endpoints::discover(&urls, |url| session.builder_for(url), &progress).await;
+                if json {
+                    println!("{}", endpoints::report_json(&probes));
+                } else {
+                    print!("{}", endpoints::report(&probes));
+                }
+                endpoints::offer_profiles(&probes, yes, json)?;
+            }
+            EndpointsCommands::Update { source } => {
+                let list = endpoints::update_list(client.http(), &source).await?;
+                println!("{:#?}", list);
+            }
+        },
+        Commands::Repl => return Err(anyhow!("already in the repl")),
     }
 
     Ok(())
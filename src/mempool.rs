@@ -0,0 +1,204 @@
+//! The backend's mempool: the blocks projected from it and streaming changes to it.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use bitcoin::Txid;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::fees;
+
+/// Maximum virtual size of a block, in vbytes.
+const MAX_BLOCK_VSIZE: f64 = 1_000_000.0;
+
+/// Expected time between blocks, in minutes.
+const BLOCK_INTERVAL_MINUTES: usize = 10;
+
+/// A block template projected from the mempool, as served by the mempool.space
+/// `/v1/fees/mempool-blocks` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectedBlock {
+    /// Virtual size in vbytes. The last block holds the rest of the mempool and can exceed the
+    /// block size limit.
+    #[serde(rename = "blockVSize")]
+    pub vsize: f64,
+    /// Feerate range in sat/vB, lowest first.
+    #[serde(rename = "feeRange")]
+    pub fee_range: Vec<f64>,
+}
+
+/// Where a transaction paying a given feerate would land in the mempool.
+#[derive(Debug, Serialize)]
+pub struct Position {
+    /// Feerate in sat/vB.
+    pub feerate: f64,
+    /// Virtual size of the transaction in vbytes.
+    pub vsize: u64,
+    /// Fee it pays at that feerate, in satoshis.
+    pub fee: u64,
+    /// Virtual size of the transactions paying at least as much, in vbytes.
+    pub ahead: u64,
+    /// Number of blocks until it would be mined.
+    pub blocks: usize,
+    /// Roughly how many minutes until it would be mined.
+    pub minutes: usize,
+    /// What the position was judged from, `projected-blocks` or `fee-histogram`.
+    pub source: &'static str,
+}
+
+/// Estimate when a transaction of `vsize` vbytes paying `feerate` would be mined.
+///
+/// The backend's projected blocks are used when it provides them. Otherwise the transactions
+/// ahead in the fee histogram are packed into full blocks, which ignores how templates are
+/// filled around package limits.
+pub async fn position(client: &Client, feerate: f64, vsize: u64) -> anyhow::Result<Position> {
+    let stats = client.get_mempool_stats().await?;
+    let ahead: u64 = stats
+        .fee_histogram
+        .iter()
+        .filter(|(rate, _)| *rate >= feerate)
+        .map(|(_, vsize)| *vsize as u64)
+        .sum();
+    let projected = projected_blocks(client)
+        .await?
+        .and_then(|blocks| blocks_until(&blocks, feerate));
+    let (blocks, source) = match projected {
+        Some(blocks) => (blocks, "projected-blocks"),
+        None => (
+            ((ahead + vsize) as f64 / MAX_BLOCK_VSIZE).ceil().max(1.0) as usize,
+            "fee-histogram",
+        ),
+    };
+    Ok(Position {
+        feerate,
+        vsize,
+        fee: (feerate * vsize as f64).ceil() as u64,
+        ahead,
+        blocks,
+        minutes: blocks * BLOCK_INTERVAL_MINUTES,
+        source,
+    })
+}
+
+/// Render `position` for the terminal.
+pub fn report(position: &Position) -> String {
+    format!(
+        "feerate: {:.2} sat/vB ({} sat for {} vB)\nahead in mempool: {} vB\nconfirmation: {}\n",
+        position.feerate,
+        position.fee,
+        position.vsize,
+        position.ahead,
+        describe_blocks(Some(position.blocks)),
+    )
+}
+
+/// Describe a number of blocks to wait and the time it takes.
+pub fn describe_blocks(blocks: Option<usize>) -> String {
+    match blocks {
+        Some(1) => format!("next block (~{} min)", BLOCK_INTERVAL_MINUTES),
+        Some(n) => format!("{} blocks (~{} min)", n, n * BLOCK_INTERVAL_MINUTES),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Fetch the blocks projected from the mempool, `None` if the backend doesn't provide them.
+pub async fn projected_blocks(client: &Client) -> anyhow::Result<Option<Vec<ProjectedBlock>>> {
+    Ok(client.get_opt_json("/v1/fees/mempool-blocks").await?)
+}
+
+/// Number of blocks until a transaction paying `feerate` would be mined, judging by the
+/// projected `blocks`. `None` if it pays less than anything projected.
+///
+/// A position in the last block, which holds the rest of the mempool, is spread over as many
+/// blocks as it fills, assuming its feerates are evenly distributed over its range.
+pub fn blocks_until(blocks: &[ProjectedBlock], feerate: f64) -> Option<usize> {
+    let index = blocks
+        .iter()
+        .position(|b| b.fee_range.first().is_some_and(|&min| feerate >= min))?;
+    let block = &blocks[index];
+    if index + 1 < blocks.len() || block.vsize <= MAX_BLOCK_VSIZE {
+        return Some(index + 1);
+    }
+    let (min, max) = (block.fee_range[0], *block.fee_range.last().unwrap_or(&0.0));
+    let above = if max > min {
+        ((max - feerate) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Some(index + 1 + (above * block.vsize / MAX_BLOCK_VSIZE) as usize)
+}
+
+/// A transaction entering or leaving the mempool.
+#[derive(Debug, Serialize)]
+pub struct Delta {
+    /// UNIX timestamp of the snapshot the change was seen in.
+    pub time: u64,
+    /// `added` or `removed`.
+    pub event: &'static str,
+    /// The transaction id.
+    pub txid: Txid,
+    /// Feerate in sat/vB, `None` for transactions that were already in the mempool when the
+    /// stream started, or that left it before they could be looked up.
+    pub feerate: Option<f64>,
+}
+
+/// Snapshot the mempool txids every `interval` and print each transaction that was added or
+/// removed since the previous snapshot as a line of JSON, looking up the feerates of added
+/// ones with up to `jobs` requests in flight. Runs until interrupted.
+///
+/// The first snapshot only serves as the baseline. A failed snapshot is reported on stderr
+/// and retried on the next tick.
+pub async fn stream(client: &Client, interval: Duration, jobs: usize) -> anyhow::Result<()> {
+    let mut txids: HashSet<Txid> = client.get_mempool_txids().await?.into_iter().collect();
+    let mut feerates: HashMap<Txid, f64> = HashMap::new();
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let time = fees::now();
+        let snapshot: HashSet<Txid> = match client.get_mempool_txids().await {
+            Ok(snapshot) => snapshot.into_iter().collect(),
+            Err(e) => {
+                eprintln!("poll failed: {}", e);
+                continue;
+            }
+        };
+
+        let added: Vec<Txid> = snapshot.difference(&txids).copied().collect();
+        let looked_up: Vec<(Txid, Option<f64>)> = futures::stream::iter(added)
+            .map(|txid| async move {
+                let tx = client.get_tx_info(&txid).await.ok().flatten();
+                (txid, tx.map(|tx| tx.fee as f64 / tx.weight.div_ceil(4) as f64))
+            })
+            .buffer_unordered(jobs.max(1))
+            .collect()
+            .await;
+        for (txid, feerate) in looked_up {
+            if let Some(feerate) = feerate {
+                feerates.insert(txid, feerate);
+            }
+            emit(&Delta {
+                time,
+                event: "added",
+                txid,
+                feerate,
+            })?;
+        }
+
+        for &txid in txids.difference(&snapshot) {
+            emit(&Delta {
+                time,
+                event: "removed",
+                txid,
+                feerate: feerates.remove(&txid),
+            })?;
+        }
+        txids = snapshot;
+    }
+}
+
+fn emit(delta: &Delta) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string(delta)?);
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! Prometheus exporter polling the backend in the background.
+//!
+//! Serves the gauges of the last poll in the Prometheus text format on every HTTP request,
+//! whatever its path, so the listen address can be scraped directly.
+
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bitcoin::{Address, Txid};
+use tokio::net::TcpListener;
+
+use crate::client::Client;
+use crate::fees;
+use crate::http::{self, Response};
+
+/// What to poll besides the chain and mempool.
+#[derive(Debug, Clone, Default)]
+pub struct Watch {
+    /// Addresses to export the balance of.
+    pub addresses: Vec<Address>,
+    /// Transactions to export the confirmations of.
+    pub txids: Vec<Txid>,
+}
+
+/// Poll the backend every `interval` and serve the resulting metrics on `listen`. Runs until
+/// interrupted.
+pub async fn serve(
+    client: &Client,
+    listen: &str,
+    interval: Duration,
+    watch: &Watch,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+    eprintln!("serving metrics on http://{}/metrics", listener.local_addr()?);
+
+    let metrics = Arc::new(Mutex::new(String::new()));
+    let server = {
+        let metrics = Arc::clone(&metrics);
+        tokio::spawn(http::serve(listener, move |_| {
+            let body = metrics.lock().map(|m| m.clone()).unwrap_or_default();
+            async move {
+                Response {
+                    status: 200,
+                    content_type: "text/plain; version=0.0.4",
+                    body,
+                }
+            }
+        }))
+    };
+
+    let mut errors = 0;
+    loop {
+        let rendered = match poll(client, watch).await {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                errors += 1;
+                eprintln!("poll failed: {:#}", e);
+                String::new()
+            }
+        };
+        let mut out = rendered;
+        describe(
+            &mut out,
+            "esplora_poll_errors_total",
+            "Number of failed polls.",
+            "counter",
+        );
+        let _ = writeln!(out, "esplora_poll_errors_total {}", errors);
+        describe(
+            &mut out,
+            "esplora_last_poll_timestamp_seconds",
+            "Time of the last poll.",
+            "gauge",
+        );
+        let _ = writeln!(out, "esplora_last_poll_timestamp_seconds {}", fees::now());
+        if let Ok(mut metrics) = metrics.lock() {
+            *metrics = out;
+        }
+
+        if server.is_finished() {
+            return Err(anyhow::anyhow!("metrics server stopped"));
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Fetch every metric and render them in the Prometheus text format.
+async fn poll(client: &Client, watch: &Watch) -> anyhow::Result<String> {
+    let mut out = String::new();
+
+    let height = client.get_height().await?;
+    describe(
+        &mut out,
+        "esplora_tip_height",
+        "Height of the best chain tip.",
+        "gauge",
+    );
+    let _ = writeln!(out, "esplora_tip_height {}", height);
+
+    let mempool = client.get_mempool_stats().await?;
+    describe(
+        &mut out,
+        "esplora_mempool_tx_count",
+        "Number of mempool transactions.",
+        "gauge",
+    );
+    let _ = writeln!(out, "esplora_mempool_tx_count {}", mempool.count);
+    describe(
+        &mut out,
+        "esplora_mempool_vsize",
+        "Total mempool size in vbytes.",
+        "gauge",
+    );
+    let _ = writeln!(out, "esplora_mempool_vsize {}", mempool.vsize);
+    describe(
+        &mut out,
+        "esplora_mempool_total_fee_sat",
+        "Total mempool fees in sat.",
+        "gauge",
+    );
+    let _ = writeln!(out, "esplora_mempool_total_fee_sat {}", mempool.total_fee);
+
+    let mut estimates: Vec<(u16, f64)> = client.get_fee_estimates().await?.into_iter().collect();
+    estimates.sort_by_key(|(target, _)| *target);
+    describe(
+        &mut out,
+        "esplora_fee_estimate_sat_per_vb",
+        "Fee estimate by target.",
+        "gauge",
+    );
+    for (target, feerate) in estimates {
+        let _ = writeln!(
+            out,
+            "esplora_fee_estimate_sat_per_vb{{target=\"{}\"}} {}",
+            target, feerate
+        );
+    }
+
+    if !watch.addresses.is_empty() {
+        describe(
+            &mut out,
+            "esplora_address_balance_sat",
+            "Balance of an address.",
+            "gauge",
+        );
+    }
+    for address in &watch.addresses {
+        let stats = client.get_address_stats(address).await?;
+        let confirmed =
+            stats.chain_stats.funded_txo_sum as i64 - stats.chain_stats.spent_txo_sum as i64;
+        let pending =
+            stats.mempool_stats.funded_txo_sum as i64 - stats.mempool_stats.spent_txo_sum as i64;
+        for (state, balance) in [("confirmed", confirmed), ("mempool", pending)] {
+            let _ = writeln!(
+                out,
+                "esplora_address_balance_sat{{address=\"{}\",state=\"{}\"}} {}",
+                address, state, balance
+            );
+        }
+    }
+
+    if !watch.txids.is_empty() {
+        describe(
+            &mut out,
+            "esplora_tx_confirmations",
+            "Confirmations of a transaction, 0 if unconfirmed and -1 if unknown.",
+            "gauge",
+        );
+    }
+    for txid in &watch.txids {
+        let confirmations = match client.get_tx_info(txid).await? {
+            None => -1,
+            Some(tx) => match tx.status.block_height.filter(|_| tx.status.confirmed) {
+                Some(confirmed_at) => i64::from(height) - i64::from(confirmed_at) + 1,
+                None => 0,
+            },
+        };
+        let _ = writeln!(out, "esplora_tx_confirmations{{txid=\"{}\"}} {}", txid, confirmations);
+    }
+
+    Ok(out)
+}
+
+/// Write the help and type lines of a metric.
+fn describe(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
@@ -0,0 +1,66 @@
+//! Failover across several backends serving the same network.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use esplora_client::Error;
+
+/// An ordered list of backend urls, the first being the primary.
+///
+/// Requests go to the backend that last served one, falling through to the others in order
+/// when it is unreachable or failing.
+#[derive(Debug)]
+pub struct Backends {
+    urls: Vec<String>,
+    /// Index of the backend that served the last request.
+    active: AtomicUsize,
+}
+
+impl Backends {
+    /// Create the list from the primary url and its fallbacks.
+    pub fn new(primary: String, fallbacks: Vec<String>) -> Self {
+        let mut urls = vec![primary];
+        for url in fallbacks {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        Self {
+            urls,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// The primary backend.
+    pub fn primary(&self) -> &str {
+        &self.urls[0]
+    }
+
+    /// The backends in the order to try them, starting with the active one.
+    pub fn ordered(&self) -> impl Iterator<Item = (usize, &str)> {
+        let active = self.active.load(Ordering::Relaxed);
+        (0..self.urls.len())
+            .map(move |i| (active + i) % self.urls.len())
+            .map(|i| (i, self.urls[i].as_str()))
+    }
+
+    /// Record that backend `index` served a request, reporting on stderr when this switches
+    /// the active backend.
+    pub fn served(&self, index: usize) {
+        let previous = self.active.swap(index, Ordering::Relaxed);
+        if previous != index {
+            eprintln!("note: failed over to {}", self.urls[index]);
+        }
+    }
+}
+
+/// Whether `error` means the backend itself is unusable, so that another one should be tried.
+///
+/// Connection failures, timeouts and server errors qualify, while client errors such as a 404
+/// would be answered the same by any backend.
+pub fn is_backend_failure(error: &Error) -> bool {
+    match error {
+        Error::Reqwest(e) => e.is_connect() || e.is_timeout(),
+        Error::HttpResponse { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
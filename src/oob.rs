@@ -0,0 +1,218 @@
+//! Detection of transactions confirmed out of band.
+//!
+//! A transaction confirmed at a feerate far below what the rest of its block paid was most
+//! likely submitted to the miner directly, through an accelerator or as a miner's own
+//! transaction. The block's feerate distribution is taken from the mempool.space
+//! `/v1/block/:hash` extras, other backends don't provide it.
+
+use std::collections::{HashMap, HashSet};
+
+use bitcoin::{BlockHash, Txid};
+use esplora_client::{Error, OutputStatus, Tx};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::client::Client;
+
+/// Fraction of the block floor below which a transaction is flagged.
+const FLOOR_RATIO: f64 = 0.5;
+
+/// Number of transactions per page of a block's transactions.
+const PAGE_SIZE: usize = 25;
+
+/// Feerate distribution of a block, in sat/vB.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockFees {
+    /// The 10th percentile feerate, taken as the floor the block's transactions paid.
+    pub floor: f64,
+    /// The median feerate.
+    pub median: f64,
+}
+
+#[derive(Deserialize)]
+struct BlockSummary {
+    extras: Option<Extras>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Extras {
+    median_fee: f64,
+    /// Feerate percentiles: min, 10th, 25th, 50th, 75th, 90th, max.
+    fee_range: Vec<f64>,
+}
+
+/// The assessment of a transaction's feerate against its block.
+#[derive(Debug, Clone, Copy)]
+pub struct Assessment {
+    /// Feerate of the transaction, or of it together with children confirmed in the same
+    /// block if they paid for it at a higher feerate.
+    pub effective: f64,
+    /// Number of children counted into the effective feerate, 0 if they didn't raise it.
+    pub children: usize,
+    /// The block's feerate distribution.
+    pub block: BlockFees,
+}
+
+impl Assessment {
+    /// Whether the transaction paid far below the block floor.
+    pub fn is_out_of_band(&self) -> bool {
+        self.effective < self.block.floor * FLOOR_RATIO
+    }
+
+    /// How far below the block floor the transaction paid, in sat/vB.
+    pub fn gap(&self) -> f64 {
+        self.block.floor - self.effective
+    }
+}
+
+/// Fetch the feerate distribution of the block `hash`, `None` if the backend doesn't
+/// provide it.
+pub async fn block_fees(client: &Client, hash: &BlockHash) -> Result<Option<BlockFees>, Error> {
+    let summary: Option<BlockSummary> = client.get_opt_json(&format!("/v1/block/{}", hash)).await?;
+    let Some(extras) = summary.and_then(|s| s.extras) else {
+        return Ok(None);
+    };
+    Ok(extras.fee_range.get(1).map(|&floor| BlockFees {
+        floor,
+        median: extras.median_fee,
+    }))
+}
+
+/// Assess the confirmed transaction `tx` against its block, `None` if it is unconfirmed, a
+/// coinbase, or the backend has no feerate distribution for the block.
+pub async fn assess(
+    client: &Client,
+    tx: &Tx,
+    outspends: &[OutputStatus],
+) -> Result<Option<Assessment>, Error> {
+    let Some(hash) = tx.status.block_hash.filter(|_| tx.status.confirmed) else {
+        return Ok(None);
+    };
+    if tx.vin.iter().any(|vin| vin.is_coinbase) {
+        return Ok(None);
+    }
+    let Some(block) = block_fees(client, &hash).await? else {
+        return Ok(None);
+    };
+
+    // Count children confirmed in the same block, a low feerate parent paid for by its
+    // child (CPFP) is not out of band.
+    let mut children = vec![];
+    for status in outspends {
+        let Some(txid) = status.txid.filter(|_| status.spent) else {
+            continue;
+        };
+        if children.iter().any(|child: &Tx| child.txid == txid)
+            || status.status.as_ref().and_then(|s| s.block_hash) != Some(hash)
+        {
+            continue;
+        }
+        if let Some(child) = client.get_tx_info(&txid).await? {
+            children.push(child);
+        }
+    }
+
+    let (effective, children) = effective(tx, &children);
+    Ok(Some(Assessment {
+        effective,
+        children,
+        block,
+    }))
+}
+
+/// The effective feerate of `tx` given its `children` in the same block, and the number of
+/// children counted into it.
+///
+/// Children paying less than the transaction didn't pay for it, they would only lower the
+/// package feerate, so the higher of the two is taken.
+fn effective(tx: &Tx, children: &[Tx]) -> (f64, usize) {
+    let own = tx.fee as f64 / tx.weight.div_ceil(4) as f64;
+    let fee = tx.fee + children.iter().map(|child| child.fee).sum::<u64>();
+    let vsize =
+        tx.weight.div_ceil(4) + children.iter().map(|child| child.weight.div_ceil(4)).sum::<u64>();
+    let package = fee as f64 / vsize as f64;
+    if package > own {
+        (package, children.len())
+    } else {
+        (own, 0)
+    }
+}
+
+/// A transaction of a block flagged as confirmed out of band.
+#[derive(Debug, Serialize)]
+pub struct OutOfBand {
+    pub txid: Txid,
+    /// Feerate of the transaction alone, in sat/vB.
+    pub feerate: f64,
+    /// Effective feerate with its children in the block, in sat/vB.
+    pub effective: f64,
+    /// Number of children counted into the effective feerate.
+    pub children: usize,
+}
+
+/// Assess every transaction of the block `hash` against the block's floor, fetching its
+/// transactions with up to `jobs` requests in flight.
+///
+/// Returns the flagged transactions, `None` if the backend has no feerate distribution for
+/// the block. Children are found among the block's own transactions, so
+/// this takes a request per 25 transactions and none per flagged one.
+pub async fn scan_block(
+    client: &Client,
+    hash: &BlockHash,
+    jobs: usize,
+) -> anyhow::Result<Option<Vec<OutOfBand>>> {
+    let Some(block) = block_fees(client, hash).await? else {
+        return Ok(None);
+    };
+    let info = client.get_block_info(hash).await?;
+    let pages: Vec<u32> = (0..info.tx_count as u32).step_by(PAGE_SIZE).collect();
+    let txs: Vec<Vec<Tx>> = futures::stream::iter(pages)
+        .map(|start| async move { client.get_block_txs(hash, start).await })
+        .buffered(jobs.max(1))
+        .try_collect()
+        .await?;
+    let txs = txs.concat();
+
+    let mut children: HashMap<Txid, Vec<&Tx>> = HashMap::new();
+    for tx in &txs {
+        let parents: HashSet<Txid> = tx.vin.iter().map(|vin| vin.txid).collect();
+        for parent in parents {
+            children.entry(parent).or_default().push(tx);
+        }
+    }
+
+    let mut flagged = vec![];
+    for tx in txs.iter().filter(|tx| !tx.vin.iter().any(|vin| vin.is_coinbase)) {
+        let kids: Vec<Tx> = children
+            .get(&tx.txid)
+            .map_or(vec![], |kids| kids.iter().map(|&kid| kid.clone()).collect());
+        let (effective, counted) = effective(tx, &kids);
+        let assessment = Assessment {
+            effective,
+            children: counted,
+            block,
+        };
+        if assessment.is_out_of_band() {
+            flagged.push(OutOfBand {
+                txid: tx.txid,
+                feerate: tx.fee as f64 / tx.weight.div_ceil(4) as f64,
+                effective,
+                children: counted,
+            });
+        }
+    }
+    Ok(Some(flagged))
+}
+
+/// Render `assessment` as JSON, for the commands that expose it.
+pub fn report_json(assessment: &Assessment) -> serde_json::Value {
+    json!({
+        "effective_feerate": assessment.effective,
+        "children": assessment.children,
+        "block_floor": assessment.block.floor,
+        "block_median": assessment.block.median,
+        "flagged": assessment.is_out_of_band(),
+    })
+}
@@ -0,0 +1,226 @@
+//! Human-friendly rendering of transactions for `--format pretty`: tables of inputs and
+//! outputs with their addresses and amounts, in color when printing to a terminal.
+
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+
+use bitcoin::{Address, Network, Script, Txid};
+
+use crate::client::Client;
+use crate::endpoints;
+use crate::error;
+use crate::rbf::script_kind;
+use crate::timefmt;
+
+/// Whether to decorate output with ANSI colors.
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    /// Color output only when stdout is a terminal, `--no-color` isn't passed and `NO_COLOR`
+    /// isn't set.
+    pub fn detect(no_color: bool) -> Self {
+        Style {
+            color: !no_color
+                && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && std::io::stdout().is_terminal(),
+        }
+    }
+
+    fn paint(self, code: &str, s: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, s)
+        } else {
+            s.to_string()
+        }
+    }
+
+    fn bold(self, s: &str) -> String {
+        self.paint("1", s)
+    }
+
+    fn dim(self, s: &str) -> String {
+        self.paint("2", s)
+    }
+
+    fn red(self, s: &str) -> String {
+        self.paint("31", s)
+    }
+
+    fn green(self, s: &str) -> String {
+        self.paint("32", s)
+    }
+
+    fn yellow(self, s: &str) -> String {
+        self.paint("33", s)
+    }
+}
+
+/// An input or output row of a transaction table.
+struct Row {
+    label: String,
+    value: Option<u64>,
+}
+
+/// Render the transaction `txid` with its inputs and outputs, fee and confirmation depth.
+pub async fn tx(client: &Client, txid: &Txid, style: Style) -> anyhow::Result<String> {
+    let tx = client
+        .get_tx_info(txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+    let network = network(client).await?;
+    let vsize = tx.weight.div_ceil(4);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", style.bold(&format!("Transaction {}", tx.txid)));
+    let status = match (tx.status.block_height, tx.status.block_time) {
+        (Some(height), Some(time)) if tx.status.confirmed => {
+            let depth = client.get_height().await?.saturating_sub(height) + 1;
+            style.green(&format!(
+                "confirmed, {} confirmation{} (block {}, {})",
+                depth,
+                if depth == 1 { "" } else { "s" },
+                height,
+                timefmt::format_local(time)
+            ))
+        }
+        _ => style.yellow("unconfirmed"),
+    };
+    field(&mut out, style, "Status", &status);
+    field(
+        &mut out,
+        style,
+        "Size",
+        &format!("{} vB, {} WU, {} B", vsize, tx.weight, tx.size),
+    );
+    field(
+        &mut out,
+        style,
+        "Fee",
+        &format!("{} sat, {:.2} sat/vB", group(tx.fee), tx.fee as f64 / vsize as f64),
+    );
+
+    let inputs: Vec<Row> = tx
+        .vin
+        .iter()
+        .map(|vin| match &vin.prevout {
+            _ if vin.is_coinbase => Row {
+                label: "coinbase".to_string(),
+                value: None,
+            },
+            Some(prevout) => Row {
+                label: label(&prevout.scriptpubkey, network),
+                value: Some(prevout.value),
+            },
+            None => Row {
+                label: format!("{}:{}", vin.txid, vin.vout),
+                value: None,
+            },
+        })
+        .collect();
+    let outputs: Vec<Row> = tx
+        .vout
+        .iter()
+        .map(|vout| Row {
+            label: label(&vout.scriptpubkey, network),
+            value: Some(vout.value),
+        })
+        .collect();
+    let width = inputs
+        .iter()
+        .chain(&outputs)
+        .map(|row| row.label.len())
+        .max()
+        .unwrap_or(0)
+        .max("address".len());
+
+    table(&mut out, style, "Inputs", &inputs, width, |s| style.red(s));
+    table(&mut out, style, "Outputs", &outputs, width, |s| style.green(s));
+    Ok(out)
+}
+
+/// Identify the network of the backend from its genesis block, for encoding addresses.
+pub async fn network(client: &Client) -> anyhow::Result<Option<Network>> {
+    let genesis = client.get_block_hash(0).await?;
+    Ok(endpoints::network_of(&genesis))
+}
+
+/// The address paying to `script`, or its type if it has none.
+fn label(script: &Script, network: Option<Network>) -> String {
+    if script.is_op_return() {
+        return "OP_RETURN".to_string();
+    }
+    match network.and_then(|n| Address::from_script(script, n).ok()) {
+        Some(address) => address.to_string(),
+        None => format!("({} script)", script_kind(script)),
+    }
+}
+
+fn field(out: &mut String, style: Style, name: &str, value: &str) {
+    let _ = writeln!(out, "{}  {}", style.bold(&format!("{:<8}", name)), value);
+}
+
+fn table(
+    out: &mut String,
+    style: Style,
+    title: &str,
+    rows: &[Row],
+    width: usize,
+    paint: impl Fn(&str) -> String,
+) {
+    let _ = writeln!(out);
+    let _ = writeln!(out, "{}", style.bold(&format!("{} ({})", title, rows.len())));
+    let header = format!(
+        "{:>4}  {:<width$}  {:>16}  {:>17}",
+        "#",
+        "address",
+        "BTC",
+        "sat",
+        width = width
+    );
+    let _ = writeln!(out, "{}", style.dim(&header));
+    for (i, row) in rows.iter().enumerate() {
+        let (btc, sat) = match row.value {
+            Some(value) => (btc(value), group(value)),
+            None => (String::new(), String::new()),
+        };
+        let _ = writeln!(
+            out,
+            "{:>4}  {:<width$}  {}  {:>17}",
+            i,
+            row.label,
+            paint(&format!("{:>16}", btc)),
+            sat,
+            width = width
+        );
+    }
+    let total: u64 = rows.iter().filter_map(|row| row.value).sum();
+    let _ = writeln!(
+        out,
+        "{:>4}  {}  {:>16}  {:>17}",
+        "",
+        style.dim(&format!("{:<width$}", "total", width = width)),
+        btc(total),
+        group(total)
+    );
+}
+
+/// Format `sats` in BTC with all eight decimals.
+pub fn btc(sats: u64) -> String {
+    format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000)
+}
+
+/// Format `n` with thousands separators.
+fn group(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
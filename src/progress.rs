@@ -0,0 +1,191 @@
+//! Progress reporting and interruption of long-running commands.
+//!
+//! In JSON mode progress is emitted on stderr as NDJSON events so that programs wrapping the
+//! CLI can follow along without scraping human readable output, e.g.
+//!
+//! ```text
+//! {"event":"progress","done":120,"total":500}
+//! ```
+//!
+//! Otherwise a progress bar is drawn on stderr when it is a terminal.
+//!
+//! Commands that can stop early and still write out what they have call
+//! [`Progress::interruptible`], after which the first Ctrl-C only flags the interruption for
+//! them to check with [`Progress::is_interrupted`]. A second Ctrl-C exits at once.
+
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Once};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+use serde::Serialize;
+
+/// Exit code of a process killed by SIGINT, used on a second Ctrl-C.
+const SIGINT_EXIT_CODE: i32 = 130;
+
+/// Whether Ctrl-C was pressed since [`Progress::interruptible`] was called.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Guards installing the Ctrl-C handler once per process.
+static HANDLER: Once = Once::new();
+
+/// A progress event.
+#[derive(Debug, Serialize)]
+struct Event {
+    event: &'static str,
+    done: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<u64>,
+    #[serde(skip_serializing_if = "is_zero")]
+    errors: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Shared progress counter of a long-running command.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    json: bool,
+    done: Arc<AtomicU64>,
+    total: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+    /// The progress bar, `None` in JSON mode or when stderr isn't a terminal.
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    /// Create a progress reporter emitting events if `json` is set, and drawing a progress bar
+    /// otherwise.
+    pub fn new(json: bool) -> Self {
+        let bar = (!json && std::io::stderr().is_terminal()).then(|| {
+            // Commands that report no progress drop the bar untouched, which mustn't draw it.
+            ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr())
+                .with_style(spinner_style())
+                .with_finish(ProgressFinish::AndClear)
+        });
+        Self::with_bar(json, bar)
+    }
+
+    /// Create a progress reporter that reports nothing, for work that is part of a larger
+    /// unit of progress.
+    pub fn hidden() -> Self {
+        Self::with_bar(false, None)
+    }
+
+    fn with_bar(json: bool, bar: Option<ProgressBar>) -> Self {
+        Self {
+            json,
+            done: Arc::new(AtomicU64::new(0)),
+            total: Arc::new(AtomicU64::new(0)),
+            errors: Arc::new(AtomicU64::new(0)),
+            bar,
+        }
+    }
+
+    /// Set the total amount of work, if known.
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+        if let Some(bar) = &self.bar {
+            bar.set_style(bar_style());
+            bar.set_length(total);
+        }
+    }
+
+    /// Record `n` more units of work done.
+    pub fn inc(&self, n: u64) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+        if let Some(bar) = &self.bar {
+            bar.inc(n);
+        }
+        self.emit("progress");
+    }
+
+    /// Record a unit of work that failed without failing the command.
+    pub fn fail(&self) {
+        let errors = self.errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(bar) = &self.bar {
+            bar.set_message(format!(", {} failed", errors));
+        }
+        self.inc(1);
+    }
+
+    /// Record the absolute amount of work done.
+    pub fn set(&self, done: u64) {
+        self.done.store(done, Ordering::Relaxed);
+        if let Some(bar) = &self.bar {
+            bar.set_position(done);
+        }
+        self.emit("progress");
+    }
+
+    /// Signal that the work is complete, or stopped early if interrupted.
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+        self.emit(if Self::is_interrupted() {
+            "interrupted"
+        } else {
+            "done"
+        });
+    }
+
+    /// Let Ctrl-C interrupt the command instead of killing it, for commands that stop early
+    /// when [`Progress::is_interrupted`] and write out their partial results.
+    pub fn interruptible(&self) {
+        let bar = self.bar.clone();
+        HANDLER.call_once(|| {
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_err() {
+                    return;
+                }
+                INTERRUPTED.store(true, Ordering::Relaxed);
+                match &bar {
+                    Some(bar) => bar.println("interrupted, writing partial results"),
+                    None => eprintln!("interrupted, writing partial results"),
+                }
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(SIGINT_EXIT_CODE);
+                }
+            });
+        });
+    }
+
+    /// Whether Ctrl-C interrupted the command.
+    pub fn is_interrupted() -> bool {
+        INTERRUPTED.load(Ordering::Relaxed)
+    }
+
+    fn emit(&self, event: &'static str) {
+        if !self.json {
+            return;
+        }
+        let total = self.total.load(Ordering::Relaxed);
+        let event = Event {
+            event,
+            done: self.done.load(Ordering::Relaxed),
+            total: (total > 0).then_some(total),
+            errors: self.errors.load(Ordering::Relaxed),
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            // Progress is best effort, a closed stderr must not abort the command.
+            let _ = writeln!(std::io::stderr().lock(), "{}", line);
+        }
+    }
+}
+
+/// Style of the progress bar while the total is unknown.
+fn spinner_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner} {pos} done{msg} [{elapsed}]")
+        .unwrap_or_else(|_| ProgressStyle::default_spinner())
+        .tick_chars("|/-\\ ")
+}
+
+/// Style of the progress bar once the total is known.
+fn bar_style() -> ProgressStyle {
+    ProgressStyle::with_template("[{bar:40}] {pos}/{len}{msg} [{elapsed}, ETA {eta}]")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ")
+}
@@ -0,0 +1,320 @@
+//! Fee bumping of unconfirmed transactions by replacement (BIP125).
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use bitcoin::absolute::LockTime;
+use bitcoin::psbt::Psbt;
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, Script, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use esplora_client::Tx;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::fees;
+
+/// Feerate in sat/vB a replacement must add on top of the fees it evicts, Core's default
+/// `-incrementalrelayfee`.
+const INCREMENTAL_RELAY_FEERATE: u64 = 1;
+
+/// Maximum number of descendants followed, Core's default `-limitdescendantcount`.
+const MAX_DESCENDANTS: usize = 25;
+
+/// Maximum number of ancestors followed, Core's default `-limitancestorcount`.
+const MAX_ANCESTORS: usize = 25;
+
+/// How a transaction opts in to replacement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signaling {
+    /// One of its inputs has a sequence below `0xfffffffe`.
+    Explicit,
+    /// An unconfirmed ancestor signals. BIP125 makes the transaction replaceable, but Core
+    /// never implemented it (CVE-2021-31876), so replacing it relies on full-RBF nodes as
+    /// without signaling.
+    Inherited,
+    /// It doesn't signal, replacing it relies on full-RBF nodes.
+    None,
+}
+
+/// What it takes to replace a transaction at a higher fee.
+#[derive(Debug, Serialize)]
+pub struct Bump {
+    /// The transaction to replace.
+    pub txid: Txid,
+    /// Its fee, in satoshis.
+    pub fee: u64,
+    /// Its virtual size, in vbytes.
+    pub vsize: u64,
+    /// How it opts in to replacement.
+    pub signaling: Signaling,
+    /// Whether nodes without full-RBF relay a replacement, i.e. it signals explicitly.
+    pub replaceable: bool,
+    /// Number of unconfirmed descendants the replacement would evict.
+    pub descendants: usize,
+    /// Fee of those descendants, in satoshis.
+    pub descendants_fee: u64,
+    /// Lowest fee BIP125 accepts for a replacement of the same size, in satoshis.
+    pub min_fee: u64,
+    /// Backend estimate used, as confirmation target and feerate in sat/vB.
+    pub estimate: Option<(u16, f64)>,
+    /// Fee the replacement should pay, in satoshis.
+    pub required_fee: u64,
+    /// Index of the output identified as change, which pays for the bump.
+    pub change: Option<usize>,
+    /// Unsigned replacement with the change output reduced, as a base64 PSBT.
+    pub psbt: Option<String>,
+}
+
+impl Bump {
+    /// Fee to add to the original, in satoshis.
+    pub fn additional(&self) -> u64 {
+        self.required_fee.saturating_sub(self.fee)
+    }
+}
+
+/// Work out the fee a replacement of `txid` must pay to be accepted and to confirm within
+/// `target` blocks, and build it if the change output can be identified.
+pub async fn bump(client: &Client, txid: &Txid, target: u16) -> anyhow::Result<Bump> {
+    let tx = client
+        .get_tx_info(txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+    if tx.status.confirmed {
+        return Err(anyhow!("transaction {} is already confirmed", txid));
+    }
+    let vsize = tx.weight.div_ceil(4);
+    let signaling = signaling(client, &tx).await?;
+    let (descendants, descendants_fee) = descendants(client, txid).await?;
+
+    // Rules 3 and 4: pay at least the fees evicted, plus the incremental relay fee for the
+    // replacement's own size.
+    let min_fee = tx.fee + descendants_fee + INCREMENTAL_RELAY_FEERATE * vsize;
+    let estimate = fees::estimate_for(&client.get_fee_estimates().await?, target);
+    let target_fee = estimate.map_or(0, |(_, rate)| (rate * vsize as f64).ceil() as u64);
+    let required_fee = min_fee.max(target_fee);
+
+    let change = find_change(&tx, required_fee - tx.fee);
+    let psbt = match change {
+        Some(change) => Some(replacement(client, &tx, change, required_fee - tx.fee).await?),
+        None => None,
+    };
+
+    Ok(Bump {
+        txid: *txid,
+        fee: tx.fee,
+        vsize,
+        signaling,
+        replaceable: signaling == Signaling::Explicit,
+        descendants,
+        descendants_fee,
+        min_fee,
+        estimate,
+        required_fee,
+        change,
+        psbt: psbt.map(|psbt| psbt.to_string()),
+    })
+}
+
+/// How `tx` opts in to replacement, following its unconfirmed ancestors for inherited
+/// signaling.
+async fn signaling(client: &Client, tx: &Tx) -> anyhow::Result<Signaling> {
+    let signals = |tx: &Tx| {
+        tx.vin
+            .iter()
+            .any(|vin| vin.sequence < Sequence::ENABLE_LOCKTIME_NO_RBF.0)
+    };
+    if signals(tx) {
+        return Ok(Signaling::Explicit);
+    }
+    let mut seen = HashSet::new();
+    let mut queue: Vec<Txid> = tx.vin.iter().map(|vin| vin.txid).collect();
+    while let Some(txid) = queue.pop() {
+        if seen.len() >= MAX_ANCESTORS || !seen.insert(txid) {
+            continue;
+        }
+        let Some(ancestor) = client.get_tx_info(&txid).await? else {
+            continue;
+        };
+        if ancestor.status.confirmed {
+            continue;
+        }
+        if signals(&ancestor) {
+            return Ok(Signaling::Inherited);
+        }
+        queue.extend(ancestor.vin.iter().map(|vin| vin.txid));
+    }
+    Ok(Signaling::None)
+}
+
+/// The number and total fee of the unconfirmed descendants of `txid`.
+async fn descendants(client: &Client, txid: &Txid) -> anyhow::Result<(usize, u64)> {
+    let mut seen = HashSet::new();
+    let mut queue = vec![*txid];
+    let mut fee = 0;
+    while let Some(txid) = queue.pop() {
+        for status in client.get_tx_outspends(&txid).await? {
+            let Some(child) = status.txid.filter(|_| status.spent) else {
+                continue;
+            };
+            let confirmed = status.status.is_some_and(|s| s.confirmed);
+            if confirmed || seen.len() >= MAX_DESCENDANTS || !seen.insert(child) {
+                continue;
+            }
+            if let Some(child) = client.get_tx_info(&child).await? {
+                fee += child.fee;
+                queue.push(child.txid);
+            }
+        }
+    }
+    Ok((seen.len(), fee))
+}
+
+/// Index of the output that looks like change and can pay `additional` more fee.
+///
+/// Only an output of the same script type as every input, and the only such output, is taken
+/// as change.
+fn find_change(tx: &Tx, additional: u64) -> Option<usize> {
+    if tx.vout.len() < 2 {
+        return None;
+    }
+    let mut kinds = tx
+        .vin
+        .iter()
+        .map(|vin| vin.prevout.as_ref().map(|p| script_kind(&p.scriptpubkey)));
+    let kind = kinds.next()??;
+    if !kinds.all(|k| k == Some(kind)) {
+        return None;
+    }
+    let mut candidates = tx
+        .vout
+        .iter()
+        .enumerate()
+        .filter(|(_, vout)| script_kind(&vout.scriptpubkey) == kind);
+    let (index, vout) = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    let remaining = vout.value.checked_sub(additional)?;
+    (remaining >= vout.scriptpubkey.minimal_non_dust().to_sat()).then_some(index)
+}
+
+/// Short name of the standard type of `script`, `other` for non-standard ones.
+pub fn script_kind(script: &Script) -> &'static str {
+    if script.is_p2pkh() {
+        "p2pkh"
+    } else if script.is_p2sh() {
+        "p2sh"
+    } else if script.is_p2wpkh() {
+        "p2wpkh"
+    } else if script.is_p2wsh() {
+        "p2wsh"
+    } else if script.is_p2tr() {
+        "p2tr"
+    } else {
+        "other"
+    }
+}
+
+/// An unsigned copy of `tx` with output `change` reduced by `additional`, signaling
+/// replacement, with the previous outputs needed to sign it.
+async fn replacement(
+    client: &Client,
+    tx: &Tx,
+    change: usize,
+    additional: u64,
+) -> anyhow::Result<Psbt> {
+    let unsigned = Transaction {
+        version: Version(tx.version),
+        lock_time: LockTime::from_consensus(tx.locktime),
+        input: tx
+            .vin
+            .iter()
+            .map(|vin| TxIn {
+                previous_output: OutPoint::new(vin.txid, vin.vout),
+                script_sig: Default::default(),
+                sequence: Sequence(vin.sequence.min(Sequence::ENABLE_RBF_NO_LOCKTIME.0)),
+                witness: Witness::new(),
+            })
+            .collect(),
+        output: tx
+            .vout
+            .iter()
+            .enumerate()
+            .map(|(i, vout)| TxOut {
+                value: Amount::from_sat(if i == change {
+                    vout.value - additional
+                } else {
+                    vout.value
+                }),
+                script_pubkey: vout.scriptpubkey.clone(),
+            })
+            .collect(),
+    };
+
+    let mut psbt = Psbt::from_unsigned_tx(unsigned)?;
+    for (input, vin) in psbt.inputs.iter_mut().zip(&tx.vin) {
+        let Some(prevout) = &vin.prevout else {
+            continue;
+        };
+        if prevout.scriptpubkey.is_witness_program() {
+            input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(prevout.value),
+                script_pubkey: prevout.scriptpubkey.clone(),
+            });
+        } else {
+            input.non_witness_utxo = client.get_tx(&vin.txid).await?;
+        }
+    }
+    Ok(psbt)
+}
+
+/// Render `bump` for the terminal.
+pub fn report(bump: &Bump) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("txid: {}\n", bump.txid));
+    let signaling = match bump.signaling {
+        Signaling::Explicit => "yes",
+        Signaling::Inherited => {
+            "only inherited from an unconfirmed ancestor, which Core ignores (CVE-2021-31876), \
+             replacing it relies on full-RBF nodes"
+        }
+        Signaling::None => "no, replacing it relies on full-RBF nodes",
+    };
+    out.push_str(&format!("signals RBF: {}\n", signaling));
+    out.push_str(&format!(
+        "current: {} sat, {} vB, {:.2} sat/vB\n",
+        bump.fee,
+        bump.vsize,
+        bump.fee as f64 / bump.vsize as f64
+    ));
+    if bump.descendants > 0 {
+        out.push_str(&format!(
+            "descendants evicted: {} paying {} sat\n",
+            bump.descendants, bump.descendants_fee
+        ));
+    }
+    out.push_str(&format!(
+        "BIP125 minimum: {} sat, {:.2} sat/vB\n",
+        bump.min_fee,
+        bump.min_fee as f64 / bump.vsize as f64
+    ));
+    if let Some((target, rate)) = bump.estimate {
+        out.push_str(&format!("estimate for {} blocks: {:.2} sat/vB\n", target, rate));
+    }
+    out.push_str(&format!(
+        "required: {} sat, {:.2} sat/vB (+{} sat)\n",
+        bump.required_fee,
+        bump.required_fee as f64 / bump.vsize as f64,
+        bump.additional()
+    ));
+    match (&bump.change, &bump.psbt) {
+        (Some(change), Some(psbt)) => {
+            out.push_str(&format!("change output: {}\n", change));
+            out.push_str(&format!("psbt: {}\n", psbt));
+        }
+        _ => out.push_str("change output: not identified, add the fee by hand\n"),
+    }
+    out
+}
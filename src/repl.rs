@@ -0,0 +1,144 @@
+//! Interactive session running commands against a single client.
+//!
+//! Lines are parsed like the arguments of the binary without the global options, e.g.
+//! `get-tx-info <txid>` or `show block $tip`. Variables are expanded before parsing:
+//!
+//! - `$tip`: the height of the current tip
+//! - `$tiphash`: the hash of the current tip
+
+use clap::{CommandFactory, Parser};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+
+use crate::{config, run, Commands, Session};
+
+/// Name of the history file in the state directory.
+const HISTORY_FILE: &str = "repl_history";
+
+/// Words handled by the repl itself rather than parsed as commands.
+const BUILTINS: [&str; 3] = ["help", "exit", "quit"];
+
+/// A line of input, a command without the binary name and global options.
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// Completes command names, including nested subcommands such as `show block`.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct LineHelper {
+    command: clap::Command,
+}
+
+impl Completer for LineHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let mut command = &self.command;
+        for word in line[..start].split_whitespace() {
+            match command.find_subcommand(word) {
+                Some(subcommand) => command = subcommand,
+                None => return Ok((start, vec![])),
+            }
+        }
+
+        let prefix = &line[start..];
+        let mut candidates: Vec<String> = command
+            .get_subcommands()
+            .map(|c| c.get_name())
+            .chain(BUILTINS.into_iter().filter(|_| start == 0))
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        candidates.sort();
+        Ok((start, candidates))
+    }
+}
+
+/// Read and run commands until end of input or `exit`.
+///
+/// Errors of a command are printed and don't end the session.
+pub async fn repl(session: &Session) -> anyhow::Result<()> {
+    let mut editor: Editor<LineHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(LineHelper {
+        command: Line::command(),
+    }));
+    let history = config::state_dir()?.join(HISTORY_FILE);
+    // There is no history yet on the first run.
+    let _ = editor.load_history(&history);
+
+    loop {
+        let line = match editor.readline("esplora> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        match line {
+            "exit" | "quit" => break,
+            "help" => {
+                Line::command().print_help()?;
+                continue;
+            }
+            _ => {}
+        }
+        if let Err(e) = run_line(session, line).await {
+            eprintln!("Error: {:#}", e);
+        }
+    }
+
+    if let Some(parent) = history.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    editor.save_history(&history)?;
+    Ok(())
+}
+
+/// Expand the variables of `line`, then parse and run it.
+async fn run_line(session: &Session, line: &str) -> anyhow::Result<()> {
+    let words = shlex::split(line).ok_or(anyhow::anyhow!("unbalanced quotes"))?;
+    let mut args = Vec::with_capacity(words.len());
+    for word in words {
+        args.push(expand(session, word).await?);
+    }
+
+    let line = match Line::try_parse_from(args) {
+        Ok(line) => line,
+        Err(e) => {
+            e.print()?;
+            return Ok(());
+        }
+    };
+    run(line.command, session).await
+}
+
+/// Replace `word` with the value of the variable it names, if it names one.
+async fn expand(session: &Session, word: String) -> anyhow::Result<String> {
+    let client = &session.client;
+    match word.as_str() {
+        "$tip" => Ok(client.get_height().await?.to_string()),
+        "$tiphash" => {
+            let height = client.get_height().await?;
+            Ok(client.get_block_hash(height).await?.to_string())
+        }
+        var if var.starts_with('$') => Err(anyhow::anyhow!("unknown variable `{}`", var)),
+        _ => Ok(word),
+    }
+}
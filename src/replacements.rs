@@ -0,0 +1,136 @@
+//! Replacements seen by the backend, from the mempool.space `/v1/replacements` and
+//! `/v1/tx/:txid/rbf` endpoints. Other backends don't track them.
+
+use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::{self, Feature};
+use crate::client::Client;
+use crate::error;
+use crate::timefmt;
+
+/// A transaction and the transactions it replaced, recursively.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RbfTree {
+    /// The replacing transaction.
+    pub tx: RbfTx,
+    /// UNIX timestamp of when it was first seen.
+    pub time: u64,
+    /// Whether any of the transactions it replaced didn't signal, so it relied on full-RBF.
+    #[serde(default)]
+    pub full_rbf: bool,
+    /// Whether it was mined.
+    #[serde(default)]
+    pub mined: bool,
+    /// The transactions it replaced.
+    #[serde(default)]
+    pub replaces: Vec<RbfTree>,
+}
+
+/// A transaction in a replacement tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RbfTx {
+    /// The transaction id.
+    pub txid: Txid,
+    /// Fee in satoshis.
+    pub fee: u64,
+    /// Virtual size in vbytes.
+    pub vsize: f64,
+    /// Total output value in satoshis.
+    pub value: u64,
+    /// Feerate in sat/vB.
+    pub rate: f64,
+    /// Whether it signals replaceability.
+    #[serde(default)]
+    pub rbf: bool,
+}
+
+/// The replacement history of a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxRbf {
+    /// The tree of replacements rooted at the latest version of the transaction.
+    pub replacements: Option<RbfTree>,
+    /// The transactions this one replaced directly.
+    pub replaces: Option<Vec<Txid>>,
+}
+
+/// Fetch the most recent replacements, only those relying on full-RBF if `full_rbf` is set.
+pub async fn recent(client: &Client, full_rbf: bool) -> anyhow::Result<Vec<RbfTree>> {
+    let path = if full_rbf {
+        "/v1/fullrbf/replacements"
+    } else {
+        "/v1/replacements"
+    };
+    client
+        .get_opt_json(path)
+        .await?
+        .ok_or_else(|| capabilities::unsupported(Feature::Replacements))
+}
+
+/// Fetch the replacement history of `txid`.
+pub async fn tx_rbf(client: &Client, txid: &Txid) -> anyhow::Result<TxRbf> {
+    capabilities::require(client, Feature::Replacements).await?;
+    client
+        .get_opt_json(&format!("/v1/tx/{}/rbf", txid))
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))
+}
+
+/// Render `trees` for the terminal, each replacing transaction above the ones it replaced.
+pub fn report(trees: &[RbfTree]) -> String {
+    let mut out = String::new();
+    for tree in trees {
+        render(tree, 0, &mut out);
+    }
+    out
+}
+
+/// Render the history of a transaction for the terminal.
+pub fn report_tx(txid: &Txid, rbf: &TxRbf) -> String {
+    let mut out = String::new();
+    if let Some(replaces) = rbf.replaces.as_ref().filter(|r| !r.is_empty()) {
+        out.push_str(&format!("{} replaced:\n", txid));
+        for replaced in replaces {
+            out.push_str(&format!("  {}\n", replaced));
+        }
+    }
+    if let Some(tree) = &rbf.replacements {
+        if tree.tx.txid != *txid {
+            out.push_str(&format!("{} was replaced, latest version first:\n", txid));
+        }
+        render(tree, 0, &mut out);
+    }
+    if out.is_empty() {
+        out.push_str(&format!("no replacements seen for {}\n", txid));
+    }
+    out
+}
+
+fn render(tree: &RbfTree, depth: usize, out: &mut String) {
+    let mut flags = vec![];
+    if !tree.replaces.is_empty() {
+        flags.push(if tree.full_rbf {
+            "full-rbf"
+        } else {
+            "signaled"
+        });
+    }
+    if tree.mined {
+        flags.push("mined");
+    }
+    let line = format!(
+        "{}{}  {}  {:>8.2} sat/vB  {:>9} sat  {}",
+        "  ".repeat(depth),
+        timefmt::format_rfc3339(tree.time),
+        tree.tx.txid,
+        tree.tx.rate,
+        tree.tx.fee,
+        flags.join(" ")
+    );
+    out.push_str(line.trim_end());
+    out.push('\n');
+    for replaced in &tree.replaces {
+        render(replaced, depth + 1, out);
+    }
+}
@@ -0,0 +1,294 @@
+//! Bitcoin Core compatible JSON-RPC server translating a subset of methods to Esplora requests.
+//!
+//! Supported methods, with positional parameters only:
+//!
+//! - `getblockhash height`
+//! - `getrawtransaction txid` (hex only, verbose output is refused)
+//! - `sendrawtransaction hexstring`
+//! - `getblockheader blockhash (verbose=true)`, without `chainwork` which Esplora doesn't expose
+//! - `estimatesmartfee conf_target`
+//!
+//! Both JSON-RPC 1.0 as sent by `bitcoin-cli` and 2.0 are understood, including batches.
+//! Authentication headers are accepted and ignored, so the listen address should stay local.
+
+use bitcoin::consensus::encode;
+use bitcoin::{BlockHash, Transaction, Txid};
+use esplora_client::Error;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::client::Client;
+use crate::fees;
+use crate::http::{self, Request, Response};
+
+/// Error codes of Bitcoin Core's `RPCErrorCode`.
+const RPC_MISC_ERROR: i64 = -1;
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+const RPC_INVALID_PARAMETER: i64 = -8;
+const RPC_DESERIALIZATION_ERROR: i64 = -22;
+const RPC_VERIFY_REJECTED: i64 = -26;
+const RPC_INVALID_REQUEST: i64 = -32600;
+const RPC_METHOD_NOT_FOUND: i64 = -32601;
+const RPC_PARSE_ERROR: i64 = -32700;
+
+/// An error returned to the caller in the `error` member of the response.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Translate a backend error, using `not_found` when the backend answered 404.
+    fn backend(error: Error, not_found: RpcError) -> Self {
+        match error {
+            Error::HttpResponse { status: 404, .. } => not_found,
+            Error::HttpResponse { message, .. } => Self::new(RPC_MISC_ERROR, message),
+            e => Self::new(RPC_MISC_ERROR, e.to_string()),
+        }
+    }
+}
+
+/// Serve the JSON-RPC methods on `listen`, translated to requests to `client`. Runs until
+/// interrupted.
+pub async fn serve(client: &Client, listen: &str) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+    eprintln!("serving JSON-RPC on http://{}", listener.local_addr()?);
+    let client = client.clone();
+    http::serve(listener, move |request| handle(client.clone(), request)).await?;
+    Ok(())
+}
+
+/// Answer an HTTP request carrying a single JSON-RPC request or a batch.
+async fn handle(client: Client, request: Request) -> Response {
+    if request.method != "POST" {
+        return Response {
+            status: 405,
+            content_type: "text/plain",
+            body: "JSON-RPC server handles only POST requests".to_string(),
+        };
+    }
+    let (status, body) = match serde_json::from_slice::<Value>(&request.body) {
+        Err(e) => (
+            500,
+            reply(&Value::Null, false, Err(RpcError::new(RPC_PARSE_ERROR, e.to_string()))),
+        ),
+        Ok(Value::Array(batch)) => {
+            let mut replies = Vec::with_capacity(batch.len());
+            for request in &batch {
+                replies.push(dispatch(&client, request).await.1);
+            }
+            (200, Value::Array(replies))
+        }
+        Ok(request) => dispatch(&client, &request).await,
+    };
+    Response {
+        status,
+        content_type: "application/json",
+        body: body.to_string(),
+    }
+}
+
+/// Run a single request, returning the HTTP status Bitcoin Core would answer it with and the
+/// reply.
+async fn dispatch(client: &Client, request: &Value) -> (u16, Value) {
+    let id = request.get("id").unwrap_or(&Value::Null);
+    let v2 = request.get("jsonrpc").and_then(Value::as_str) == Some("2.0");
+    let result = match request.get("method").and_then(Value::as_str) {
+        None => Err(RpcError::new(RPC_INVALID_REQUEST, "Method must be a string")),
+        Some(method) => match request.get("params").unwrap_or(&Value::Null) {
+            Value::Null => call(client, method, &[]).await,
+            Value::Array(params) => call(client, method, params).await,
+            _ => Err(RpcError::new(
+                RPC_INVALID_PARAMETER,
+                "Named parameters are not supported",
+            )),
+        },
+    };
+    // Bitcoin Core signals errors in the HTTP status for JSON-RPC 1.0 only.
+    let status = match &result {
+        Ok(_) => 200,
+        Err(_) if v2 => 200,
+        Err(e) if e.code == RPC_METHOD_NOT_FOUND => 404,
+        Err(_) => 500,
+    };
+    (status, reply(id, v2, result))
+}
+
+/// Build the reply to request `id`.
+fn reply(id: &Value, v2: bool, result: Result<Value, RpcError>) -> Value {
+    match (v2, result) {
+        (false, Ok(result)) => json!({ "result": result, "error": null, "id": id }),
+        (false, Err(e)) => json!({
+            "result": null,
+            "error": { "code": e.code, "message": e.message },
+            "id": id,
+        }),
+        (true, Ok(result)) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        (true, Err(e)) => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": e.code, "message": e.message },
+            "id": id,
+        }),
+    }
+}
+
+/// Run the method `method`.
+async fn call(client: &Client, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+    match method {
+        "getblockhash" => {
+            let height: u32 = required(params, 0, "height")?;
+            let hash = client.get_block_hash(height).await.map_err(|e| {
+                let out_of_range =
+                    RpcError::new(RPC_INVALID_PARAMETER, "Block height out of range");
+                RpcError::backend(e, out_of_range)
+            })?;
+            Ok(json!(hash))
+        }
+        "getrawtransaction" => {
+            let txid: Txid = required(params, 0, "txid")?;
+            if verbosity(params.get(1)) > 0 {
+                return Err(RpcError::new(
+                    RPC_INVALID_PARAMETER,
+                    "Verbose output is not supported, request the hex instead",
+                ));
+            }
+            let not_found = || {
+                RpcError::new(
+                    RPC_INVALID_ADDRESS_OR_KEY,
+                    "No such mempool or blockchain transaction",
+                )
+            };
+            let tx = client
+                .get_tx(&txid)
+                .await
+                .map_err(|e| RpcError::backend(e, not_found()))?
+                .ok_or_else(not_found)?;
+            Ok(json!(encode::serialize_hex(&tx)))
+        }
+        "sendrawtransaction" => {
+            let hex: String = required(params, 0, "hexstring")?;
+            let tx: Transaction = encode::deserialize_hex(&hex)
+                .map_err(|_| RpcError::new(RPC_DESERIALIZATION_ERROR, "TX decode failed"))?;
+            client.broadcast(&tx).await.map_err(|e| match e {
+                Error::HttpResponse { message, .. } => RpcError::new(RPC_VERIFY_REJECTED, message),
+                e => RpcError::new(RPC_MISC_ERROR, e.to_string()),
+            })?;
+            Ok(json!(tx.compute_txid()))
+        }
+        "getblockheader" => {
+            let hash: BlockHash = required(params, 0, "blockhash")?;
+            let verbose = optional(params, 1, "verbose")?.unwrap_or(true);
+            block_header(client, &hash, verbose).await
+        }
+        "estimatesmartfee" => {
+            let target: u16 = required(params, 0, "conf_target")?;
+            if !(1..=1008).contains(&target) {
+                return Err(RpcError::new(
+                    RPC_INVALID_PARAMETER,
+                    "Invalid conf_target, must be between 1 and 1008",
+                ));
+            }
+            let estimates = client
+                .get_fee_estimates()
+                .await
+                .map_err(|e| RpcError::new(RPC_MISC_ERROR, e.to_string()))?;
+            Ok(match fees::estimate_for(&estimates, target) {
+                // sat/vB to BTC/kvB.
+                Some((blocks, rate)) => json!({
+                    "feerate": (rate * 1e3).round() / 1e8,
+                    "blocks": blocks,
+                }),
+                None => json!({
+                    "errors": ["Insufficient data or no feerate found"],
+                    "blocks": 0,
+                }),
+            })
+        }
+        _ => Err(RpcError::new(RPC_METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+/// The header of block `hash`, as hex or as the object of Core's verbose output.
+async fn block_header(client: &Client, hash: &BlockHash, verbose: bool) -> Result<Value, RpcError> {
+    let not_found = || RpcError::new(RPC_INVALID_ADDRESS_OR_KEY, "Block not found");
+    let backend = |e| RpcError::backend(e, not_found());
+    if !verbose {
+        let header = client.get_header_by_hash(hash).await.map_err(backend)?;
+        return Ok(json!(encode::serialize_hex(&header)));
+    }
+
+    let (info, status, tip) = futures::try_join!(
+        client.get_block_info(hash),
+        client.get_block_status(hash),
+        client.get_height(),
+    )
+    .map_err(backend)?;
+    let confirmations = if status.in_best_chain {
+        i64::from(tip) - i64::from(info.height) + 1
+    } else {
+        -1
+    };
+    let mut header = json!({
+        "hash": info.id,
+        "confirmations": confirmations,
+        "height": info.height,
+        "version": info.version.to_consensus(),
+        "versionHex": format!("{:08x}", info.version.to_consensus()),
+        "merkleroot": info.merkle_root,
+        "time": info.timestamp,
+        "mediantime": info.mediantime,
+        "nonce": info.nonce,
+        "bits": format!("{:08x}", info.bits.to_consensus()),
+        "difficulty": info.difficulty,
+        "nTx": info.tx_count,
+    });
+    if let Some(previous) = info.previousblockhash {
+        header["previousblockhash"] = json!(previous);
+    }
+    if let Some(next) = status.next_best {
+        header["nextblockhash"] = json!(next);
+    }
+    Ok(header)
+}
+
+/// The parameter at `index`, `None` if it is missing or null.
+fn optional<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+    name: &str,
+) -> Result<Option<T>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| RpcError::new(RPC_INVALID_PARAMETER, format!("Invalid {}: {}", name, e))),
+    }
+}
+
+/// The parameter at `index`, which must be given.
+fn required<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+    name: &str,
+) -> Result<T, RpcError> {
+    optional(params, index, name)?
+        .ok_or_else(|| RpcError::new(RPC_INVALID_PARAMETER, format!("Missing parameter {}", name)))
+}
+
+/// The verbosity of `getrawtransaction`, which Core takes as a bool or a number.
+fn verbosity(param: Option<&Value>) -> u64 {
+    match param {
+        Some(Value::Bool(verbose)) => u64::from(*verbose),
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(1),
+        _ => 0,
+    }
+}
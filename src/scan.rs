@@ -0,0 +1,312 @@
+//! Descriptor scanning and wallet-level rollup of the transactions found.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::anyhow;
+use bitcoin::{Script, ScriptBuf, Txid};
+use esplora_client::Tx;
+use miniscript::{Descriptor, DescriptorPublicKey};
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::progress::Progress;
+
+/// Number of confirmed transactions the backend returns per page of script history.
+pub const CHAIN_PAGE_SIZE: usize = 25;
+
+/// The role of a descriptor in a wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keychain {
+    /// Receiving addresses handed out to others.
+    External,
+    /// Change addresses the wallet pays back to itself.
+    Internal,
+}
+
+impl fmt::Display for Keychain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Keychain::External => write!(f, "external"),
+            Keychain::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// Pair the external descriptor with an optional change descriptor.
+///
+/// A multipath descriptor such as `wpkh(xpub/<0;1>/*)` is split into its external and internal
+/// keychains when no change descriptor is given.
+pub fn keychains(
+    external: &str,
+    internal: Option<&str>,
+) -> anyhow::Result<Vec<(Keychain, Descriptor<DescriptorPublicKey>)>> {
+    let external: Descriptor<DescriptorPublicKey> = external.parse()?;
+    let internal: Option<Descriptor<DescriptorPublicKey>> = internal.map(str::parse).transpose()?;
+    if external.is_multipath() {
+        if internal.is_some() {
+            return Err(anyhow!("a multipath descriptor already includes the change keychain"));
+        }
+        let mut single = external.into_single_descriptors()?.into_iter();
+        let (Some(external), Some(internal), None) = (single.next(), single.next(), single.next())
+        else {
+            return Err(anyhow!("a multipath descriptor must have exactly two paths"));
+        };
+        return Ok(vec![(Keychain::External, external), (Keychain::Internal, internal)]);
+    }
+
+    let mut keychains = vec![(Keychain::External, external)];
+    if let Some(internal) = internal {
+        keychains.push((Keychain::Internal, internal));
+    }
+    Ok(keychains)
+}
+
+/// Everything learned from scanning a wallet's descriptors.
+#[derive(Debug, Default)]
+pub struct ScanResult {
+    /// Every derived script, with the keychain and index it was derived at.
+    pub spks: HashMap<ScriptBuf, (Keychain, u32)>,
+    /// Last index with history of each keychain.
+    pub last_active: BTreeMap<Keychain, u32>,
+    /// All transactions touching the wallet's scripts.
+    pub txs: HashMap<Txid, Tx>,
+}
+
+/// Derive scripts from each keychain until `gap_limit` consecutive scripts have no history,
+/// requesting up to `parallel` script histories at once.
+///
+/// Progress is counted in scripts scanned, the total isn't known up front. Once interrupted the
+/// scan stops after the batch in flight, returning what it found so far.
+pub async fn scan(
+    client: &Client,
+    keychains: &[(Keychain, Descriptor<DescriptorPublicKey>)],
+    gap_limit: u32,
+    parallel: u32,
+    progress: &Progress,
+) -> anyhow::Result<ScanResult> {
+    let mut result = ScanResult::default();
+
+    for (keychain, descriptor) in keychains {
+        if Progress::is_interrupted() {
+            break;
+        }
+        // A descriptor without a wildcard describes a single script.
+        let (gap_limit, parallel) = if descriptor.has_wildcard() {
+            (gap_limit.max(1), parallel.max(1))
+        } else {
+            (1, 1)
+        };
+        let mut next = 0;
+        let mut unused = 0;
+
+        while unused < gap_limit && !Progress::is_interrupted() {
+            let batch = (next..next + parallel)
+                .map(|i| Ok((i, descriptor.at_derivation_index(i)?.script_pubkey())))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let histories = futures::future::try_join_all(batch.iter().map(|(_, spk)| async {
+                let history = script_history(client, spk).await;
+                progress.inc(1);
+                history
+            }))
+            .await?;
+
+            for ((index, spk), history) in batch.into_iter().zip(histories) {
+                if unused >= gap_limit {
+                    break;
+                }
+                if history.is_empty() {
+                    unused += 1;
+                } else {
+                    unused = 0;
+                    result.last_active.insert(*keychain, index);
+                }
+                for tx in history {
+                    result.txs.insert(tx.txid, tx);
+                }
+                result.spks.insert(spk, (*keychain, index));
+            }
+            next += parallel;
+            if !descriptor.has_wildcard() {
+                break;
+            }
+        }
+    }
+    progress.finish();
+
+    Ok(result)
+}
+
+/// Fetch the full mempool and confirmed history of `script`, paging through confirmed txs.
+pub async fn script_history(client: &Client, script: &Script) -> anyhow::Result<Vec<Tx>> {
+    let mut history = client.scripthash_txs(script, None).await?;
+    let mut confirmed = history.iter().filter(|tx| tx.status.confirmed).count();
+
+    while confirmed == CHAIN_PAGE_SIZE {
+        let last_seen = history.last().map(|tx| tx.txid);
+        let page = client.scripthash_txs(script, last_seen).await?;
+        confirmed = page.len();
+        history.extend(page);
+    }
+
+    Ok(history)
+}
+
+/// Net change `tx` makes to the value locked to `script`, in satoshis.
+pub fn net_effect(tx: &Tx, script: &Script) -> i64 {
+    let received: u64 = tx
+        .vout
+        .iter()
+        .filter(|vout| vout.scriptpubkey.as_script() == script)
+        .map(|vout| vout.value)
+        .sum();
+    let spent: u64 = tx
+        .vin
+        .iter()
+        .filter_map(|vin| vin.prevout.as_ref())
+        .filter(|prevout| prevout.scriptpubkey.as_script() == script)
+        .map(|prevout| prevout.value)
+        .sum();
+    received as i64 - spent as i64
+}
+
+/// How a transaction moved funds relative to the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxKind {
+    /// Funds received from others.
+    Incoming,
+    /// Funds sent to others, possibly with change back to the wallet.
+    Outgoing,
+    /// Funds moved between the wallet's own scripts.
+    #[serde(rename = "self")]
+    SelfTransfer,
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxKind::Incoming => write!(f, "incoming"),
+            TxKind::Outgoing => write!(f, "outgoing"),
+            TxKind::SelfTransfer => write!(f, "self"),
+        }
+    }
+}
+
+/// The effect of a single transaction on the wallet.
+#[derive(Debug, Clone, Serialize)]
+pub struct TxSummary {
+    /// The transaction id.
+    pub txid: Txid,
+    /// Confirmation height, `None` if unconfirmed.
+    pub height: Option<u32>,
+    /// How the transaction moved funds.
+    pub kind: TxKind,
+    /// Value received on the wallet's external scripts, in satoshis.
+    pub received: u64,
+    /// Value returned to the wallet's internal scripts, in satoshis.
+    pub change: u64,
+    /// Value paid to scripts outside the wallet, in satoshis.
+    pub sent: u64,
+    /// Fee paid, in satoshis. Only attributed to the wallet if it funded the transaction.
+    pub fee: u64,
+    /// Net change to the wallet balance, in satoshis.
+    pub net: i64,
+}
+
+/// Classify every transaction found by [`scan`], ordered by confirmation height with
+/// unconfirmed transactions last.
+///
+/// Outputs to the internal keychain of a transaction the wallet funded are counted as change.
+/// When no internal keychain is scanned, outputs back to any wallet script are counted as
+/// change instead.
+pub fn rollup(result: &ScanResult) -> Vec<TxSummary> {
+    let has_internal = result.spks.values().any(|(k, _)| *k == Keychain::Internal);
+
+    let mut summaries: Vec<TxSummary> = result
+        .txs
+        .values()
+        .map(|tx| {
+            let spent: u64 = tx
+                .vin
+                .iter()
+                .filter_map(|vin| vin.prevout.as_ref())
+                .filter(|prevout| result.spks.contains_key(&prevout.scriptpubkey))
+                .map(|prevout| prevout.value)
+                .sum();
+            let funded = spent > 0;
+
+            let (mut received, mut change, mut sent) = (0, 0, 0);
+            for vout in &tx.vout {
+                match result.spks.get(&vout.scriptpubkey) {
+                    Some((Keychain::Internal, _)) if funded => change += vout.value,
+                    Some((Keychain::External, _)) if funded && !has_internal => {
+                        change += vout.value
+                    }
+                    Some(_) => received += vout.value,
+                    None => sent += vout.value,
+                }
+            }
+
+            let kind = match (funded, sent) {
+                (false, _) => TxKind::Incoming,
+                (true, 0) => TxKind::SelfTransfer,
+                (true, _) => TxKind::Outgoing,
+            };
+            let net = (received + change) as i64 - spent as i64;
+
+            TxSummary {
+                txid: tx.txid,
+                height: tx.status.block_height.filter(|_| tx.status.confirmed),
+                kind,
+                received,
+                change,
+                sent: if funded { sent } else { 0 },
+                fee: if funded { tx.fee } else { 0 },
+                net,
+            }
+        })
+        .collect();
+
+    summaries.sort_by_key(|s| (s.height.is_none(), s.height, s.txid));
+    summaries
+}
+
+/// Render the rollup as a table followed by wallet totals.
+pub fn report(result: &ScanResult, summaries: &[TxSummary]) -> String {
+    let mut out = format!(
+        "{:>8}  {:<64}  {:<8}  {:>12}  {:>12}  {:>12}  {:>8}  {:>13}\n",
+        "height", "txid", "kind", "received", "sent", "change", "fee", "net"
+    );
+    for s in summaries {
+        let height = s.height.map_or("-".to_string(), |h| h.to_string());
+        out.push_str(&format!(
+            "{:>8}  {:<64}  {:<8}  {:>12}  {:>12}  {:>12}  {:>8}  {:>+13}\n",
+            height, s.txid, s.kind, s.received, s.sent, s.change, s.fee, s.net
+        ));
+    }
+
+    let balance: i64 = summaries.iter().map(|s| s.net).sum();
+    let pending: i64 = summaries.iter().filter(|s| s.height.is_none()).map(|s| s.net).sum();
+    out.push('\n');
+    for (keychain, index) in &result.last_active {
+        out.push_str(&format!("last active {} index: {}\n", keychain, index));
+    }
+    out.push_str(&format!("transactions: {}\n", summaries.len()));
+    out.push_str(&format!("confirmed balance: {} sat\n", balance - pending));
+    out.push_str(&format!("unconfirmed balance: {:+} sat\n", pending));
+    out
+}
+
+/// Render the rollup and wallet totals as JSON.
+pub fn report_json(result: &ScanResult, summaries: &[TxSummary]) -> serde_json::Value {
+    let balance: i64 = summaries.iter().map(|s| s.net).sum();
+    let pending: i64 = summaries.iter().filter(|s| s.height.is_none()).map(|s| s.net).sum();
+    serde_json::json!({
+        "transactions": summaries,
+        "last_active": result.last_active,
+        "confirmed_balance": balance - pending,
+        "unconfirmed_balance": pending,
+    })
+}
@@ -0,0 +1,218 @@
+//! Explorer-style detail pages rendered in the terminal pager.
+
+use std::fmt::Write as _;
+use std::io::{IsTerminal, Write as _};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bitcoin::{BlockHash, Txid};
+
+use crate::client::Client;
+use crate::error;
+use crate::oob;
+use crate::timefmt;
+
+/// A block referenced either by its hash or by its height in the best chain.
+#[derive(Debug, Clone, Copy)]
+pub enum BlockRef {
+    Hash(BlockHash),
+    Height(u32),
+}
+
+impl FromStr for BlockRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(height) = s.parse::<u32>() {
+            return Ok(BlockRef::Height(height));
+        }
+        let hash = s
+            .parse::<BlockHash>()
+            .map_err(|_| anyhow!("expected block hash or height"))?;
+        Ok(BlockRef::Hash(hash))
+    }
+}
+
+impl BlockRef {
+    /// Resolve to a block hash, looking up the hash at height if necessary.
+    pub async fn resolve(self, client: &Client) -> anyhow::Result<BlockHash> {
+        match self {
+            BlockRef::Hash(hash) => Ok(hash),
+            BlockRef::Height(height) => Ok(client.get_block_hash(height).await?),
+        }
+    }
+}
+
+/// Render the detail page of a block.
+pub async fn block_page(client: &Client, block: BlockRef) -> anyhow::Result<String> {
+    let hash = block.resolve(client).await?;
+    let info = client.get_block_info(&hash).await?;
+    let status = client.get_block_status(&hash).await?;
+    let txids = client.get_block_txids(&hash).await?;
+
+    let mut page = String::new();
+    heading(&mut page, &format!("Block {}", info.height));
+    field(&mut page, "Hash", info.id);
+    let chain = if status.in_best_chain {
+        "in best chain"
+    } else {
+        "stale"
+    };
+    field(&mut page, "Status", chain);
+    field(&mut page, "Previous", opt(info.previousblockhash));
+    field(&mut page, "Next", opt(status.next_best));
+
+    heading(&mut page, "Header");
+    field(&mut page, "Version", format!("{:#x}", info.version.to_consensus()));
+    field(&mut page, "Merkle root", info.merkle_root);
+    field(&mut page, "Timestamp", timefmt::format(info.timestamp));
+    field(&mut page, "Median time", timefmt::format(info.mediantime));
+    field(&mut page, "Bits", format!("{:#010x}", info.bits.to_consensus()));
+    field(&mut page, "Difficulty", info.difficulty);
+    field(&mut page, "Nonce", info.nonce);
+
+    heading(&mut page, "Stats");
+    field(&mut page, "Transactions", info.tx_count);
+    field(&mut page, "Size", format!("{} B", info.size));
+    field(&mut page, "Weight", format!("{} WU", info.weight));
+    field(&mut page, "Virtual size", format!("{} vB", info.weight.div_ceil(4)));
+
+    heading(&mut page, &format!("Transactions ({})", txids.len()));
+    for (i, txid) in txids.iter().enumerate() {
+        let _ = writeln!(page, "{:>6}  {}", i, txid);
+    }
+
+    Ok(page)
+}
+
+/// Render the detail page of a transaction.
+pub async fn tx_page(client: &Client, txid: Txid) -> anyhow::Result<String> {
+    let tx = client
+        .get_tx_info(&txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", txid)))?;
+    let outspends = client.get_tx_outspends(&txid).await?;
+    let vsize = tx.weight.div_ceil(4);
+
+    let mut page = String::new();
+    heading(&mut page, "Transaction");
+    field(&mut page, "Txid", tx.txid);
+    match (tx.status.block_height, tx.status.block_hash) {
+        (Some(height), Some(hash)) if tx.status.confirmed => {
+            field(&mut page, "Status", format!("confirmed at height {}", height));
+            field(&mut page, "Block", hash);
+            field(&mut page, "Block time", opt(tx.status.block_time.map(timefmt::format)));
+        }
+        _ => field(&mut page, "Status", "unconfirmed"),
+    }
+
+    heading(&mut page, "Summary");
+    field(&mut page, "Version", tx.version);
+    field(&mut page, "Locktime", tx.locktime);
+    field(&mut page, "Size", format!("{} B", tx.size));
+    field(&mut page, "Virtual size", format!("{} vB", vsize));
+    field(&mut page, "Weight", format!("{} WU", tx.weight));
+    field(&mut page, "Fee", format!("{} sat", tx.fee));
+    let feerate = tx.fee as f64 / vsize as f64;
+    field(&mut page, "Feerate", format!("{:.2} sat/vB", feerate));
+
+    if let Some(assessment) = oob::assess(client, &tx, &outspends).await? {
+        heading(&mut page, "Block feerates");
+        let floor = assessment.block.floor;
+        field(&mut page, "Floor", format!("{:.2} sat/vB (10th percentile)", floor));
+        field(&mut page, "Median", format!("{:.2} sat/vB", assessment.block.median));
+        let effective = match assessment.children {
+            0 => format!("{:.2} sat/vB", assessment.effective),
+            n => format!("{:.2} sat/vB (with {} child(ren))", assessment.effective, n),
+        };
+        field(&mut page, "Effective", effective);
+        if assessment.is_out_of_band() {
+            let flag =
+                format!("likely out of band, {:.2} sat/vB below the floor", assessment.gap());
+            field(&mut page, "Flag", flag);
+        }
+    }
+
+    heading(&mut page, &format!("Inputs ({})", tx.vin.len()));
+    for (i, vin) in tx.vin.iter().enumerate() {
+        if vin.is_coinbase {
+            let _ = writeln!(page, "{:>4}  coinbase", i);
+        } else {
+            let _ = writeln!(page, "{:>4}  {}:{}", i, vin.txid, vin.vout);
+        }
+        if let Some(prevout) = &vin.prevout {
+            let _ = writeln!(page, "      {} sat", prevout.value);
+            let _ = writeln!(page, "      {}", prevout.scriptpubkey.to_asm_string());
+        }
+        let _ = writeln!(
+            page,
+            "      sequence {:#010x}, {} witness item(s)",
+            vin.sequence,
+            vin.witness.len()
+        );
+    }
+
+    heading(&mut page, &format!("Outputs ({})", tx.vout.len()));
+    for (i, vout) in tx.vout.iter().enumerate() {
+        let spent = match outspends.get(i) {
+            Some(status) if status.spent => match status.txid {
+                Some(txid) => format!("spent by {}", txid),
+                None => "spent".to_string(),
+            },
+            Some(_) => "unspent".to_string(),
+            None => "unknown".to_string(),
+        };
+        let _ = writeln!(page, "{:>4}  {} sat  ({})", i, vout.value, spent);
+        let _ = writeln!(page, "      {}", vout.scriptpubkey.to_asm_string());
+    }
+
+    Ok(page)
+}
+
+/// Display `page` through the user's pager when attached to a terminal, otherwise print it.
+///
+/// The pager is taken from `$PAGER`, falling back to `less -R`. If the pager can't be spawned
+/// the page is printed directly.
+pub fn page(page: &str) -> anyhow::Result<()> {
+    if !std::io::stdout().is_terminal() {
+        print!("{}", page);
+        return Ok(());
+    }
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        print!("{}", page);
+        return Ok(());
+    };
+    let child = Command::new(program).args(parts).stdin(Stdio::piped()).spawn();
+    let Ok(mut child) = child else {
+        print!("{}", page);
+        return Ok(());
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything, so a broken pipe is fine.
+        let _ = stdin.write_all(page.as_bytes());
+    }
+    child.wait()?;
+
+    Ok(())
+}
+
+fn heading(page: &mut String, title: &str) {
+    if !page.is_empty() {
+        page.push('\n');
+    }
+    let _ = writeln!(page, "{}", title);
+    let _ = writeln!(page, "{}", "-".repeat(title.len()));
+}
+
+fn field(page: &mut String, name: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(page, "{:<14}{}", name, value);
+}
+
+fn opt<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
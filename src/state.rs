@@ -0,0 +1,249 @@
+//! Small persistence layer for state carried between runs.
+//!
+//! Each kind of state is a JSON file in the state directory.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use bitcoin::{BlockHash, Txid};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::cache;
+use crate::config;
+
+/// Path of the state file `name` in the state directory.
+pub fn path(name: &str) -> anyhow::Result<PathBuf> {
+    Ok(config::state_dir()?.join(name))
+}
+
+/// Load the state file `name`, returning the default if it doesn't exist yet.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> anyhow::Result<T> {
+    let path = path(name)?;
+    match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Save `state` to the state file `name`.
+///
+/// The file is written in full to a temporary file first and then renamed over the old one, so
+/// an interrupted write never leaves a truncated state file behind.
+pub fn save<T: Serialize>(name: &str, state: &T) -> anyhow::Result<()> {
+    let path = path(name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(state)?)
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Last seen tip of each backend, keyed by backend url.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TipState {
+    /// The tips.
+    pub tips: BTreeMap<String, Tip>,
+}
+
+impl TipState {
+    /// Name of the tip state file.
+    pub const FILE: &'static str = "tip.json";
+}
+
+/// A block at the tip of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tip {
+    /// The block height.
+    pub height: u32,
+    /// The block hash.
+    pub hash: BlockHash,
+}
+
+/// Transactions already reported by `watch-address`, keyed by backend url, then by address.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchState {
+    /// The watched addresses of each backend.
+    pub watches: BTreeMap<String, BTreeMap<String, BTreeMap<Txid, SeenTx>>>,
+}
+
+impl WatchState {
+    /// Name of the watch state file.
+    pub const FILE: &'static str = "watch.json";
+}
+
+/// A transaction already reported for a watched address.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SeenTx {
+    /// Height the transaction confirmed at, `None` while it is in the mempool.
+    pub height: Option<u32>,
+    /// Net change to the address balance, in satoshis.
+    pub delta: i64,
+}
+
+/// Bundle directory holding the files of the state directory.
+const STATE: &str = "state";
+
+/// Bundle directory holding the files of the data directory: the tracker database and the fee
+/// log.
+const DATA: &str = "data";
+
+/// Bundle directory holding the response cache.
+const CACHE: &str = "cache";
+
+/// What a bundle holds, or what was restored from one.
+#[derive(Debug, Default)]
+pub struct Bundle {
+    /// Files of the state directory: the tip, the watch list and the REPL history.
+    pub state: usize,
+    /// Files of the data directory: the tracker database and the fee log.
+    pub data: usize,
+    /// Entries of the response cache.
+    pub cache: usize,
+    /// Cache entries in the bundle that weren't restored because no `--cache-dir` was given.
+    pub cache_skipped: usize,
+}
+
+/// What a bundle never holds, for reporting alongside it.
+pub const NOT_BUNDLED: &str = "not bundled: the config file, and header files written by \
+    sync-headers, which live wherever --out put them";
+
+/// Package the state and data directories, and the response cache in `cache_dir` if given,
+/// as a zstd compressed tarball at `bundle`.
+pub fn export(bundle: &Path, cache_dir: Option<&Path>) -> anyhow::Result<Bundle> {
+    let file =
+        File::create(bundle).with_context(|| format!("failed to create {}", bundle.display()))?;
+    let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+    let mut contents = Bundle {
+        state: append_files(&mut tar, &config::state_dir()?, STATE)?,
+        data: append_files(&mut tar, &config::data_dir()?, DATA)?,
+        ..Bundle::default()
+    };
+    if let Some(root) = cache_dir {
+        let dir = cache::dir(root);
+        if dir.exists() {
+            tar.append_dir_all(CACHE, &dir)
+                .with_context(|| format!("failed to bundle {}", dir.display()))?;
+            contents.cache = cache::stats(root)?.0 as usize;
+        }
+    }
+    tar.into_inner()?.finish()?;
+    Ok(contents)
+}
+
+/// Append the files directly in `dir` to `tar` below `prefix`, returning how many.
+fn append_files<W: Write>(
+    tar: &mut tar::Builder<W>,
+    dir: &Path,
+    prefix: &str,
+) -> anyhow::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Leftovers of interrupted writes are not state.
+        if !entry.file_type()?.is_file() || entry.path().extension() == Some("tmp".as_ref()) {
+            continue;
+        }
+        tar.append_path_with_name(entry.path(), Path::new(prefix).join(entry.file_name()))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Unpack a bundle created by [`export`] into the state and data directories, and its cache
+/// entries into `cache_dir` if given.
+///
+/// Existing state and data files are only replaced if `force` is set. Cache entries never
+/// change, so existing ones are kept. Bundles of older versions, holding only the state
+/// directory at their root, are restored into the state directory.
+pub fn import(bundle: &Path, force: bool, cache_dir: Option<&Path>) -> anyhow::Result<Bundle> {
+    let (state_dir, data_dir) = (config::state_dir()?, config::data_dir()?);
+    let open = || -> anyhow::Result<_> {
+        let file =
+            File::open(bundle).with_context(|| format!("failed to open {}", bundle.display()))?;
+        Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+    };
+    // Where an entry of the bundle goes, `None` for cache entries without a cache directory.
+    let destination = |path: &Path| -> anyhow::Result<Option<(&'static str, PathBuf)>> {
+        let mut components = path.components();
+        if !components.clone().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow!("{} is not a valid bundle entry", path.display()));
+        }
+        let first = components.next().map(|c| c.as_os_str());
+        let rest = components.as_path();
+        Ok(match first.and_then(|c| c.to_str()) {
+            Some(STATE) => Some((STATE, state_dir.join(rest))),
+            Some(DATA) => Some((DATA, data_dir.join(rest))),
+            Some(CACHE) => cache_dir.map(|root| (CACHE, cache::dir(root).join(rest))),
+            _ if rest.as_os_str().is_empty() => Some((STATE, state_dir.join(path))),
+            _ => return Err(anyhow!("{} is not a valid bundle entry", path.display())),
+        })
+    };
+
+    if !force {
+        for entry in open()?.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            if let Some((kind, path)) = destination(&entry.path()?)? {
+                if kind != CACHE && path.exists() {
+                    return Err(anyhow!(
+                        "{} already exists, use --force to replace it",
+                        path.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut restored = Bundle::default();
+    for entry in open()?.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let Some((kind, path)) = destination(&entry.path()?)? else {
+            restored.cache_skipped += 1;
+            continue;
+        };
+        if kind == CACHE && path.exists() {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry
+            .unpack(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        match kind {
+            STATE => restored.state += 1,
+            DATA => restored.data += 1,
+            _ => restored.cache += 1,
+        }
+    }
+    Ok(restored)
+}
+
+/// Render what `bundle` holds, e.g. `2 state files, 1 data file and 40 cache entries`.
+pub fn describe(bundle: &Bundle) -> String {
+    let plural = |n: usize, what: &str| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" });
+    format!(
+        "{}, {} and {}",
+        plural(bundle.state, "state file"),
+        plural(bundle.data, "data file"),
+        match bundle.cache {
+            1 => "1 cache entry".to_string(),
+            n => format!("{} cache entries", n),
+        }
+    )
+}
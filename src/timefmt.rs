@@ -0,0 +1,93 @@
+//! Rendering of block and transaction timestamps in text output, chosen with `--time-format`.
+//!
+//! Without `--time-format` every renderer keeps showing timestamps the way it did before the
+//! flag existed, UNIX timestamps unless noted otherwise. JSON output always carries UNIX
+//! timestamps.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+
+/// How timestamps are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeFormat {
+    /// Seconds since the UNIX epoch.
+    Unix,
+    /// RFC 3339 in UTC, e.g. `2024-04-20T00:09:27Z`.
+    Rfc3339,
+    /// The local date and time with the UTC offset.
+    Local,
+}
+
+static FORMAT: OnceLock<TimeFormat> = OnceLock::new();
+
+/// Show timestamps in `format` from now on. Only the first call has an effect.
+pub fn init(format: TimeFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Format the UNIX timestamp `time` as chosen with [`init`], as is if nothing was.
+pub fn format(time: u64) -> String {
+    format_as(time, *FORMAT.get().unwrap_or(&TimeFormat::Unix))
+}
+
+/// Format the UNIX timestamp `time` as chosen with [`init`], in RFC 3339 if nothing was, for
+/// the renderers that always showed dates.
+pub fn format_rfc3339(time: u64) -> String {
+    format_as(time, *FORMAT.get().unwrap_or(&TimeFormat::Rfc3339))
+}
+
+/// Format the UNIX timestamp `time` as chosen with [`init`], in local time if nothing was.
+pub fn format_local(time: u64) -> String {
+    format_as(time, *FORMAT.get().unwrap_or(&TimeFormat::Local))
+}
+
+fn format_as(time: u64, format: TimeFormat) -> String {
+    match format {
+        TimeFormat::Unix => time.to_string(),
+        TimeFormat::Rfc3339 => format_time(time),
+        TimeFormat::Local => DateTime::from_timestamp(time as i64, 0)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %:z").to_string())
+            .unwrap_or_else(|| time.to_string()),
+    }
+}
+
+/// A UNIX timestamp whose `Debug` output is formatted as chosen with [`init`], for dumps of
+/// API types.
+#[derive(Clone, Copy)]
+pub struct Time(pub u64);
+
+impl fmt::Debug for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match FORMAT.get() {
+            None | Some(TimeFormat::Unix) => fmt::Debug::fmt(&self.0, f),
+            Some(_) => f.write_str(&format(self.0)),
+        }
+    }
+}
+
+/// Format UNIX time `time` as an RFC 3339 UTC timestamp.
+pub fn format_time(time: u64) -> String {
+    let (days, secs) = (time / 86400, time % 86400);
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
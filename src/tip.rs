@@ -0,0 +1,215 @@
+//! Following the chain tip.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use bitcoin::BlockHash;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Client;
+use crate::fees;
+use crate::progress::Progress;
+use crate::state::Tip;
+use crate::timefmt;
+
+/// Poll the backend every `interval` until the tip reaches `height`, or the next block if no
+/// height is given, returning the new tip height and hash.
+///
+/// Gives up with an error once `timeout` has elapsed. Progress is reported as the tip height
+/// against the target height.
+pub async fn wait_for_block(
+    client: &Client,
+    height: Option<u32>,
+    interval: Duration,
+    timeout: Option<Duration>,
+    progress: &Progress,
+) -> anyhow::Result<(u32, BlockHash)> {
+    let wait = async {
+        let target = match height {
+            Some(height) => height,
+            None => client.get_height().await? + 1,
+        };
+        progress.set_total(target.into());
+        loop {
+            let tip = client.get_height().await?;
+            progress.set(tip.into());
+            if tip >= target {
+                progress.finish();
+                let hash = client.get_block_hash(tip).await?;
+                return anyhow::Ok((tip, hash));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    };
+
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| anyhow!("timed out waiting for block"))?,
+        None => wait.await,
+    }
+}
+
+/// The tip of the best chain with the time it was mined.
+#[derive(Debug, Serialize)]
+pub struct TipInfo {
+    /// The block height.
+    pub height: u32,
+    /// The block hash.
+    pub hash: BlockHash,
+    /// UNIX timestamp from the block header.
+    pub time: u32,
+}
+
+/// Fetch the tip from the `/blocks/tip` endpoints and the time from its header.
+///
+/// The height is read after the hash and is retried if a block arrived in between, so the two
+/// always describe the same block.
+pub async fn tip_info(client: &Client) -> anyhow::Result<TipInfo> {
+    loop {
+        let hash = client.get_tip_hash().await?;
+        let height = client.get_height().await?;
+        if client.get_tip_hash().await? != hash {
+            continue;
+        }
+        let header = client.get_header_by_hash(&hash).await?;
+        return Ok(TipInfo {
+            height,
+            hash,
+            time: header.time,
+        });
+    }
+}
+
+/// Poll the backend every `interval` until its tip is no longer `hash`.
+pub async fn wait_for_change(
+    client: &Client,
+    hash: &BlockHash,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    while client.get_tip_hash().await? == *hash {
+        tokio::time::sleep(interval).await;
+    }
+    Ok(())
+}
+
+/// Render `tip` for the terminal.
+pub fn report(tip: &TipInfo) -> String {
+    format!(
+        "height: {}\nhash: {}\ntime: {} ({} ago)\n",
+        tip.height,
+        tip.hash,
+        timefmt::format_rfc3339(tip.time.into()),
+        format_age(fees::now().saturating_sub(tip.time.into()))
+    )
+}
+
+/// Maximum number of blocks to walk back looking for a fork point.
+const MAX_REORG_DEPTH: u32 = 100;
+
+/// The result of comparing a previously seen tip with the current best chain.
+#[derive(Debug)]
+pub enum TipChange {
+    /// No tip was recorded before.
+    First,
+    /// The previous tip is still in the best chain, `blocks` below the current tip.
+    Extended { blocks: u32 },
+    /// The previous tip was reorganized out of the best chain.
+    Reorg {
+        /// Number of blocks of the old chain that were replaced.
+        depth: u32,
+        /// The last block common to the old and new chain.
+        fork_point: Tip,
+    },
+}
+
+/// The timestamp of a block summary, present on both Bitcoin and Liquid backends.
+#[derive(Deserialize)]
+struct BlockTime {
+    timestamp: u64,
+}
+
+/// Check that the backend's tip was mined within the last `max_age` seconds.
+///
+/// A stale tip is reported as a warning on stderr, or as an error if `abort` is set, so that
+/// nothing silently runs against a backend that stopped following the chain.
+pub async fn check_staleness(client: &Client, max_age: u64, abort: bool) -> anyhow::Result<()> {
+    let hash = client.get_tip_hash().await?;
+    let age = fees::now().saturating_sub(block_time(client, &hash).await?);
+    if age <= max_age {
+        return Ok(());
+    }
+
+    let message = format!(
+        "the backend's tip {} was mined {} ago, the backend may have stalled",
+        hash,
+        format_age(age)
+    );
+    if abort {
+        return Err(anyhow!(message));
+    }
+    eprintln!("WARNING: {} (skip this check with --no-staleness-check)", message);
+    Ok(())
+}
+
+/// The timestamp of the block `hash`.
+pub async fn block_time(client: &Client, hash: &BlockHash) -> anyhow::Result<u64> {
+    let block: BlockTime = client.get_json(&format!("/block/{}", hash)).await?;
+    Ok(block.timestamp)
+}
+
+/// Format `secs` as days, hours and minutes, e.g. `1d 2h 5m`.
+pub fn format_age(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Fetch the current tip of the best chain.
+pub async fn current_tip(client: &Client) -> anyhow::Result<Tip> {
+    let height = client.get_height().await?;
+    let hash = client.get_block_hash(height).await?;
+    Ok(Tip { height, hash })
+}
+
+/// Work out whether `previous` is still in the best chain, walking back through its ancestors
+/// to find the fork point if it isn't.
+pub async fn compare(
+    client: &Client,
+    previous: Option<Tip>,
+    current: Tip,
+) -> anyhow::Result<TipChange> {
+    let Some(previous) = previous else {
+        return Ok(TipChange::First);
+    };
+    if client.get_block_status(&previous.hash).await?.in_best_chain {
+        return Ok(TipChange::Extended {
+            blocks: current.height.saturating_sub(previous.height),
+        });
+    }
+
+    let mut hash = previous.hash;
+    for _ in 0..MAX_REORG_DEPTH {
+        let parent = client.get_header_by_hash(&hash).await?.prev_blockhash;
+        let status = client.get_block_status(&parent).await?;
+        if let (true, Some(height)) = (status.in_best_chain, status.height) {
+            return Ok(TipChange::Reorg {
+                depth: previous.height - height,
+                fork_point: Tip {
+                    height,
+                    hash: parent,
+                },
+            });
+        }
+        hash = parent;
+    }
+
+    Err(anyhow!(
+        "no fork point found within {} blocks of {}",
+        MAX_REORG_DEPTH,
+        previous.hash
+    ))
+}
@@ -0,0 +1,330 @@
+//! SQLite store of tracked wallets.
+//!
+//! Only the wallet's side of each transaction is stored: the outputs paying to its scripts
+//! and which transaction spent them. Balances and the net effect of transactions are derived
+//! from those.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use bitcoin::{ScriptBuf, Txid};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::scan::Keychain;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS wallets (
+    name TEXT PRIMARY KEY,
+    descriptor TEXT NOT NULL,
+    change TEXT
+);
+CREATE TABLE IF NOT EXISTS scripts (
+    wallet TEXT NOT NULL REFERENCES wallets(name) ON DELETE CASCADE,
+    script BLOB NOT NULL,
+    keychain TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    used INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (wallet, script)
+);
+CREATE TABLE IF NOT EXISTS txs (
+    wallet TEXT NOT NULL REFERENCES wallets(name) ON DELETE CASCADE,
+    txid TEXT NOT NULL,
+    height INTEGER,
+    time INTEGER,
+    PRIMARY KEY (wallet, txid)
+);
+CREATE TABLE IF NOT EXISTS outputs (
+    wallet TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    vout INTEGER NOT NULL,
+    value INTEGER NOT NULL,
+    script BLOB NOT NULL,
+    spent_by TEXT,
+    PRIMARY KEY (wallet, txid, vout),
+    FOREIGN KEY (wallet, txid) REFERENCES txs(wallet, txid) ON DELETE CASCADE
+);
+";
+
+/// A tracked wallet.
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    /// Name the wallet is tracked under.
+    pub name: String,
+    /// External (receive) descriptor, or a multipath descriptor.
+    pub descriptor: String,
+    /// Internal (change) descriptor.
+    pub change: Option<String>,
+}
+
+/// A derived script of a wallet.
+#[derive(Debug, Clone)]
+pub struct StoredScript {
+    /// The script.
+    pub script: ScriptBuf,
+    /// The keychain it was derived from.
+    pub keychain: Keychain,
+    /// The derivation index.
+    pub index: u32,
+    /// Whether it has history.
+    pub used: bool,
+}
+
+/// A transaction of a wallet.
+#[derive(Debug, Clone)]
+pub struct StoredTx {
+    /// The transaction id.
+    pub txid: Txid,
+    /// Confirmation height, `None` while unconfirmed.
+    pub height: Option<u32>,
+    /// UNIX timestamp of the block, `None` while unconfirmed.
+    pub time: Option<u64>,
+    /// Net change to the wallet balance, in satoshis.
+    pub net: i64,
+}
+
+/// An output paying to a wallet script.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// Index of the output.
+    pub vout: u32,
+    /// Value in satoshis.
+    pub value: u64,
+    /// The script paid to.
+    pub script: ScriptBuf,
+}
+
+/// Handle to the store.
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    /// Open the store at `path`, creating it if needed.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn =
+            Connection::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Start tracking `wallet`.
+    pub fn add_wallet(&self, wallet: &Wallet) -> anyhow::Result<()> {
+        if self.wallet(&wallet.name)?.is_some() {
+            return Err(anyhow!("wallet `{}` is already tracked", wallet.name));
+        }
+        self.conn.execute(
+            "INSERT INTO wallets (name, descriptor, change) VALUES (?1, ?2, ?3)",
+            params![wallet.name, wallet.descriptor, wallet.change],
+        )?;
+        Ok(())
+    }
+
+    /// The wallet tracked under `name`.
+    pub fn wallet(&self, name: &str) -> anyhow::Result<Option<Wallet>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT name, descriptor, change FROM wallets WHERE name = ?1",
+                params![name],
+                |row| {
+                    Ok(Wallet {
+                        name: row.get(0)?,
+                        descriptor: row.get(1)?,
+                        change: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// The derived scripts of `wallet`.
+    pub fn scripts(&self, wallet: &str) -> anyhow::Result<Vec<StoredScript>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT script, keychain, idx, used FROM scripts WHERE wallet = ?1 ORDER BY keychain, idx",
+        )?;
+        let rows = stmt.query_map(params![wallet], |row| {
+            Ok((
+                row.get::<_, Vec<u8>>(0)?,
+                row.get::<_, String>(1)?,
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (script, keychain, index, used) = row?;
+            Ok(StoredScript {
+                script: ScriptBuf::from_bytes(script),
+                keychain: parse_keychain(&keychain)?,
+                index,
+                used,
+            })
+        })
+        .collect()
+    }
+
+    /// Store a derived script of `wallet`, or update whether it is used.
+    pub fn put_script(&self, wallet: &str, script: &StoredScript) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO scripts (wallet, script, keychain, idx, used) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (wallet, script) DO UPDATE SET used = excluded.used",
+            params![
+                wallet,
+                script.script.as_bytes(),
+                script.keychain.to_string(),
+                script.index,
+                script.used
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The confirmation height of each stored transaction of `wallet`.
+    pub fn heights(&self, wallet: &str) -> anyhow::Result<Vec<(Txid, Option<u32>)>> {
+        let mut stmt = self.conn.prepare("SELECT txid, height FROM txs WHERE wallet = ?1")?;
+        let rows =
+            stmt.query_map(params![wallet], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?;
+        rows.map(|row| {
+            let (txid, height) = row?;
+            Ok((txid.parse()?, height))
+        })
+        .collect()
+    }
+
+    /// Store a transaction of `wallet` with its outputs paying to the wallet, or update its
+    /// confirmation.
+    pub fn put_tx(
+        &self,
+        wallet: &str,
+        txid: &Txid,
+        height: Option<u32>,
+        time: Option<u64>,
+        outputs: &[Output],
+    ) -> anyhow::Result<()> {
+        let txid = txid.to_string();
+        self.conn.execute(
+            "INSERT INTO txs (wallet, txid, height, time) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (wallet, txid) DO UPDATE SET height = excluded.height, time = excluded.time",
+            params![wallet, txid, height, time.map(|t| t as i64)],
+        )?;
+        for output in outputs {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO outputs (wallet, txid, vout, value, script)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    wallet,
+                    txid,
+                    output.vout,
+                    output.value as i64,
+                    output.script.as_bytes()
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Record that the output `vout` of `txid` was spent by `spender`.
+    pub fn spend(
+        &self,
+        wallet: &str,
+        txid: &Txid,
+        vout: u32,
+        spender: &Txid,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE outputs SET spent_by = ?4 WHERE wallet = ?1 AND txid = ?2 AND vout = ?3",
+            params![wallet, txid.to_string(), vout, spender.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a transaction of `wallet` that left the mempool, releasing the outputs it spent.
+    pub fn remove_tx(&self, wallet: &str, txid: &Txid) -> anyhow::Result<()> {
+        let txid = txid.to_string();
+        self.conn.execute(
+            "UPDATE outputs SET spent_by = NULL WHERE wallet = ?1 AND spent_by = ?2",
+            params![wallet, txid],
+        )?;
+        self.conn.execute(
+            "DELETE FROM txs WHERE wallet = ?1 AND txid = ?2",
+            params![wallet, txid],
+        )?;
+        Ok(())
+    }
+
+    /// The confirmed balance of `wallet` and the change to it from unconfirmed transactions,
+    /// in satoshis.
+    pub fn balance(&self, wallet: &str) -> anyhow::Result<(i64, i64)> {
+        let (confirmed, total) = self.conn.query_row(
+            "SELECT
+                 COALESCE(SUM(CASE WHEN t.height IS NOT NULL
+                     AND (o.spent_by IS NULL OR s.height IS NULL) THEN o.value END), 0),
+                 COALESCE(SUM(CASE WHEN o.spent_by IS NULL THEN o.value END), 0)
+             FROM outputs o
+             JOIN txs t ON t.wallet = o.wallet AND t.txid = o.txid
+             LEFT JOIN txs s ON s.wallet = o.wallet AND s.txid = o.spent_by
+             WHERE o.wallet = ?1",
+            params![wallet],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+        Ok((confirmed, total - confirmed))
+    }
+
+    /// The transactions of `wallet`, newest first with unconfirmed ones on top.
+    pub fn txs(&self, wallet: &str) -> anyhow::Result<Vec<StoredTx>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.txid, t.height, t.time,
+                 COALESCE((SELECT SUM(value) FROM outputs
+                     WHERE wallet = t.wallet AND txid = t.txid), 0)
+                 - COALESCE((SELECT SUM(value) FROM outputs
+                     WHERE wallet = t.wallet AND spent_by = t.txid), 0)
+             FROM txs t
+             WHERE t.wallet = ?1
+             ORDER BY t.height IS NOT NULL, t.height DESC, t.txid",
+        )?;
+        let rows = stmt.query_map(params![wallet], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get(3)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (txid, height, time, net) = row?;
+            Ok(StoredTx {
+                txid: txid.parse()?,
+                height,
+                time: time.map(|t| t as u64),
+                net,
+            })
+        })
+        .collect()
+    }
+
+    /// Run `f` in a transaction, committing only if it succeeds.
+    pub fn atomically<T>(&mut self, f: impl FnOnce(&Db) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        self.conn.execute_batch("BEGIN")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.conn.execute_batch("ROLLBACK")?;
+                Err(e)
+            }
+        }
+    }
+}
+
+fn parse_keychain(s: &str) -> anyhow::Result<Keychain> {
+    match s {
+        "external" => Ok(Keychain::External),
+        "internal" => Ok(Keychain::Internal),
+        _ => Err(anyhow!("invalid keychain `{}` in store", s)),
+    }
+}
@@ -0,0 +1,25 @@
+//! Watch-only wallet tracking.
+//!
+//! Wallets are added by descriptor and synced incrementally into a SQLite store in the data
+//! directory, so that balances and transactions can be listed offline and each sync only
+//! fetches what changed.
+
+mod db;
+mod sync;
+
+use std::path::PathBuf;
+
+pub use db::{Db, Wallet};
+pub use sync::sync;
+
+use crate::config;
+
+/// Path of the tracker store in the data directory.
+pub fn path() -> anyhow::Result<PathBuf> {
+    Ok(config::data_dir()?.join("tracker.sqlite3"))
+}
+
+/// Open the tracker store.
+pub fn open() -> anyhow::Result<Db> {
+    Db::open(&path()?)
+}
@@ -0,0 +1,188 @@
+//! Incremental sync of a tracked wallet through the scripthash endpoints.
+
+use std::collections::{HashMap, HashSet};
+
+use bitcoin::{Script, ScriptBuf, Txid};
+use esplora_client::Tx;
+use futures::{StreamExt, TryStreamExt};
+
+use super::db::{Db, Output, StoredScript, Wallet};
+use crate::client::Client;
+use crate::progress::Progress;
+use crate::scan::{self, CHAIN_PAGE_SIZE};
+
+/// What a sync changed in the store.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// Number of scripts derived so far.
+    pub scripts: usize,
+    /// Number of transactions seen for the first time.
+    pub new_txs: usize,
+    /// Number of transactions that confirmed since the last sync.
+    pub confirmed: usize,
+    /// Number of unconfirmed transactions that left the mempool.
+    pub dropped: usize,
+}
+
+/// Bring the store up to date with the history of `wallet`, requesting up to `parallel` script
+/// histories at once.
+///
+/// Every known script is checked again, but its confirmed history is only paged back as far as
+/// the transactions already stored. New scripts are derived until `gap_limit` consecutive ones
+/// have no history.
+pub async fn sync(
+    client: &Client,
+    db: &mut Db,
+    wallet: &Wallet,
+    gap_limit: u32,
+    parallel: u32,
+    progress: &Progress,
+) -> anyhow::Result<SyncSummary> {
+    let keychains = scan::keychains(&wallet.descriptor, wallet.change.as_deref())?;
+    let stored: HashMap<ScriptBuf, StoredScript> = db
+        .scripts(&wallet.name)?
+        .into_iter()
+        .map(|s| (s.script.clone(), s))
+        .collect();
+    let known: HashMap<Txid, Option<u32>> = db.heights(&wallet.name)?.into_iter().collect();
+
+    let mut scripts = vec![];
+    let mut txs: HashMap<Txid, Tx> = HashMap::new();
+    for (keychain, descriptor) in &keychains {
+        let (gap_limit, parallel) = if descriptor.has_wildcard() {
+            (gap_limit.max(1), parallel.max(1))
+        } else {
+            (1, 1)
+        };
+        let last_stored = stored
+            .values()
+            .filter(|s| s.keychain == *keychain)
+            .map(|s| s.index)
+            .max();
+        let mut next = 0;
+        let mut unused = 0;
+
+        while unused < gap_limit || last_stored.is_some_and(|last| next <= last) {
+            let batch = (next..next + parallel)
+                .map(|i| Ok((i, descriptor.at_derivation_index(i)?.script_pubkey())))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let histories: Vec<Vec<Tx>> = futures::stream::iter(&batch)
+                .map(|(_, spk)| async {
+                    let history = history(client, spk, &known).await;
+                    progress.inc(1);
+                    history
+                })
+                .buffered(parallel as usize)
+                .try_collect()
+                .await?;
+
+            for ((index, spk), history) in batch.into_iter().zip(histories) {
+                let used = !history.is_empty() || stored.get(&spk).is_some_and(|s| s.used);
+                if used {
+                    unused = 0;
+                } else {
+                    unused += 1;
+                }
+                for tx in history {
+                    txs.insert(tx.txid, tx);
+                }
+                scripts.push(StoredScript {
+                    script: spk,
+                    keychain: *keychain,
+                    index,
+                    used,
+                });
+            }
+            next += parallel;
+            if !descriptor.has_wildcard() {
+                break;
+            }
+        }
+    }
+    progress.finish();
+
+    // Unconfirmed transactions missing from the histories either confirmed deeper than the
+    // first page or left the mempool.
+    let mut missing = vec![];
+    for (txid, _) in known.iter().filter(|(_, h)| h.is_none()) {
+        if !txs.contains_key(txid) {
+            missing.push((*txid, client.get_tx_info(txid).await?));
+        }
+    }
+
+    let mut summary = SyncSummary {
+        scripts: scripts.len(),
+        ..Default::default()
+    };
+    let spks: HashSet<&Script> = scripts.iter().map(|s| s.script.as_script()).collect();
+    db.atomically(|db| {
+        for script in &scripts {
+            db.put_script(&wallet.name, script)?;
+        }
+        for tx in txs.values().chain(missing.iter().filter_map(|(_, tx)| tx.as_ref())) {
+            let height = tx.status.block_height.filter(|_| tx.status.confirmed);
+            match known.get(&tx.txid) {
+                None => summary.new_txs += 1,
+                Some(None) if height.is_some() => summary.confirmed += 1,
+                _ => {}
+            }
+            let outputs: Vec<Output> = tx
+                .vout
+                .iter()
+                .enumerate()
+                .filter(|(_, vout)| spks.contains(vout.scriptpubkey.as_script()))
+                .map(|(i, vout)| Output {
+                    vout: i as u32,
+                    value: vout.value,
+                    script: vout.scriptpubkey.clone(),
+                })
+                .collect();
+            let time = tx.status.block_time.filter(|_| tx.status.confirmed);
+            db.put_tx(&wallet.name, &tx.txid, height, time, &outputs)?;
+        }
+        // Spends are recorded once every output is stored, as a spending transaction may come
+        // before the transaction it spends from.
+        for tx in txs.values() {
+            for vin in &tx.vin {
+                if vin
+                    .prevout
+                    .as_ref()
+                    .is_some_and(|p| spks.contains(p.scriptpubkey.as_script()))
+                {
+                    db.spend(&wallet.name, &vin.txid, vin.vout, &tx.txid)?;
+                }
+            }
+        }
+        for (txid, tx) in &missing {
+            if tx.is_none() {
+                db.remove_tx(&wallet.name, txid)?;
+                summary.dropped += 1;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(summary)
+}
+
+/// Fetch the history of `script`, paging back through confirmed transactions until reaching
+/// one already stored as confirmed.
+async fn history(
+    client: &Client,
+    script: &Script,
+    known: &HashMap<Txid, Option<u32>>,
+) -> anyhow::Result<Vec<Tx>> {
+    let mut history = client.scripthash_txs(script, None).await?;
+    let mut page = history.iter().filter(|tx| tx.status.confirmed).count();
+    while page == CHAIN_PAGE_SIZE
+        && history
+            .last()
+            .is_some_and(|tx| !matches!(known.get(&tx.txid), Some(Some(_))))
+    {
+        let last_seen = history.last().map(|tx| tx.txid);
+        let older = client.scripthash_txs(script, last_seen).await?;
+        page = older.len();
+        history.extend(older);
+    }
+    Ok(history)
+}
@@ -0,0 +1,223 @@
+//! Data embedded in transactions: `OP_RETURN` payloads and envelopes in taproot script-path
+//! witnesses, such as ordinals inscriptions.
+//!
+//! An envelope is the `OP_FALSE OP_IF <protocol> ... OP_ENDIF` pattern, whose fields follow
+//! the ordinals layout: tag and value pairs, then an empty push and the body.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bitcoin::hex::DisplayHex;
+use bitcoin::opcodes::all::{OP_ENDIF, OP_IF, OP_PUSHNUM_1, OP_PUSHNUM_16, OP_PUSHNUM_NEG1};
+use bitcoin::script::Instruction;
+use bitcoin::{Script, Transaction, Txid};
+use serde::Serialize;
+
+/// Envelope tag of the content type field.
+const CONTENT_TYPE_TAG: &[u8] = &[1];
+
+/// Where a piece of data was found.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Location {
+    /// An `OP_RETURN` output, by index.
+    Output(usize),
+    /// An envelope in the witness of an input, by index of the input and of the envelope
+    /// within its witness.
+    Witness { input: usize, envelope: usize },
+}
+
+/// A piece of data embedded in a transaction.
+#[derive(Debug, Serialize)]
+pub struct Data {
+    pub location: Location,
+    /// The envelope protocol, e.g. `ord`, `None` for `OP_RETURN` data.
+    pub protocol: Option<String>,
+    /// The content type declared in the envelope.
+    pub content_type: Option<String>,
+    /// The payload, hex encoded.
+    #[serde(serialize_with = "serialize_hex")]
+    pub payload: Vec<u8>,
+    /// The payload, if it is valid UTF-8.
+    pub text: Option<String>,
+}
+
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&bytes.to_lower_hex_string())
+}
+
+impl Data {
+    fn new(
+        location: Location,
+        protocol: Option<String>,
+        content_type: Option<String>,
+        payload: Vec<u8>,
+    ) -> Self {
+        let text = String::from_utf8(payload.clone()).ok();
+        Data {
+            location,
+            protocol,
+            content_type,
+            payload,
+            text,
+        }
+    }
+
+    /// Name of the file to write the payload of `self` in `txid` to.
+    fn file_name(&self, txid: &Txid) -> String {
+        match self.location {
+            Location::Output(i) => format!("{}-out{}.bin", txid, i),
+            Location::Witness { input, envelope } => format!(
+                "{}-in{}-{}.{}",
+                txid,
+                input,
+                envelope,
+                extension(self.content_type.as_deref())
+            ),
+        }
+    }
+}
+
+/// Extract the `OP_RETURN` payloads and witness envelopes of `tx`.
+pub fn extract(tx: &Transaction) -> Vec<Data> {
+    let mut data = vec![];
+    for (i, output) in tx.output.iter().enumerate() {
+        if output.script_pubkey.is_op_return() {
+            // Everything pushed after the `OP_RETURN`, up to anything that isn't a push.
+            let payload = output
+                .script_pubkey
+                .instructions()
+                .skip(1)
+                .map_while(|ins| push_bytes(ins.ok()?))
+                .flatten()
+                .collect();
+            data.push(Data::new(Location::Output(i), None, None, payload));
+        }
+    }
+    for (i, input) in tx.input.iter().enumerate() {
+        if let Some(leaf) = input.witness.taproot_leaf_script() {
+            let found = envelopes(leaf.script).into_iter().enumerate();
+            data.extend(found.map(|(envelope, (protocol, content_type, body))| {
+                let location = Location::Witness { input: i, envelope };
+                Data::new(location, Some(protocol), content_type, body)
+            }));
+        }
+    }
+    data
+}
+
+/// The bytes an instruction pushes, `None` if it isn't a push.
+fn push_bytes(ins: Instruction) -> Option<Vec<u8>> {
+    match ins {
+        Instruction::PushBytes(bytes) => Some(bytes.as_bytes().to_vec()),
+        Instruction::Op(OP_PUSHNUM_NEG1) => Some(vec![0x81]),
+        Instruction::Op(op)
+            if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&op.to_u8()) =>
+        {
+            Some(vec![op.to_u8() - OP_PUSHNUM_1.to_u8() + 1])
+        }
+        Instruction::Op(_) => None,
+    }
+}
+
+/// The envelopes of `script`, as their protocol, content type and body.
+fn envelopes(script: &Script) -> Vec<(String, Option<String>, Vec<u8>)> {
+    let instructions: Vec<Instruction> = script.instructions().map_while(Result::ok).collect();
+    let mut found = vec![];
+    let mut i = 0;
+    while i + 1 < instructions.len() {
+        let is_start = matches!(
+            (&instructions[i], &instructions[i + 1]),
+            (Instruction::PushBytes(b), Instruction::Op(OP_IF)) if b.is_empty()
+        );
+        if !is_start {
+            i += 1;
+            continue;
+        }
+        let rest = &instructions[i + 2..];
+        let len = rest
+            .iter()
+            .position(|ins| matches!(ins, Instruction::Op(OP_ENDIF)))
+            .unwrap_or(rest.len());
+        i += 2 + len;
+        // An envelope only holds pushes.
+        let pushes: Option<Vec<Vec<u8>>> = rest[..len].iter().map(|ins| push_bytes(*ins)).collect();
+        let Some(pushes) = pushes else { continue };
+        let Some((protocol, fields)) = pushes.split_first() else {
+            continue;
+        };
+
+        let mut content_type = None;
+        let mut body = vec![];
+        let mut fields = fields.iter();
+        while let Some(tag) = fields.next() {
+            if tag.is_empty() {
+                body = fields.by_ref().flatten().copied().collect();
+                break;
+            }
+            let value = fields.next();
+            if tag == CONTENT_TYPE_TAG {
+                content_type = value.map(|v| String::from_utf8_lossy(v).into_owned());
+            }
+        }
+        found.push((String::from_utf8_lossy(protocol).into_owned(), content_type, body));
+    }
+    found
+}
+
+/// File extension for `content_type`.
+fn extension(content_type: Option<&str>) -> &'static str {
+    let essence = content_type.and_then(|c| c.split(';').next()).map(str::trim);
+    match essence {
+        Some("text/plain") => "txt",
+        Some("text/html") => "html",
+        Some("application/json") => "json",
+        Some("image/png") => "png",
+        Some("image/jpeg") => "jpg",
+        Some("image/gif") => "gif",
+        Some("image/webp") => "webp",
+        Some("image/svg+xml") => "svg",
+        _ => "bin",
+    }
+}
+
+/// Write each payload of `data` from `txid` to a file in `dir`, returning the paths written.
+pub fn write_files(dir: &Path, txid: &Txid, data: &[Data]) -> anyhow::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    data.iter()
+        .map(|d| {
+            let path = dir.join(d.file_name(txid));
+            fs::write(&path, &d.payload)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Render `data` for the terminal.
+pub fn report(data: &[Data]) -> String {
+    if data.is_empty() {
+        return "no embedded data\n".to_string();
+    }
+    let mut out = String::new();
+    for d in data {
+        let location = match d.location {
+            Location::Output(i) => format!("output {}: OP_RETURN", i),
+            Location::Witness { input, envelope } => format!(
+                "input {}: envelope {} `{}`",
+                input,
+                envelope,
+                d.protocol.as_deref().unwrap_or_default()
+            ),
+        };
+        let content_type = d.content_type.as_ref().map(|c| format!(", {}", c)).unwrap_or_default();
+        let _ = writeln!(out, "{}{}, {} bytes", location, content_type, d.payload.len());
+        let _ = writeln!(out, "  hex: {}", d.payload.to_lower_hex_string());
+        if let Some(text) = &d.text {
+            let _ = writeln!(out, "  utf8: {}", text.escape_debug());
+        }
+    }
+    out
+}
@@ -0,0 +1,74 @@
+//! Cross-checking the answers of one backend against another.
+
+use std::fmt;
+
+use bitcoin::Txid;
+
+use crate::client::Client;
+
+/// A fact both backends must agree on.
+#[derive(Debug, Clone, Copy)]
+pub enum Check {
+    /// The height of the tip.
+    TipHeight,
+    /// The hash of the block at a height.
+    BlockHash(u32),
+    /// Whether a transaction is known, and the block it confirmed in.
+    Tx(Txid),
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::TipHeight => write!(f, "tip height"),
+            Check::BlockHash(height) => write!(f, "block hash at height {}", height),
+            Check::Tx(txid) => write!(f, "transaction {}", txid),
+        }
+    }
+}
+
+/// A check the backends disagree on, with the answer of each.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The check.
+    pub check: Check,
+    /// The answer of the primary backend.
+    pub primary: String,
+    /// The answer of the backend verified against.
+    pub other: String,
+}
+
+/// Run every check against both backends, returning the ones they disagree on.
+pub async fn verify(
+    primary: &Client,
+    other: &Client,
+    checks: &[Check],
+) -> anyhow::Result<Vec<Mismatch>> {
+    let mut mismatches = vec![];
+    for &check in checks {
+        let (a, b) = futures::try_join!(answer(primary, check), answer(other, check))?;
+        if a != b {
+            mismatches.push(Mismatch {
+                check,
+                primary: a,
+                other: b,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// The answer of `client` to `check`, rendered for comparison and display.
+async fn answer(client: &Client, check: Check) -> anyhow::Result<String> {
+    Ok(match check {
+        Check::TipHeight => client.get_height().await?.to_string(),
+        Check::BlockHash(height) => client.get_block_hash(height).await?.to_string(),
+        Check::Tx(txid) => match client.get_tx_info(&txid).await? {
+            None => "not found".to_string(),
+            Some(tx) => match tx.status.block_hash.filter(|_| tx.status.confirmed) {
+                Some(hash) => format!("confirmed in {}", hash),
+                None => "unconfirmed".to_string(),
+            },
+        },
+    })
+}
@@ -0,0 +1,287 @@
+//! Tailing the transactions of an address, and waiting for an output to be spent.
+//!
+//! Reported transactions are persisted in the state directory, so a restarted watch picks up
+//! where the previous one stopped instead of reporting the same transactions again.
+//! Outputs are watched by polling their spending status, nothing needs to persist.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use bitcoin::{Address, OutPoint, Script, Txid};
+use esplora_client::Error;
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error;
+use crate::fees;
+use crate::scan::{self, CHAIN_PAGE_SIZE};
+use crate::state::{self, SeenTx, WatchState};
+
+/// A change to a transaction of the watched address.
+#[derive(Debug, Serialize)]
+pub struct Event {
+    /// UNIX timestamp of the poll the change was seen in.
+    pub time: u64,
+    /// `mempool`, `confirmed` or `dropped`.
+    pub event: &'static str,
+    /// The transaction id.
+    pub txid: Txid,
+    /// Height the transaction confirmed at.
+    pub height: Option<u32>,
+    /// Net change to the address balance, in satoshis.
+    pub delta: i64,
+    /// Balance of the address as of the poll, including unconfirmed transactions, in satoshis.
+    pub balance: i64,
+}
+
+/// Poll the history of `address` every `interval` and print each new, confirmed or dropped
+/// transaction. Runs until interrupted.
+///
+/// The first watch of an address on a backend only records its current transactions.
+pub async fn watch(
+    client: &Client,
+    url: &str,
+    address: &Address,
+    interval: Duration,
+    json: bool,
+) -> anyhow::Result<()> {
+    let script = address.script_pubkey();
+    let key = address.to_string();
+    let mut state: WatchState = state::load(WatchState::FILE)?;
+    let recorded = state.watches.get(url).and_then(|w| w.get(&key)).cloned();
+    let mut seen = match recorded {
+        Some(seen) => seen,
+        None => {
+            let mut seen = BTreeMap::new();
+            for tx in client.scripthash_txs(&script, None).await? {
+                let height = tx.status.block_height.filter(|_| tx.status.confirmed);
+                let delta = scan::net_effect(&tx, &script);
+                seen.insert(tx.txid, SeenTx { height, delta });
+            }
+            eprintln!("watching {}, {} existing transaction(s) recorded", address, seen.len());
+            record(&mut state, url, &key, &seen)?;
+            seen
+        }
+    };
+
+    loop {
+        // A failed poll is retried in full on the next one, so only keep what it recorded
+        // once its events are printed.
+        let mut next = seen.clone();
+        match poll(client, address, &script, &mut next).await {
+            Ok(events) if !events.is_empty() => {
+                for event in &events {
+                    emit(event, json)?;
+                }
+                seen = next;
+                record(&mut state, url, &key, &seen)?;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("poll failed: {}", e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Persist the transactions reported for address `key` on backend `url`.
+fn record(
+    state: &mut WatchState,
+    url: &str,
+    key: &str,
+    seen: &BTreeMap<Txid, SeenTx>,
+) -> anyhow::Result<()> {
+    state
+        .watches
+        .entry(url.to_string())
+        .or_default()
+        .insert(key.to_string(), seen.clone());
+    state::save(WatchState::FILE, state)
+}
+
+/// Compare the history of `address` against `seen`, recording and returning the changes.
+async fn poll(
+    client: &Client,
+    address: &Address,
+    script: &Script,
+    seen: &mut BTreeMap<Txid, SeenTx>,
+) -> Result<Vec<Event>, Error> {
+    let time = fees::now();
+    let mut txs = client.scripthash_txs(script, None).await?;
+    // Page back until reaching confirmed history that was already reported, in case more than
+    // a page confirmed since the last poll.
+    let mut page = txs.iter().filter(|tx| tx.status.confirmed).count();
+    while page == CHAIN_PAGE_SIZE
+        && txs
+            .last()
+            .is_some_and(|tx| seen.get(&tx.txid).is_none_or(|s| s.height.is_none()))
+    {
+        let last_seen = txs.last().map(|tx| tx.txid);
+        let older = client.scripthash_txs(script, last_seen).await?;
+        page = older.len();
+        txs.extend(older);
+    }
+
+    let mut events = vec![];
+    let mut present = vec![];
+    for tx in &txs {
+        present.push(tx.txid);
+        let height = tx.status.block_height.filter(|_| tx.status.confirmed);
+        let event = match (seen.get(&tx.txid), height) {
+            (None, None) => "mempool",
+            (None, Some(_)) | (Some(SeenTx { height: None, .. }), Some(_)) => "confirmed",
+            _ => continue,
+        };
+        let delta = scan::net_effect(tx, script);
+        seen.insert(tx.txid, SeenTx { height, delta });
+        events.push(Event {
+            time,
+            event,
+            txid: tx.txid,
+            height,
+            delta,
+            balance: 0,
+        });
+    }
+
+    // Unconfirmed transactions missing from the history either confirmed deeper than the
+    // first page or left the mempool.
+    let missing: Vec<(Txid, SeenTx)> = seen
+        .iter()
+        .filter(|(txid, s)| s.height.is_none() && !present.contains(txid))
+        .map(|(&txid, &s)| (txid, s))
+        .collect();
+    for (txid, mut s) in missing {
+        let event = match client.get_tx_info(&txid).await? {
+            None => {
+                seen.remove(&txid);
+                "dropped"
+            }
+            Some(tx) if tx.status.confirmed => {
+                s.height = tx.status.block_height;
+                seen.insert(txid, s);
+                "confirmed"
+            }
+            // Still in the mempool, past the first page of history.
+            Some(_) => continue,
+        };
+        events.push(Event {
+            time,
+            event,
+            txid,
+            height: s.height,
+            delta: s.delta,
+            balance: 0,
+        });
+    }
+
+    if !events.is_empty() {
+        let stats = client.get_address_stats(address).await?;
+        let balance = (stats.chain_stats.funded_txo_sum as i64
+            - stats.chain_stats.spent_txo_sum as i64)
+            + (stats.mempool_stats.funded_txo_sum as i64
+                - stats.mempool_stats.spent_txo_sum as i64);
+        for event in &mut events {
+            event.balance = balance;
+        }
+    }
+    Ok(events)
+}
+
+fn emit(event: &Event, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(event)?);
+        return Ok(());
+    }
+    let height = event.height.map(|h| format!(" at {}", h)).unwrap_or_default();
+    println!(
+        "{:<9} {} {:+} sat{}, balance {} sat",
+        event.event, event.txid, event.delta, height, event.balance
+    );
+    Ok(())
+}
+
+/// The transaction spending a watched output.
+#[derive(Debug, Serialize)]
+pub struct Spend {
+    /// The watched output.
+    pub outpoint: OutPoint,
+    /// The spending transaction.
+    pub txid: Txid,
+    /// The input of the spending transaction that spends the output.
+    pub vin: Option<u64>,
+    /// Height the spending transaction confirmed at, `None` while in the mempool.
+    pub height: Option<u32>,
+}
+
+/// Poll the spending status of `outpoint` every `interval` until it is spent, in the mempool or
+/// in a block.
+///
+/// Fails up front if the output doesn't exist. Failed polls are reported and retried.
+pub async fn wait_for_spend(
+    client: &Client,
+    outpoint: &OutPoint,
+    interval: Duration,
+) -> anyhow::Result<Spend> {
+    let tx = client
+        .get_tx_info(&outpoint.txid)
+        .await?
+        .ok_or_else(|| error::not_found(format!("transaction {}", outpoint.txid)))?;
+    if outpoint.vout as usize >= tx.vout.len() {
+        return Err(error::not_found(format!("output {}", outpoint)));
+    }
+
+    loop {
+        match client.get_output_status(&outpoint.txid, outpoint.vout.into()).await {
+            Ok(Some(status)) if status.spent => {
+                let txid = status
+                    .txid
+                    .ok_or_else(|| anyhow!("backend reports {} spent without a txid", outpoint))?;
+                let height = status.status.and_then(|s| s.block_height.filter(|_| s.confirmed));
+                return Ok(Spend {
+                    outpoint: *outpoint,
+                    txid,
+                    vin: status.vin,
+                    height,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("poll failed: {}", e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Run `command` through `sh -c` to act on `spend`, which it finds in the
+/// `ESPLORA_OUTPOINT`, `ESPLORA_SPENDING_TXID`, `ESPLORA_SPENDING_VIN` and
+/// `ESPLORA_SPENDING_HEIGHT` environment variables. The last two are empty when unknown.
+pub async fn exec(command: &str, spend: &Spend) -> anyhow::Result<()> {
+    let optional = |v: Option<String>| v.unwrap_or_default();
+    let status = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("ESPLORA_OUTPOINT", spend.outpoint.to_string())
+        .env("ESPLORA_SPENDING_TXID", spend.txid.to_string())
+        .env("ESPLORA_SPENDING_VIN", optional(spend.vin.map(|v| v.to_string())))
+        .env(
+            "ESPLORA_SPENDING_HEIGHT",
+            optional(spend.height.map(|h| h.to_string())),
+        )
+        .status()
+        .await
+        .map_err(|e| anyhow!("failed to run `{}`: {}", command, e))?;
+    if !status.success() {
+        return Err(anyhow!("`{}` exited with {}", command, status));
+    }
+    Ok(())
+}
+
+/// Render `spend` for the terminal.
+pub fn report(spend: &Spend) -> String {
+    let input = spend.vin.map(|vin| format!(":{}", vin)).unwrap_or_default();
+    let status = match spend.height {
+        Some(height) => format!("confirmed at {}", height),
+        None => "unconfirmed".to_string(),
+    };
+    format!("{} spent by {}{} ({})\n", spend.outpoint, spend.txid, input, status)
+}